@@ -0,0 +1,10 @@
+#![feature(try_from)]
+
+#[macro_use]
+extern crate nom;
+
+pub mod parser;
+pub mod preprocessor;
+
+pub use parser::keywords::Standard;
+pub use parser::translation_unit::{parse_translation_unit, parse_file, parse_partial, parse_with_options, ParseOptions};
@@ -1,15 +1,17 @@
-#![feature(try_from)]
 #![allow(unused_imports, dead_code)]
 
-#[macro_use]
 extern crate nom;
+extern crate cparser;
 
 use nom::{IResult, ErrorKind};
 
-pub mod parser;
+use cparser::parser;
+use cparser::parser::error::Diagnostic;
 
 fn test_idents() {
-    let res = parser::ident::parsing::identifier_list(b"test4, test, testing,sdfsdf, sdf, sdf_sdf3");
+    const SOURCE: &'static [u8] = b"test4, test, testing,sdfsdf, sdf, sdf_sdf3";
+
+    let res = parser::ident::parsing::identifier_list(SOURCE);
 
     match res {
         IResult::Done(i, o) => {
@@ -17,22 +19,16 @@ fn test_idents() {
             println!("Result: {:?}", o);
         },
         IResult::Error(err) => {
-            for err in nom::error_to_list(&err) {
-                if let Some(p_err) = self::parser::error::ParseError::from_nom(&err) {
-                    println!("Error: {}", p_err);
-                } else {
-                    println!("Error: {:?}", err);
-                }
-            }
+            print!("{}", Diagnostic::from_nom(SOURCE, &err).render("<input>", SOURCE));
         },
         _ => println!("{:?}", res)
     }
 }
 
 fn test_strings() {
-    let k = br#####"    '\xAFAF'     "#####;
+    const SOURCE: &'static [u8] = br#####"    '\xAFAF'     "#####;
 
-    let res = parser::lit::parsing::char_literal(k);
+    let res = parser::lit::parsing::char_literal(SOURCE);
 
     match res {
         IResult::Done(i, o) => {
@@ -40,19 +36,36 @@ fn test_strings() {
             println!("Result: {:?}", o);
         },
         IResult::Error(err) => {
-            for err in nom::error_to_list(&err) {
-                if let Some(p_err) = self::parser::error::ParseError::from_nom(&err) {
-                    println!("Error: {}", p_err);
-                } else {
-                    println!("Error: {:?}", err);
-                }
-            }
+            print!("{}", Diagnostic::from_nom(SOURCE, &err).render("<input>", SOURCE));
         },
         _ => println!("{:?}", res)
     }
 }
 
+fn parse_file(path: &str) {
+    use std::io::Read;
+
+    let mut source = Vec::new();
+
+    if let Err(err) = std::fs::File::open(path).and_then(|mut file| file.read_to_end(&mut source)) {
+        println!("Error: {}", err);
+        return;
+    }
+
+    let (unit, diagnostics) = cparser::parse_partial(Default::default(), &source);
+
+    for item in unit.items {
+        println!("{:?}", item);
+    }
+
+    print!("{}", diagnostics.render(path, &source));
+}
+
 fn main() {
-    test_idents();
-    test_strings();
+    if let Some(path) = std::env::args().nth(1) {
+        parse_file(&path);
+    } else {
+        test_idents();
+        test_strings();
+    }
 }
\ No newline at end of file
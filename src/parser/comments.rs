@@ -1,15 +1,410 @@
+//! Block and line comments, which the grammar treats as whitespace
+//!
+//! Comments can also be kept while parsing, so documentation comments can be attached
+//! to the declarations they describe. See `parse_with_options`.
+
+use super::span::Spanned;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommentKind {
+    /// `/* ... */`
+    Block,
+    /// `// ...`
+    Line,
+}
+
+/// A comment kept from the source
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// The comment as written, including its markers
+    pub text: String,
+}
+
+impl Comment {
+    /// Returns true for Doxygen-style documentation comments, being `/** ... */`, `/*! ... */`, `///` and `//!`
+    ///
+    /// Decorative comments such as `/*****/` and `////` are not documentation.
+    pub fn is_doc(&self) -> bool {
+        let text = &self.text[..];
+
+        match self.kind {
+            CommentKind::Block => (text.starts_with("/**") && !text.starts_with("/***") && text != "/**/") || text.starts_with("/*!"),
+            CommentKind::Line => (text.starts_with("///") && !text.starts_with("////")) || text.starts_with("//!"),
+        }
+    }
+
+    /// Returns true for documentation comments that describe what comes before them, written `///<` or `/**<`
+    pub fn is_trailing_doc(&self) -> bool {
+        self.is_doc() && self.text[3..].starts_with('<')
+    }
+
+    /// The text of the comment without its markers
+    ///
+    /// The `*` commonly starting each line of a block comment is removed,
+    /// along with blank lines at the start and end.
+    pub fn content(&self) -> String {
+        let text = match self.kind {
+            CommentKind::Block => &self.text[2..self.text.len() - 2],
+            CommentKind::Line => &self.text[2..],
+        };
+
+        let text = text.trim_start_matches(|c| c == '*' || c == '/' || c == '!');
+        let text = if text.starts_with('<') { &text[1..] } else { text };
+
+        let lines: Vec<&str> = text.lines().map(|line| {
+            let line = line.trim();
+
+            if line.starts_with('*') { line[1..].trim() } else { line }
+        }).collect();
+
+        let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+        let end = lines.iter().rposition(|line| !line.is_empty()).map_or(start, |end| end + 1);
+
+        lines[start..end].join("\n")
+    }
+}
+
+/// Joins the content of documentation comments, one per line
+pub fn documentation(comments: &[Spanned<Comment>]) -> String {
+    comments.iter().map(|comment| comment.content()).collect::<Vec<_>>().join("\n")
+}
+
+/// Location of a comment in a source file, along with the tokens around it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentRange {
+    pub start: usize,
+    pub end: usize,
+    /// End of the token before the comment
+    pub previous: usize,
+    /// End of the token before the comment, skipping a `,` or `;` that ends the previous item
+    pub previous_item: usize,
+    /// Start of the token after the comment
+    pub next: usize,
+}
+
+impl CommentRange {
+    pub fn kind(&self, source: &[u8]) -> CommentKind {
+        if source[self.start..].starts_with(b"//") { CommentKind::Line } else { CommentKind::Block }
+    }
+}
+
+/// Length of the block comment at the start of `input`, if it begins with a terminated one
+///
+/// Block comments do not nest, so the first `*/` after the opening `/*` ends the comment.
+pub fn block_comment_len(input: &[u8]) -> Option<usize> {
+    if !input.starts_with(b"/*") {
+        return None;
+    }
+
+    input[2..].windows(2).position(|w| w == b"*/").map(|end| end + 4)
+}
+
+/// Length of the line comment at the start of `input`, excluding the newline that ends it
+///
+/// A backslash directly before the newline splices the next line into the comment.
+pub fn line_comment_len(input: &[u8]) -> Option<usize> {
+    if !input.starts_with(b"//") {
+        return None;
+    }
+
+    let mut len = 2;
+
+    while len < input.len() {
+        if input[len] == b'\n' {
+            let spliced = input[..len].ends_with(b"\\") || input[..len].ends_with(b"\\\r");
+
+            if !spliced {
+                break;
+            }
+        }
+
+        len += 1;
+    }
+
+    Some(len)
+}
+
+/// Length of the comment at the start of `input`, if there is one
+pub fn comment_len(input: &[u8]) -> Option<usize> {
+    block_comment_len(input).or_else(|| line_comment_len(input))
+}
+
+/// Length of the whitespace and comments at the start of `input`
+pub fn trivia_len(input: &[u8]) -> usize {
+    let mut len = 0;
+
+    while len < input.len() {
+        if input[len].is_ascii_whitespace() {
+            len += 1;
+        } else if let Some(comment) = comment_len(&input[len..]) {
+            len += comment;
+        } else {
+            break;
+        }
+    }
+
+    len
+}
+
+/// Length of the string literal or character constant at the start of `input`,
+/// which ends at the closing quote or the end of the line
+fn quoted_len(input: &[u8]) -> Option<usize> {
+    let quote = match input.first() {
+        Some(&c) if c == b'"' || c == b'\'' => c,
+        _ => return None,
+    };
+
+    let mut len = 1;
+
+    while len < input.len() && input[len] != quote && input[len] != b'\n' {
+        if input[len] == b'\\' {
+            len += 1;
+        }
+
+        len += 1;
+    }
+
+    if len < input.len() && input[len] == quote {
+        len += 1;
+    }
+
+    Some(len)
+}
+
+/// Length of the identifier or preprocessing number at the start of `input`
+///
+/// Skipping these whole keeps the digit separators of C23, as in `1'000`, and the digits of
+/// identifiers from being mistaken for the start of a character constant.
+pub fn word_len(input: &[u8]) -> Option<usize> {
+    let is_word = |c: u8| c == b'_' || c.is_ascii_alphanumeric();
+
+    match input.first() {
+        Some(&c) if c == b'_' || c.is_ascii_alphabetic() => {
+            Some(input.iter().take_while(|&&c| is_word(c)).count())
+        },
+        Some(&c) if c.is_ascii_digit() || (c == b'.' && input.get(1).map_or(false, |c| c.is_ascii_digit())) => {
+            let mut len = 1;
+
+            while len < input.len() {
+                match input[len] {
+                    b'+' | b'-' if b"eEpP".contains(&input[len - 1]) => len += 1,
+                    b'\'' if input.get(len + 1).map_or(false, |&c| is_word(c)) => len += 2,
+                    c if c == b'.' || is_word(c) => len += 1,
+                    _ => break,
+                }
+            }
+
+            Some(len)
+        },
+        _ => None,
+    }
+}
+
+/// Removes the whitespace and comments around `input`
+///
+/// Comment markers within string literals and character constants are not comments.
+pub fn trim_trivia(input: &[u8]) -> &[u8] {
+    let start = trivia_len(input);
+
+    let mut end = start;
+    let mut i = start;
+
+    while i < input.len() {
+        if let Some(len) = quoted_len(&input[i..]) {
+            i += len;
+            end = i;
+        } else if let Some(len) = word_len(&input[i..]) {
+            i += len;
+            end = i;
+        } else if input[i].is_ascii_whitespace() {
+            i += 1;
+        } else if let Some(len) = comment_len(&input[i..]) {
+            i += len;
+        } else {
+            i += 1;
+            end = i;
+        }
+    }
+
+    &input[start..end]
+}
+
+/// Replaces every comment in `source` with spaces, keeping the newlines within them
+///
+/// The result has the same length and lines as `source`, so offsets into it are offsets into
+/// `source`. Comment markers within string literals and character constants are kept.
+pub fn strip_comments(source: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::with_capacity(source.len());
+
+    let mut i = 0;
+
+    while i < source.len() {
+        if let Some(len) = quoted_len(&source[i..]).or_else(|| word_len(&source[i..])) {
+            stripped.extend_from_slice(&source[i..i + len]);
+            i += len;
+        } else if let Some(len) = comment_len(&source[i..]) {
+            stripped.extend(source[i..i + len].iter().map(|&c| if c == b'\n' { b'\n' } else { b' ' }));
+            i += len;
+        } else {
+            stripped.push(source[i]);
+            i += 1;
+        }
+    }
+
+    stripped
+}
+
+/// Finds every comment in `source`, ignoring comment markers within string literals and character constants
+pub fn find_comments(source: &[u8]) -> Vec<CommentRange> {
+    let mut comments = Vec::new();
+
+    let mut previous = 0;
+    let mut previous_item = 0;
+
+    let mut i = 0;
+
+    while i < source.len() {
+        if let Some(len) = quoted_len(&source[i..]).or_else(|| word_len(&source[i..])) {
+            i += len;
+            previous = i;
+            previous_item = i;
+        } else if source[i].is_ascii_whitespace() {
+            i += 1;
+        } else if let Some(len) = comment_len(&source[i..]) {
+            comments.push(CommentRange {
+                start: i,
+                end: i + len,
+                previous: previous,
+                previous_item: previous_item,
+                next: i + len + trivia_len(&source[i + len..]),
+            });
+
+            i += len;
+        } else {
+            if (source[i] != b',' && source[i] != b';') || previous != previous_item {
+                previous_item = i + 1;
+            }
+
+            i += 1;
+            previous = i;
+        }
+    }
+
+    comments
+}
+
 pub mod parsing {
     use nom::*;
 
-    named!(consume_null<()>, fold_many0!(anychar, (), |_, _| ()));
+    use ::parser::error::ParseError;
 
-    named!(block_comment_raw<()>, delimited!(tag!("/*"), consume_null, tag!("*/")));
+    use super::{block_comment_len, line_comment_len};
+
+    fn comment_with(input: &[u8], len: Option<usize>) -> IResult<&[u8], &[u8], ParseError> {
+        match len {
+            Some(len) => IResult::Done(&input[len..], &input[..len]),
+            None => IResult::Error(error_position!(ParseError::InvalidComment.into_nom(), input)),
+        }
+    }
+
+    /// Matches a `/* ... */` comment, which may contain `*` and span lines
+    pub fn block_comment(input: &[u8]) -> IResult<&[u8], &[u8], ParseError> {
+        comment_with(input, block_comment_len(input))
+    }
+
+    /// Matches a `//` comment up to the end of its line, which may be continued with a backslash
+    pub fn line_comment(input: &[u8]) -> IResult<&[u8], &[u8], ParseError> {
+        comment_with(input, line_comment_len(input))
+    }
 
-    //named!(filter_strings, )
+    named!(
+        #[doc = "Matches a block or line comment"],
+        pub comment<&[u8], &[u8], ParseError>,
+        alt!(block_comment | line_comment)
+    );
 
     #[cfg(test)]
     mod test {
+        use super::*;
+
+        use ::parser::comments::*;
+
+        #[test]
+        fn test_block_comments() {
+            assert_eq!(block_comment(b"/* a * b **/ c"), IResult::Done(&b" c"[..], &b"/* a * b **/"[..]));
+            assert_eq!(block_comment(b"/*/ still open */"), IResult::Done(&b""[..], &b"/*/ still open */"[..]));
+            assert_eq!(block_comment(b"/**/*/"), IResult::Done(&b"*/"[..], &b"/**/"[..]));
+            assert!(block_comment(b"/* unterminated").is_err());
+            assert!(block_comment(b"a /* */").is_err());
+        }
+
+        #[test]
+        fn test_line_comments() {
+            assert_eq!(line_comment(b"// one\ntwo"), IResult::Done(&b"\ntwo"[..], &b"// one"[..]));
+            assert_eq!(line_comment(b"// one \\\ntwo\nthree"), IResult::Done(&b"\nthree"[..], &b"// one \\\ntwo"[..]));
+            assert_eq!(comment(b"// end"), IResult::Done(&b""[..], &b"// end"[..]));
+        }
+
+        #[test]
+        fn test_doc_comments() {
+            let comment = |kind, text: &str| Comment { kind: kind, text: text.to_owned() };
+
+            let block = comment(CommentKind::Block, "/**\n * Brief.\n *\n * Details.\n */");
+
+            assert!(block.is_doc() && !block.is_trailing_doc());
+            assert_eq!(block.content(), "Brief.\n\nDetails.");
+
+            let trailing = comment(CommentKind::Line, "///< The x coordinate");
+
+            assert!(trailing.is_trailing_doc());
+            assert_eq!(trailing.content(), "The x coordinate");
+
+            assert!(comment(CommentKind::Block, "/*! Qt style */").is_doc());
+            assert!(!comment(CommentKind::Block, "/* plain */").is_doc());
+            assert!(!comment(CommentKind::Block, "/**/").is_doc());
+            assert!(!comment(CommentKind::Block, "/*****/").is_doc());
+            assert!(!comment(CommentKind::Line, "//// separator").is_doc());
+        }
+
+        #[test]
+        fn test_find_comments() {
+            let source = b"int a; // one\nchar *s = \"/* no */\", b /* two */;";
+
+            assert_eq!(find_comments(source), vec![
+                CommentRange { start: 7, end: 13, previous: 6, previous_item: 5, next: 14 },
+                CommentRange { start: 38, end: 47, previous: 37, previous_item: 37, next: 47 },
+            ]);
+        }
+
+        #[test]
+        fn test_strip_comments() {
+            let source = b"a /* b\nc */ \"/* d */\" '//' // e\nf";
+
+            assert_eq!(&strip_comments(source)[..], &b"a     \n     \"/* d */\" '//'     \nf"[..]);
+            assert_eq!(trim_trivia(b" /* x */ a \"*/\" b // c\n "), &b"a \"*/\" b"[..]);
+        }
+
+        #[test]
+        fn test_digit_separators() {
+            let source = b"int x = 1'000; ///< doc\nchar c = u8'/';";
+
+            assert_eq!(&strip_comments(source)[..], &b"int x = 1'000;         \nchar c = u8'/';"[..]);
+            assert_eq!(find_comments(source).len(), 1);
+            assert_eq!(find_comments(source)[0].start, 15);
+            assert_eq!(trim_trivia(b"0x1'f'ull // c"), &b"0x1'f'ull"[..]);
+            assert_eq!(trim_trivia(b"1.5e+3'0 /* c */"), &b"1.5e+3'0"[..]);
+        }
+
         #[test]
-        fn test_block_comments() {}
+        fn test_unterminated_comment() {
+            use ::parser::error::Diagnostic;
+            use ::parser::span::FileId;
+            use ::parser::token::tokenize;
+
+            let err = tokenize(FileId::default(), b"int a; /* never closed").unwrap_err();
+
+            assert_eq!(err, Diagnostic::new(7, ParseError::InvalidComment));
+        }
     }
-}
\ No newline at end of file
+}
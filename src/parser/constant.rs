@@ -0,0 +1,388 @@
+//! Evaluation of integer constant expressions
+//!
+//! All arithmetic is performed in `intmax_t` or `uintmax_t`, as the preprocessor does.
+//! If either operand of a binary operator is unsigned, both are converted to unsigned.
+//!
+//! Casts to integer types truncate the value to the width of the type. The sizes and alignments
+//! of types are those of LP64 targets, such as x86-64 Linux, and are only known for the scalar
+//! types, so `sizeof` of arrays, structs, typedef names and expressions is not constant here.
+
+use super::ident::Ident;
+use super::lit::{Lit, IntegerLiteral, SignedIntegerValue, UnsignedIntegerValue, FloatLiteral};
+use super::expression::Expression;
+use super::declaration::{TypeName, DirectDeclarator};
+use super::types::Type;
+use super::operator::{Operator, RelationalOperator, LogicalOperator, BinaryOperator, UnaryOperator};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Constant {
+    Signed(i64),
+    Unsigned(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EvaluationError {
+    /// The expression contains something that is not an integer constant expression
+    NotConstant,
+    UnknownIdentifier(Ident),
+    DivisionByZero,
+    Overflow,
+}
+
+impl Constant {
+    pub fn is_zero(&self) -> bool {
+        match *self {
+            Constant::Signed(value) => value == 0,
+            Constant::Unsigned(value) => value == 0,
+        }
+    }
+
+    pub fn from_bool(value: bool) -> Constant {
+        Constant::Signed(value as i64)
+    }
+
+    /// The value as a signed integer, if it can be represented as one
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Constant::Signed(value) => Some(value),
+            Constant::Unsigned(value) if value <= i64::max_value() as u64 => Some(value as i64),
+            Constant::Unsigned(_) => None,
+        }
+    }
+
+    /// The value reinterpreted as an unsigned integer, like a C conversion to `uintmax_t`
+    pub fn as_u64(&self) -> u64 {
+        match *self {
+            Constant::Signed(value) => value as u64,
+            Constant::Unsigned(value) => value,
+        }
+    }
+}
+
+impl From<IntegerLiteral> for Constant {
+    fn from(lit: IntegerLiteral) -> Constant {
+        match lit {
+            IntegerLiteral::Signed(SignedIntegerValue::Short(value)) |
+            IntegerLiteral::Signed(SignedIntegerValue::Int(value)) => Constant::Signed(value as i64),
+            IntegerLiteral::Signed(SignedIntegerValue::Long(value)) |
+            IntegerLiteral::Signed(SignedIntegerValue::LongLong(value)) => Constant::Signed(value),
+            IntegerLiteral::Unsigned(UnsignedIntegerValue::Short(value)) |
+            IntegerLiteral::Unsigned(UnsignedIntegerValue::Int(value)) => Constant::Unsigned(value as u64),
+            IntegerLiteral::Unsigned(UnsignedIntegerValue::Long(value)) |
+            IntegerLiteral::Unsigned(UnsignedIntegerValue::LongLong(value)) => Constant::Unsigned(value),
+        }
+    }
+}
+
+fn unary(op: UnaryOperator, value: Constant) -> Result<Constant, EvaluationError> {
+    Ok(match (op, value) {
+        (UnaryOperator::PLUS, value) => value,
+        (UnaryOperator::MINUS, Constant::Signed(value)) => {
+            Constant::Signed(value.checked_neg().ok_or(EvaluationError::Overflow)?)
+        },
+        (UnaryOperator::MINUS, Constant::Unsigned(value)) => Constant::Unsigned(value.wrapping_neg()),
+        (UnaryOperator::NOT, Constant::Signed(value)) => Constant::Signed(!value),
+        (UnaryOperator::NOT, Constant::Unsigned(value)) => Constant::Unsigned(!value),
+        (UnaryOperator::LNOT, value) => Constant::from_bool(value.is_zero()),
+        (UnaryOperator::ADDR, _) | (UnaryOperator::DEREF, _) => return Err(EvaluationError::NotConstant),
+    })
+}
+
+fn arithmetic(op: Operator, lhs: Constant, rhs: Constant) -> Result<Constant, EvaluationError> {
+    // Shifts take the type of their left operand
+    if op == Operator::LEFT || op == Operator::RIGHT {
+        let amount = match rhs.as_i64() {
+            Some(amount) if amount >= 0 && amount < 64 => amount as u32,
+            _ => return Err(EvaluationError::Overflow),
+        };
+
+        return Ok(match (op, lhs) {
+            (Operator::LEFT, Constant::Signed(value)) => {
+                let shifted = value << amount;
+
+                // Bits shifted out, or into the sign bit, overflow
+                if shifted >> amount != value {
+                    return Err(EvaluationError::Overflow);
+                }
+
+                Constant::Signed(shifted)
+            },
+            (Operator::LEFT, Constant::Unsigned(value)) => Constant::Unsigned(value.wrapping_shl(amount)),
+            (_, Constant::Signed(value)) => Constant::Signed(value >> amount),
+            (_, Constant::Unsigned(value)) => Constant::Unsigned(value >> amount),
+        });
+    }
+
+    match (lhs, rhs) {
+        (Constant::Signed(lhs), Constant::Signed(rhs)) => {
+            let value = match op {
+                Operator::MUL => lhs.checked_mul(rhs),
+                Operator::DIV | Operator::MOD if rhs == 0 => return Err(EvaluationError::DivisionByZero),
+                Operator::DIV => lhs.checked_div(rhs),
+                Operator::MOD => lhs.checked_rem(rhs),
+                Operator::ADD => lhs.checked_add(rhs),
+                Operator::SUB => lhs.checked_sub(rhs),
+                Operator::AND => Some(lhs & rhs),
+                Operator::XOR => Some(lhs ^ rhs),
+                Operator::OR => Some(lhs | rhs),
+                Operator::LEFT | Operator::RIGHT => unreachable!(),
+            };
+
+            value.map(Constant::Signed).ok_or(EvaluationError::Overflow)
+        },
+        (lhs, rhs) => {
+            let (lhs, rhs) = (lhs.as_u64(), rhs.as_u64());
+
+            Ok(Constant::Unsigned(match op {
+                Operator::MUL => lhs.wrapping_mul(rhs),
+                Operator::DIV | Operator::MOD if rhs == 0 => return Err(EvaluationError::DivisionByZero),
+                Operator::DIV => lhs / rhs,
+                Operator::MOD => lhs % rhs,
+                Operator::ADD => lhs.wrapping_add(rhs),
+                Operator::SUB => lhs.wrapping_sub(rhs),
+                Operator::AND => lhs & rhs,
+                Operator::XOR => lhs ^ rhs,
+                Operator::OR => lhs | rhs,
+                Operator::LEFT | Operator::RIGHT => unreachable!(),
+            }))
+        }
+    }
+}
+
+fn relational(op: RelationalOperator, lhs: Constant, rhs: Constant) -> Constant {
+    use std::cmp::Ordering;
+
+    let ordering = match (lhs, rhs) {
+        (Constant::Signed(lhs), Constant::Signed(rhs)) => lhs.cmp(&rhs),
+        (lhs, rhs) => lhs.as_u64().cmp(&rhs.as_u64()),
+    };
+
+    Constant::from_bool(match op {
+        RelationalOperator::LT => ordering == Ordering::Less,
+        RelationalOperator::GT => ordering == Ordering::Greater,
+        RelationalOperator::LE => ordering != Ordering::Greater,
+        RelationalOperator::GE => ordering != Ordering::Less,
+        RelationalOperator::EQ => ordering == Ordering::Equal,
+        RelationalOperator::NE => ordering != Ordering::Equal,
+    })
+}
+
+// Width in bits and signedness of an integer type, where enums are `int`
+fn integer_layout(ty: &Type) -> Option<(u32, bool)> {
+    Some(match *ty {
+        Type::Bool => (1, false),
+        Type::Char | Type::SignedChar => (8, true),
+        Type::UnsignedChar => (8, false),
+        Type::Short => (16, true),
+        Type::UnsignedShort => (16, false),
+        Type::Int | Type::Enum(_) => (32, true),
+        Type::UnsignedInt => (32, false),
+        Type::Long | Type::LongLong => (64, true),
+        Type::UnsignedLong | Type::UnsignedLongLong => (64, false),
+        _ => return None,
+    })
+}
+
+// The type named by a type name without a declarator, or `None` if it has a pointer, array or function declarator
+fn plain_type(ty: &TypeName) -> Option<Type> {
+    match ty.declarator {
+        None => ty.base_type().ok().map(|qualified| qualified.ty),
+        Some(_) => None,
+    }
+}
+
+// Size and alignment in bytes of a scalar type
+fn size_and_alignment(ty: &TypeName) -> Option<(u64, u64)> {
+    if let Some(ref declarator) = ty.declarator {
+        // Pointers to anything
+        return match declarator.direct.node {
+            DirectDeclarator::Abstract if !declarator.pointers.is_empty() => Some((8, 8)),
+            _ => None,
+        };
+    }
+
+    let ty = plain_type(ty)?;
+
+    let size = match ty {
+        Type::Float => 4,
+        Type::Double | Type::FloatComplex => 8,
+        Type::LongDouble | Type::DoubleComplex => 16,
+        Type::LongDoubleComplex => 32,
+        Type::Bool => 1,
+        ref ty => integer_layout(ty)?.0 as u64 / 8,
+    };
+
+    // Complex types are aligned like their real parts
+    let alignment = match ty {
+        Type::FloatComplex | Type::DoubleComplex | Type::LongDoubleComplex => size / 2,
+        _ => size,
+    };
+
+    Some((size, alignment))
+}
+
+// Converts to an integer type, truncating to its width as C does for unsigned types and
+// as every common implementation does for signed ones
+fn cast(ty: &TypeName, value: Constant) -> Result<Constant, EvaluationError> {
+    let ty = plain_type(ty).ok_or(EvaluationError::NotConstant)?;
+    let (bits, signed) = integer_layout(&ty).ok_or(EvaluationError::NotConstant)?;
+
+    if ty == Type::Bool {
+        return Ok(Constant::from_bool(!value.is_zero()));
+    }
+
+    let value = value.as_u64();
+    let unused = 64 - bits;
+
+    Ok(if signed {
+        Constant::Signed(((value << unused) as i64) >> unused)
+    } else {
+        Constant::Unsigned((value << unused) >> unused)
+    })
+}
+
+// The value of a floating constant, possibly negated
+fn float_value(expr: &Expression) -> Option<f64> {
+    match *expr {
+        Expression::Literal(Lit::Float(FloatLiteral::F32(value))) => Some(value as f64),
+        Expression::Literal(Lit::Float(FloatLiteral::F64(value))) => Some(value),
+        Expression::Unary(UnaryOperator::PLUS, ref value) => float_value(value),
+        Expression::Unary(UnaryOperator::MINUS, ref value) => float_value(value).map(|value| -value),
+        _ => None,
+    }
+}
+
+// Floating constants are only allowed in integer constant expressions as the immediate operand of a cast
+fn cast_operand(expr: &Expression, lookup: &Fn(&Ident) -> Option<Constant>) -> Result<Constant, EvaluationError> {
+    let value = match float_value(expr) {
+        Some(value) => value.trunc(),
+        None => return evaluate(expr, lookup),
+    };
+
+    // Converted toward zero, which must fit in the widest integer type
+    if value >= -9223372036854775808.0 && value < 9223372036854775808.0 {
+        Ok(Constant::Signed(value as i64))
+    } else if value >= 0.0 && value < 18446744073709551616.0 {
+        Ok(Constant::Unsigned(value as u64))
+    } else {
+        Err(EvaluationError::Overflow)
+    }
+}
+
+/// Evaluates an integer constant expression
+///
+/// Identifiers are resolved through `lookup`, such as for enumeration constants.
+pub fn evaluate(expr: &Expression, lookup: &Fn(&Ident) -> Option<Constant>) -> Result<Constant, EvaluationError> {
+    match *expr {
+        Expression::Literal(Lit::Integer(lit)) => Ok(lit.into()),
+        Expression::Literal(Lit::Char { value, .. }) => Ok(Constant::Signed(value as i64)),
+        Expression::Literal(Lit::Bool(value)) => Ok(Constant::from_bool(value)),
+        Expression::Identifier(ref ident) => {
+            lookup(ident).ok_or_else(|| EvaluationError::UnknownIdentifier(ident.clone()))
+        },
+        Expression::Unary(op, ref value) => unary(op, evaluate(value, lookup)?),
+        Expression::Binary(BinaryOperator::Logical(op), ref lhs, ref rhs) => {
+            // Short-circuits, so the right side may contain errors that are never evaluated
+            let lhs = !evaluate(lhs, lookup)?.is_zero();
+
+            Ok(Constant::from_bool(match op {
+                LogicalOperator::AND => lhs && !evaluate(rhs, lookup)?.is_zero(),
+                LogicalOperator::OR => lhs || !evaluate(rhs, lookup)?.is_zero(),
+            }))
+        },
+        Expression::Binary(BinaryOperator::Arithmetic(op), ref lhs, ref rhs) => {
+            arithmetic(op, evaluate(lhs, lookup)?, evaluate(rhs, lookup)?)
+        },
+        Expression::Binary(BinaryOperator::Relational(op), ref lhs, ref rhs) => {
+            Ok(relational(op, evaluate(lhs, lookup)?, evaluate(rhs, lookup)?))
+        },
+        Expression::Conditional(ref condition, ref then, ref otherwise) => {
+            if evaluate(condition, lookup)?.is_zero() {
+                evaluate(otherwise, lookup)
+            } else {
+                evaluate(then, lookup)
+            }
+        },
+        Expression::Cast(ref ty, ref value) => cast(ty, cast_operand(value, lookup)?),
+        Expression::SizeOfType(ref ty) => {
+            size_and_alignment(ty).map(|(size, _)| Constant::Unsigned(size)).ok_or(EvaluationError::NotConstant)
+        },
+        Expression::AlignOf(ref ty) => {
+            size_and_alignment(ty).map(|(_, alignment)| Constant::Unsigned(alignment)).ok_or(EvaluationError::NotConstant)
+        },
+        Expression::Comma(ref exprs) => {
+            let mut value = Err(EvaluationError::NotConstant);
+
+            for expr in exprs {
+                value = evaluate(expr, lookup);
+            }
+
+            value
+        },
+        _ => Err(EvaluationError::NotConstant),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use nom::IResult;
+
+    use ::parser::expression::parsing::expression;
+
+    fn eval(source: &[u8]) -> Result<Constant, EvaluationError> {
+        match expression(source) {
+            IResult::Done(rest, ref expr) if rest.is_empty() => evaluate(expr, &|ident| {
+                if *ident == "FOUR" { Some(Constant::Signed(4)) } else { None }
+            }),
+            res => panic!("Unexpected result {:?}", res)
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(eval(b"1 + 2 * 3"), Ok(Constant::Signed(7)));
+        assert_eq!(eval(b"FOUR << 2 | 1"), Ok(Constant::Signed(17)));
+        assert_eq!(eval(b"-1 < 0u"), Ok(Constant::Signed(0)));
+        assert_eq!(eval(b"0 && 1 / 0"), Ok(Constant::Signed(0)));
+        assert_eq!(eval(b"FOUR ? 'a' : 2"), Ok(Constant::Signed(97)));
+        assert_eq!(eval(b"1 / (FOUR - 4)"), Err(EvaluationError::DivisionByZero));
+        assert_eq!(eval(b"x + 1"), Err(EvaluationError::UnknownIdentifier(Ident::from("x"))));
+        assert_eq!(eval(b"f()"), Err(EvaluationError::NotConstant));
+    }
+
+    #[test]
+    fn test_shift_overflow() {
+        assert_eq!(eval(b"1 << 62"), Ok(Constant::Signed(1 << 62)));
+        assert_eq!(eval(b"-1 << 3"), Ok(Constant::Signed(-8)));
+        assert_eq!(eval(b"1 << 63"), Err(EvaluationError::Overflow));
+        assert_eq!(eval(b"3 << 62"), Err(EvaluationError::Overflow));
+        assert_eq!(eval(b"1u << 63"), Ok(Constant::Unsigned(1 << 63)));
+        assert_eq!(eval(b"1 << 64"), Err(EvaluationError::Overflow));
+    }
+
+    #[test]
+    fn test_casts_and_sizes() {
+        assert_eq!(eval(b"(int)1"), Ok(Constant::Signed(1)));
+        assert_eq!(eval(b"(unsigned char)257"), Ok(Constant::Unsigned(1)));
+        assert_eq!(eval(b"(signed char)255"), Ok(Constant::Signed(-1)));
+        assert_eq!(eval(b"(unsigned)-1"), Ok(Constant::Unsigned(0xFFFF_FFFF)));
+        assert_eq!(eval(b"(short)0x18000"), Ok(Constant::Signed(-32768)));
+        assert_eq!(eval(b"(unsigned long long)-1"), Ok(Constant::Unsigned(u64::max_value())));
+        assert_eq!(eval(b"(_Bool)FOUR"), Ok(Constant::Signed(1)));
+        assert_eq!(eval(b"(int)2.9 + (long)-1.5e0"), Ok(Constant::Signed(1)));
+        assert_eq!(eval(b"(int)1e30"), Err(EvaluationError::Overflow));
+        assert_eq!(eval(b"(double)1"), Err(EvaluationError::NotConstant));
+        assert_eq!(eval(b"(char *)0"), Err(EvaluationError::NotConstant));
+        assert_eq!(eval(b"1.5 + 1"), Err(EvaluationError::NotConstant));
+
+        assert_eq!(eval(b"sizeof(int)"), Ok(Constant::Unsigned(4)));
+        assert_eq!(eval(b"sizeof(unsigned long) * 2"), Ok(Constant::Unsigned(16)));
+        assert_eq!(eval(b"sizeof(char) + sizeof(short) + sizeof(long double)"), Ok(Constant::Unsigned(19)));
+        assert_eq!(eval(b"sizeof(const char *)"), Ok(Constant::Unsigned(8)));
+        assert_eq!(eval(b"_Alignof(double _Complex)"), Ok(Constant::Unsigned(8)));
+        assert_eq!(eval(b"sizeof(int[4])"), Err(EvaluationError::NotConstant));
+        assert_eq!(eval(b"sizeof FOUR"), Err(EvaluationError::NotConstant));
+    }
+}
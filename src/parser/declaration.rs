@@ -0,0 +1,895 @@
+use super::ident::Ident;
+use super::lit::Lit;
+use super::expression::Expression;
+use super::misc::StorageClassSpecifier;
+use super::types::{TypeSpecifier, TypeQualifier, QualifiedType};
+use super::function::FunctionSpecifier;
+use super::span::Spanned;
+use super::comments::Comment;
+use super::initializer::Initializer;
+use super::error::ParseError;
+
+/// `_Alignas(type-name)` or `_Alignas(constant-expression)`, also spelled `alignas` since C23
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlignmentSpecifier {
+    Type(Spanned<TypeName>),
+    Expression(Spanned<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeclarationSpecifier {
+    StorageClass(StorageClassSpecifier),
+    TypeSpecifier(TypeSpecifier),
+    TypeQualifier(TypeQualifier),
+    Function(FunctionSpecifier),
+    Alignment(AlignmentSpecifier),
+}
+
+/// A single `*` in a declarator along with the qualifiers that follow it
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct Pointer {
+    pub qualifiers: Vec<TypeQualifier>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArraySize {
+    /// `[]`
+    Unspecified,
+    /// `[*]`, a variable length array of unspecified size
+    VariableUnspecified,
+    /// `[n]`, where `n` may be a constant or variable length
+    Expression(Spanned<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayDeclarator {
+    pub qualifiers: Vec<TypeQualifier>,
+    /// `[static n]`, only valid in function parameters
+    pub is_static: bool,
+    pub size: ArraySize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterDeclaration {
+    pub specifiers: Vec<Spanned<DeclarationSpecifier>>,
+    /// Either a concrete or abstract declarator, if any
+    pub declarator: Option<Spanned<Declarator>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterList {
+    /// `(int a, char *, ...)`
+    Prototype {
+        parameters: Vec<Spanned<ParameterDeclaration>>,
+        variadic: bool,
+    },
+    /// Old-style `(a, b, c)` or an empty `()`
+    Identifiers(Vec<Ident>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DirectDeclarator {
+    Identifier(Ident),
+    /// The missing identifier of an abstract declarator
+    Abstract,
+    /// `( declarator )`
+    Parenthesized(Box<Spanned<Declarator>>),
+    Array(Box<Spanned<DirectDeclarator>>, ArrayDeclarator),
+    Function(Box<Spanned<DirectDeclarator>>, ParameterList),
+}
+
+/// Concrete and abstract declarators, which only differ in whether an identifier is present
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declarator {
+    pub pointers: Vec<Spanned<Pointer>>,
+    pub direct: Spanned<DirectDeclarator>,
+}
+
+impl DirectDeclarator {
+    pub fn ident(&self) -> Option<&Ident> {
+        match *self {
+            DirectDeclarator::Identifier(ref ident) => Some(ident),
+            DirectDeclarator::Abstract => None,
+            DirectDeclarator::Parenthesized(ref declarator) => declarator.ident(),
+            DirectDeclarator::Array(ref direct, _) |
+            DirectDeclarator::Function(ref direct, _) => direct.ident(),
+        }
+    }
+
+    fn into_abstract(self) -> (Option<Ident>, DirectDeclarator) {
+        match self {
+            DirectDeclarator::Identifier(ident) => (Some(ident), DirectDeclarator::Abstract),
+            DirectDeclarator::Abstract => (None, DirectDeclarator::Abstract),
+            DirectDeclarator::Parenthesized(declarator) => {
+                let Spanned { node, span } = *declarator;
+                let (ident, declarator) = node.into_abstract();
+
+                (ident, DirectDeclarator::Parenthesized(Box::new(Spanned::new(declarator, span))))
+            },
+            DirectDeclarator::Array(direct, array) => {
+                let (ident, direct) = DirectDeclarator::spanned_into_abstract(*direct);
+
+                (ident, DirectDeclarator::Array(Box::new(direct), array))
+            },
+            DirectDeclarator::Function(direct, params) => {
+                let (ident, direct) = DirectDeclarator::spanned_into_abstract(*direct);
+
+                (ident, DirectDeclarator::Function(Box::new(direct), params))
+            },
+        }
+    }
+
+    fn spanned_into_abstract(direct: Spanned<DirectDeclarator>) -> (Option<Ident>, Spanned<DirectDeclarator>) {
+        let Spanned { node, span } = direct;
+        let (ident, direct) = node.into_abstract();
+
+        (ident, Spanned::new(direct, span))
+    }
+}
+
+impl Declarator {
+    /// The identifier being declared, or `None` for abstract declarators
+    pub fn ident(&self) -> Option<&Ident> {
+        self.direct.ident()
+    }
+
+    /// Returns true if the declarator consists of nothing at all
+    pub fn is_empty(&self) -> bool {
+        self.pointers.is_empty() && self.direct.node == DirectDeclarator::Abstract
+    }
+
+    /// Splits the declarator into its identifier and the abstract declarator describing its type
+    pub fn into_abstract(self) -> (Option<Ident>, Declarator) {
+        let (ident, direct) = DirectDeclarator::spanned_into_abstract(self.direct);
+
+        (ident, Declarator { pointers: self.pointers, direct: direct })
+    }
+}
+
+/// Specifiers and an abstract declarator, as used in casts, `sizeof` and `_Alignas`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeName {
+    pub specifiers: Vec<Spanned<DeclarationSpecifier>>,
+    pub declarator: Option<Spanned<Declarator>>,
+}
+
+impl TypeName {
+    /// Builds the type of a declared identifier from its specifiers and declarator
+    pub fn from_declarator(specifiers: Vec<Spanned<DeclarationSpecifier>>, declarator: Spanned<Declarator>) -> (Option<Ident>, TypeName) {
+        let Spanned { node, span } = declarator;
+        let (ident, declarator) = node.into_abstract();
+
+        (ident, TypeName {
+            specifiers: specifiers,
+            declarator: if declarator.is_empty() { None } else { Some(Spanned::new(declarator, span)) },
+        })
+    }
+
+    /// The qualified type named by the specifiers, before the declarator is applied
+    pub fn base_type(&self) -> Result<QualifiedType, ParseError> {
+        QualifiedType::from_specifiers(&self.specifiers)
+    }
+}
+
+/// `_Static_assert(condition, "message");`, or C23 `static_assert(condition);`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticAssert {
+    pub condition: Spanned<Expression>,
+    /// Only optional since C23
+    pub message: Option<Spanned<Lit>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitDeclarator {
+    pub declarator: Spanned<Declarator>,
+    pub initializer: Option<Spanned<Initializer>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub specifiers: Vec<Spanned<DeclarationSpecifier>>,
+    pub declarators: Vec<Spanned<InitDeclarator>>,
+    /// Documentation comments, which are only kept when parsing with `ParseOptions::keep_comments`
+    pub doc: Vec<Spanned<Comment>>,
+}
+
+impl Declaration {
+    /// Returns true if the declaration declares typedef names
+    pub fn is_typedef(&self) -> bool {
+        self.specifiers.iter().any(|s| s.node == DeclarationSpecifier::StorageClass(StorageClassSpecifier::Typedef))
+    }
+
+    /// The qualified type named by the specifiers, shared by every declarator
+    ///
+    /// Fails with `ParseError::InvalidTypeSpecifier` for declarations without type specifiers, such as `static x;`
+    pub fn base_type(&self) -> Result<QualifiedType, ParseError> {
+        QualifiedType::from_specifiers(&self.specifiers)
+    }
+}
+
+pub mod parsing {
+    use nom::*;
+    use ::wse;
+    use super::*;
+
+    use ::parser::error::ParseError;
+    use ::parser::ident::parsing::{identifier, identifier_list, typedef_name};
+    use ::parser::lit::parsing::string_literal;
+    use ::parser::expression::parsing::{assignment_expression, constant_expression};
+    use ::parser::initializer::parsing::initializer;
+    use ::parser::misc::parsing::storage_class_specifier;
+    use ::parser::types::Type;
+    use ::parser::types::parsing::{type_specifier, type_qualifier};
+    use ::parser::function::parsing::function_specifier;
+    use ::parser::scope::{self, Symbol};
+    use ::parser::span::{self, Spanned};
+    use ::parser::keywords::{self, Standard};
+
+    named!(alignment_specifier<&[u8], AlignmentSpecifier, ParseError>, wse!(preceded!(
+        alt_complete!(keyword!("_Alignas") | keyword!("alignas")),
+        delimited!(
+            punct!('('),
+            alt_complete!(
+                type_name           => { |ty| AlignmentSpecifier::Type(ty) } |
+                constant_expression => { |e| AlignmentSpecifier::Expression(e) }
+            ),
+            punct!(')')
+        )
+    )));
+
+    /// Which specifiers are allowed in a specifier list
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum SpecifierKind {
+        /// All declaration specifiers
+        Declaration,
+        /// Only type specifiers, qualifiers and alignment specifiers, as in struct members and type names
+        SpecifierQualifier,
+    }
+
+    fn specifier(input: &[u8], kind: SpecifierKind, allow_typedef_name: bool) -> IResult<&[u8], DeclarationSpecifier, ParseError> {
+        alt_complete!(input,
+            cond_reduce!(kind == SpecifierKind::Declaration, storage_class_specifier) => {
+                |s| DeclarationSpecifier::StorageClass(s)
+            } |
+            cond_reduce!(kind == SpecifierKind::Declaration, function_specifier) => {
+                |s| DeclarationSpecifier::Function(s)
+            } |
+            type_specifier          => { |s| DeclarationSpecifier::TypeSpecifier(s) } |
+            type_qualifier          => { |q| DeclarationSpecifier::TypeQualifier(q) } |
+            alignment_specifier     => { |a| DeclarationSpecifier::Alignment(a) } |
+            cond_reduce!(allow_typedef_name, typedef_name) => {
+                |ident| DeclarationSpecifier::TypeSpecifier(TypeSpecifier::TypedefName(ident))
+            }
+        )
+    }
+
+    fn specifiers(mut input: &[u8], kind: SpecifierKind) -> IResult<&[u8], Vec<Spanned<DeclarationSpecifier>>, ParseError> {
+        let start = input;
+
+        let mut specifiers = Vec::new();
+
+        loop {
+            // A typedef name is only a type specifier if no other type specifier has been seen,
+            // otherwise it is the identifier being declared, as in `unsigned T;`
+            let allow_typedef_name = !specifiers.iter().any(|s: &Spanned<DeclarationSpecifier>| match s.node {
+                DeclarationSpecifier::TypeSpecifier(_) => true,
+                _ => false,
+            });
+
+            match spanned!(input, apply!(specifier, kind, allow_typedef_name)) {
+                IResult::Done(rest, s) => {
+                    specifiers.push(s);
+                    input = rest;
+                },
+                _ => break,
+            }
+        }
+
+        if specifiers.is_empty() {
+            return IResult::Error(error_position!(ParseError::InvalidTypeSpecifier.into_nom(), input));
+        }
+
+        let mut type_specifiers = specifiers.iter().filter_map(|s| match s.node {
+            DeclarationSpecifier::TypeSpecifier(ref specifier) => Some(specifier),
+            _ => None,
+        }).peekable();
+
+        // Specifiers without any type specifier are left for the caller to reject, as in `static x;`
+        if type_specifiers.peek().is_some() {
+            if let Err(err) = Type::from_specifiers(type_specifiers) {
+                return IResult::Error(error_position!(err.into_nom(), start));
+            }
+        }
+
+        IResult::Done(input, specifiers)
+    }
+
+    named!(
+        #[doc = "Matches one or more storage class, type and function specifiers and type qualifiers"],
+        pub declaration_specifiers<&[u8], Vec<Spanned<DeclarationSpecifier>>, ParseError>,
+        apply!(specifiers, SpecifierKind::Declaration)
+    );
+
+    named!(
+        #[doc = "Matches one or more type specifiers and qualifiers"],
+        pub specifier_qualifier_list<&[u8], Vec<Spanned<DeclarationSpecifier>>, ParseError>,
+        apply!(specifiers, SpecifierKind::SpecifierQualifier)
+    );
+
+    named!(
+        #[doc = "Matches a `*` and any qualifiers after it"],
+        pub pointer<&[u8], Spanned<Pointer>, ParseError>,
+        spanned!(wse!(do_parse!(
+            punct!('*')                         >>
+            qualifiers: many0!(type_qualifier)  >>
+            (Pointer { qualifiers: qualifiers })
+        )))
+    );
+
+    // The `*` in `[*]`
+    named!(unspecified_variable_length<&[u8], char, ParseError>,
+        wse!(terminated!(punct!('*'), peek!(punct!(']')))));
+
+    named!(specified_array_size<&[u8], ArraySize, ParseError>, wse!(alt_complete!(
+        unspecified_variable_length => { |_| ArraySize::VariableUnspecified } |
+        assignment_expression       => { |e| ArraySize::Expression(e) }
+    )));
+
+    named!(array_size<&[u8], ArraySize, ParseError>, map!(
+        opt!(specified_array_size),
+        |size: Option<ArraySize>| size.unwrap_or(ArraySize::Unspecified)
+    ));
+
+    named!(array_declarator<&[u8], ArrayDeclarator, ParseError>, wse!(do_parse!(
+        punct!('[')                                 >>
+        static_before: opt!(keyword!("static"))     >>
+        qualifiers: many0!(type_qualifier)          >>
+        static_after: opt!(keyword!("static"))      >>
+        size: array_size                            >>
+        punct!(']')                                 >>
+        (ArrayDeclarator {
+            qualifiers: qualifiers,
+            is_static: static_before.is_some() || static_after.is_some(),
+            size: size,
+        })
+    )));
+
+    named!(
+        #[doc = "Matches a single parameter declaration with an optional concrete or abstract declarator"],
+        pub parameter_declaration<&[u8], Spanned<ParameterDeclaration>, ParseError>,
+        spanned!(wse!(do_parse!(
+            specifiers: declaration_specifiers                  >>
+            declarator: apply!(declarator_with, DeclaratorKind::Any) >>
+            (ParameterDeclaration {
+                specifiers: specifiers,
+                declarator: if declarator.is_empty() { None } else { Some(declarator) },
+            })
+        )))
+    );
+
+    named!(parameter_type_list<&[u8], ParameterList, ParseError>, wse!(do_parse!(
+        parameters: separated_nonempty_list!(punct!(','), parameter_declaration) >>
+        variadic: opt!(complete!(wse!(preceded!(punct!(','), op!("...")))))       >>
+        (ParameterList::Prototype {
+            parameters: parameters,
+            variadic: variadic.is_some(),
+        })
+    )));
+
+    named!(non_empty_parameter_list<&[u8], ParameterList, ParseError>, wse!(alt_complete!(
+        parameter_type_list |
+        identifier_list => { |idents| ParameterList::Identifiers(idents) }
+    )));
+
+    named!(
+        #[doc = "Matches the contents of a function declarator's parentheses"],
+        pub parameter_list<&[u8], ParameterList, ParseError>,
+        map!(
+            opt!(non_empty_parameter_list),
+            |params: Option<ParameterList>| params.unwrap_or(ParameterList::Identifiers(Vec::new()))
+        )
+    );
+
+    enum DeclaratorSuffix {
+        Array(ArrayDeclarator),
+        Function(ParameterList),
+    }
+
+    named!(declarator_suffix<&[u8], DeclaratorSuffix, ParseError>, wse!(alt_complete!(
+        array_declarator                                        => { |a| DeclaratorSuffix::Array(a) } |
+        delimited!(punct!('('), parameter_list, punct!(')'))    => { |p| DeclaratorSuffix::Function(p) }
+    )));
+
+    /// Whether the identifier of a declarator is required, forbidden or optional
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum DeclaratorKind {
+        Concrete,
+        Abstract,
+        Any,
+    }
+
+    fn direct_declarator_base(input: &[u8], kind: DeclaratorKind) -> IResult<&[u8], DirectDeclarator, ParseError> {
+        if kind != DeclaratorKind::Abstract {
+            if let IResult::Done(rest, ident) = identifier(input) {
+                return IResult::Done(rest, DirectDeclarator::Identifier(ident));
+            }
+        }
+
+        let nested = wse!(input, delimited!(
+            punct!('('),
+            apply!(declarator_with, kind),
+            punct!(')')
+        ));
+
+        match nested {
+            IResult::Done(rest, ref declarator) if !declarator.is_empty() => {
+                return IResult::Done(rest, DirectDeclarator::Parenthesized(Box::new(declarator.clone())));
+            },
+            _ => {}
+        }
+
+        if kind == DeclaratorKind::Concrete {
+            IResult::Error(error_position!(ParseError::InvalidDeclarator.into_nom(), input))
+        } else {
+            IResult::Done(input, DirectDeclarator::Abstract)
+        }
+    }
+
+    fn direct_declarator_with(input: &[u8], kind: DeclaratorKind) -> IResult<&[u8], Spanned<DirectDeclarator>, ParseError> {
+        let (mut input, mut direct) = try_parse!(input, spanned!(apply!(direct_declarator_base, kind)));
+
+        while let IResult::Done(rest, suffix) = spanned!(input, declarator_suffix) {
+            let span = if direct.span.is_empty() { suffix.span } else { direct.span.to(suffix.span) };
+
+            direct = Spanned::new(match suffix.node {
+                DeclaratorSuffix::Array(array) => DirectDeclarator::Array(Box::new(direct), array),
+                DeclaratorSuffix::Function(params) => DirectDeclarator::Function(Box::new(direct), params),
+            }, span);
+
+            input = rest;
+        }
+
+        IResult::Done(input, direct)
+    }
+
+    /// Matches a declarator of the given kind
+    ///
+    /// Abstract and optional declarators may match nothing at all.
+    pub fn declarator_with(input: &[u8], kind: DeclaratorKind) -> IResult<&[u8], Spanned<Declarator>, ParseError> {
+        add_return_error!(input,
+            ParseError::InvalidDeclarator.into_nom(),
+            spanned!(wse!(do_parse!(
+                pointers: many0!(pointer)                       >>
+                direct: apply!(direct_declarator_with, kind)    >>
+                (Declarator { pointers: pointers, direct: direct })
+            )))
+        )
+    }
+
+    named!(
+        #[doc = "Matches a declarator that declares an identifier"],
+        pub declarator<&[u8], Spanned<Declarator>, ParseError>,
+        apply!(declarator_with, DeclaratorKind::Concrete)
+    );
+
+    named!(
+        #[doc = "Matches a possibly empty declarator without an identifier"],
+        pub abstract_declarator<&[u8], Spanned<Declarator>, ParseError>,
+        apply!(declarator_with, DeclaratorKind::Abstract)
+    );
+
+    named!(
+        #[doc = "Matches a type name, as used in casts and `sizeof`"],
+        pub type_name<&[u8], Spanned<TypeName>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidTypeName.into_nom(),
+            spanned!(wse!(do_parse!(
+                specifiers: specifier_qualifier_list    >>
+                declarator: abstract_declarator         >>
+                (TypeName {
+                    specifiers: specifiers,
+                    declarator: if declarator.is_empty() { None } else { Some(declarator) },
+                })
+            )))
+        )
+    );
+
+    named!(init_declarator<&[u8], Spanned<InitDeclarator>, ParseError>, spanned!(wse!(do_parse!(
+        declarator: declarator                                                  >>
+        initializer: opt!(complete!(preceded!(punct!('='), initializer)))    >>
+        (InitDeclarator {
+            declarator: declarator,
+            initializer: initializer,
+        })
+    ))));
+
+    named!(declaration_raw<&[u8], Spanned<Declaration>, ParseError>, add_return_error!(
+        ParseError::InvalidDeclaration.into_nom(),
+        spanned!(wse!(do_parse!(
+            specifiers: declaration_specifiers                              >>
+            declarators: separated_list!(punct!(','), init_declarator)      >>
+            punct!(';')                                                     >>
+            (Declaration {
+                specifiers: specifiers,
+                declarators: declarators,
+                doc: Vec::new(),
+            })
+        )))
+    ));
+
+    /// Declares the enumeration constants of every enum defined by `specifiers`, including those
+    /// of enums nested in struct and union members, as ordinary identifiers hiding typedef names
+    ///
+    /// Only called once the declaration or function definition the specifiers belong to has matched,
+    /// so specifiers matched by an alternative that fails do not declare anything.
+    pub fn declare_enumerators(specifiers: &[Spanned<DeclarationSpecifier>]) {
+        use ::parser::structure::Member;
+
+        for specifier in specifiers {
+            match specifier.node {
+                DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Enum(ref e)) => {
+                    for enumerator in e.enumerators.iter().flat_map(|e| e) {
+                        scope::declare(&enumerator.ident, Symbol::Ordinary);
+                    }
+                },
+                DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Struct(ref variant)) => {
+                    for member in variant.data().members.iter().flat_map(|m| m) {
+                        if let Member::Field(ref field) = member.node {
+                            declare_enumerators(&field.ty.specifiers);
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Matches declaration specifiers followed by a possibly empty list of declarators and `;`
+    ///
+    /// The declared names are added to the current scope, either as typedef names
+    /// or as ordinary identifiers hiding any typedef of the same name.
+    pub fn declaration(input: &[u8]) -> IResult<&[u8], Spanned<Declaration>, ParseError> {
+        let (rest, mut declaration) = try_parse!(input, declaration_raw);
+
+        declaration.node.doc = span::doc_comments(declaration.span);
+
+        declare_enumerators(&declaration.specifiers);
+
+        let symbol = if declaration.is_typedef() { Symbol::Typedef } else { Symbol::Ordinary };
+
+        for init_declarator in &declaration.declarators {
+            if let Some(ident) = init_declarator.declarator.ident() {
+                scope::declare(ident, symbol);
+            }
+        }
+
+        IResult::Done(rest, declaration)
+    }
+
+    named!(static_assert_message<&[u8], Spanned<Lit>, ParseError>, wse!(preceded!(punct!(','), spanned!(string_literal))));
+
+    // The message can only be left out since C23
+    fn optional_static_assert_message(input: &[u8]) -> IResult<&[u8], Option<Spanned<Lit>>, ParseError> {
+        if keywords::standard() >= Standard::C23 {
+            opt!(input, complete!(static_assert_message))
+        } else {
+            map!(input, static_assert_message, Some)
+        }
+    }
+
+    named!(
+        #[doc = "Matches `_Static_assert(condition, \"message\");` or, since C23, `static_assert(condition);`, at any scope"],
+        pub static_assert_declaration<&[u8], Spanned<StaticAssert>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidStaticAssert.into_nom(),
+            spanned!(wse!(do_parse!(
+                alt_complete!(keyword!("_Static_assert") | keyword!("static_assert")) >>
+                punct!('(')                         >>
+                condition: constant_expression      >>
+                message: optional_static_assert_message >>
+                punct!(')')                         >>
+                punct!(';')                         >>
+                (StaticAssert {
+                    condition: condition,
+                    message: message,
+                })
+            )))
+        )
+    );
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn int() -> Spanned<DeclarationSpecifier> {
+            DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Int).into()
+        }
+
+        fn ident(name: &str) -> Spanned<DirectDeclarator> {
+            DirectDeclarator::Identifier(Ident::from(name)).into()
+        }
+
+        fn int_parameter() -> Spanned<ParameterDeclaration> {
+            ParameterDeclaration { specifiers: vec![int()], declarator: None }.into()
+        }
+
+        fn pointer(qualifiers: Vec<TypeQualifier>) -> Spanned<Pointer> {
+            Pointer { qualifiers: qualifiers }.into()
+        }
+
+        fn int_literal(value: i32) -> Spanned<Expression> {
+            Expression::Literal(::parser::lit::Lit::Integer(
+                ::parser::lit::IntegerLiteral::Signed(::parser::lit::SignedIntegerValue::Int(value))
+            )).into()
+        }
+
+        #[test]
+        fn test_declaration() {
+            assert_eq!(declaration(b"static const unsigned long x = 1, *const y[];"), IResult::Done(&[] as &[u8], Declaration {
+                specifiers: vec![
+                    DeclarationSpecifier::StorageClass(StorageClassSpecifier::Static).into(),
+                    DeclarationSpecifier::TypeQualifier(TypeQualifier::Const).into(),
+                    DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Unsigned).into(),
+                    DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Long).into(),
+                ],
+                declarators: vec![
+                    InitDeclarator {
+                        declarator: Declarator { pointers: vec![], direct: ident("x") }.into(),
+                        initializer: Some(Initializer::Expression(int_literal(1)).into()),
+                    }.into(),
+                    InitDeclarator {
+                        declarator: Declarator {
+                            pointers: vec![pointer(vec![TypeQualifier::Const])],
+                            direct: DirectDeclarator::Array(Box::new(ident("y")), ArrayDeclarator {
+                                qualifiers: vec![],
+                                is_static: false,
+                                size: ArraySize::Unspecified,
+                            }).into(),
+                        }.into(),
+                        initializer: None,
+                    }.into(),
+                ],
+                doc: vec![],
+            }.into()));
+        }
+
+        #[test]
+        fn test_typedef_name_declaration() {
+            scope::isolated(|| {
+                assert!(declaration(b"size_t len;").is_err());
+
+                assert!(declaration(b"typedef unsigned long size_t;").is_done());
+
+                assert_eq!(declaration(b"size_t len;"), IResult::Done(&[] as &[u8], Declaration {
+                    specifiers: vec![DeclarationSpecifier::TypeSpecifier(TypeSpecifier::TypedefName(Ident::from("size_t"))).into()],
+                    declarators: vec![InitDeclarator {
+                        declarator: Declarator { pointers: vec![], direct: ident("len") }.into(),
+                        initializer: None,
+                    }.into()],
+                    doc: vec![],
+                }.into()));
+            });
+
+            assert!(declaration(b"size_t len;").is_err());
+        }
+
+        #[test]
+        fn test_initializer_lists() {
+            match declaration(b"struct entry table[] = { [0] = { .name = \"a\", .flags = 1 }, [1 ... 3] = {}, }, *last = 0;") {
+                IResult::Done(rest, d) => {
+                    assert_eq!(rest, b"");
+
+                    let table = d.declarators[0].initializer.as_ref().unwrap();
+
+                    assert_eq!(table.as_list().unwrap().len(), 2);
+                    assert_eq!(table.as_list().unwrap()[1].initializer.as_list(), Some(&[][..]));
+                    assert_eq!(d.declarators[1].initializer.as_ref().unwrap().as_expression(), Some(&int_literal(0)));
+                },
+                res => panic!("Unexpected result {:?}", res)
+            }
+
+            assert!(declaration(b"int a[] = { [0] 1 };").is_err());
+        }
+
+        #[test]
+        fn test_keyword_prefixed_identifiers() {
+            match declaration(b"const int constant, inline_fn, *restrictive;") {
+                IResult::Done(rest, d) => {
+                    assert_eq!(rest, b"");
+                    assert_eq!(d.specifiers.len(), 2);
+
+                    let names: Vec<String> = d.declarators.iter().map(|d| d.declarator.ident().unwrap().to_string()).collect();
+
+                    assert_eq!(names, vec!["constant", "inline_fn", "restrictive"]);
+                },
+                res => panic!("Unexpected result {:?}", res)
+            }
+
+            assert!(declaration(b"int return;").is_err());
+        }
+
+        #[test]
+        fn test_base_type() {
+            use ::parser::types::Type;
+
+            fn base_type(source: &[u8]) -> QualifiedType {
+                match declaration(source) {
+                    IResult::Done(_, d) => d.base_type().unwrap(),
+                    res => panic!("Unexpected result {:?}", res)
+                }
+            }
+
+            assert_eq!(base_type(b"long unsigned int long x;").ty, Type::UnsignedLongLong);
+            assert_eq!(base_type(b"static unsigned x;").ty, Type::UnsignedInt);
+            assert_eq!(base_type(b"signed x;").ty, Type::Int);
+            assert_eq!(base_type(b"char signed c;").ty, Type::SignedChar);
+            assert_eq!(base_type(b"char c;").ty, Type::Char);
+            assert_eq!(base_type(b"int short s;").ty, Type::Short);
+            assert_eq!(base_type(b"double _Complex long z;").ty, Type::LongDoubleComplex);
+
+            let qualified = base_type(b"const volatile long const l;");
+
+            assert_eq!(qualified.ty, Type::Long);
+            assert!(qualified.qualifiers.is_const && qualified.qualifiers.is_volatile);
+            assert!(!qualified.qualifiers.is_restrict);
+
+            for &(source, spelling) in &[
+                (&b"short double x;"[..], "short double"),
+                (&b"long long long x;"[..], "long long long"),
+                (&b"unsigned signed x;"[..], "unsigned signed"),
+                (&b"void int x;"[..], "void int"),
+                (&b"_Complex x;"[..], "_Complex"),
+                (&b"unsigned float x;"[..], "unsigned float"),
+                (&b"struct s int x;"[..], "struct s int"),
+            ] {
+                match declaration(source) {
+                    IResult::Error(err) => {
+                        let diagnostic = ::parser::error::Diagnostic::from_nom(source, &err);
+
+                        assert_eq!(diagnostic.error(), Some(&ParseError::InvalidTypeSpecifiers(spelling.to_owned())));
+                        assert_eq!(diagnostic.offset, 0);
+                    },
+                    res => panic!("Unexpected result {:?}", res)
+                }
+            }
+        }
+
+        #[test]
+        fn test_function_pointer_declarator() {
+            // void (*)(int)
+            let handler = ParameterDeclaration {
+                specifiers: vec![DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Void).into()],
+                declarator: Some(Declarator {
+                    pointers: vec![],
+                    direct: DirectDeclarator::Function(
+                        Box::new(DirectDeclarator::Parenthesized(Box::new(Declarator {
+                            pointers: vec![pointer(vec![])],
+                            direct: DirectDeclarator::Abstract.into(),
+                        }.into())).into()),
+                        ParameterList::Prototype { parameters: vec![int_parameter()], variadic: false }
+                    ).into(),
+                }.into()),
+            };
+
+            // *const signal(int, void (*)(int))
+            let signal = Declarator {
+                pointers: vec![pointer(vec![TypeQualifier::Const])],
+                direct: DirectDeclarator::Function(
+                    Box::new(ident("signal")),
+                    ParameterList::Prototype { parameters: vec![int_parameter(), handler.into()], variadic: false }
+                ).into(),
+            };
+
+            assert_eq!(declaration(b"int (*const signal(int, void (*)(int)))(int);"), IResult::Done(&[] as &[u8], Declaration {
+                specifiers: vec![int()],
+                declarators: vec![InitDeclarator {
+                    declarator: Declarator {
+                        pointers: vec![],
+                        direct: DirectDeclarator::Function(
+                            Box::new(DirectDeclarator::Parenthesized(Box::new(signal.into())).into()),
+                            ParameterList::Prototype { parameters: vec![int_parameter()], variadic: false }
+                        ).into(),
+                    }.into(),
+                    initializer: None,
+                }.into()],
+                doc: vec![],
+            }.into()));
+        }
+
+        #[test]
+        fn test_array_and_variadic_declarators() {
+            let res = declarator(b"f(const char *fmt, int a[static restrict 10], int b[*], ...)");
+
+            match res {
+                IResult::Done(rest, ref d) => {
+                    assert_eq!(rest, b"");
+
+                    let (parameters, variadic) = match d.direct.node {
+                        DirectDeclarator::Function(_, ParameterList::Prototype { ref parameters, variadic }) => (parameters, variadic),
+                        ref other => panic!("Unexpected declarator {:?}", other)
+                    };
+
+                    assert!(variadic);
+                    assert_eq!(parameters.len(), 3);
+
+                    match parameters[1].declarator.as_ref().map(|d| &d.direct.node) {
+                        Some(&DirectDeclarator::Array(_, ref array)) => {
+                            assert!(array.is_static);
+                            assert_eq!(array.qualifiers, vec![TypeQualifier::Restrict]);
+                        },
+                        ref other => panic!("Unexpected declarator {:?}", other)
+                    }
+
+                    match parameters[2].declarator.as_ref().map(|d| &d.direct.node) {
+                        Some(&DirectDeclarator::Array(_, ref array)) => {
+                            assert_eq!(array.size, ArraySize::VariableUnspecified);
+                        },
+                        ref other => panic!("Unexpected declarator {:?}", other)
+                    }
+                },
+                ref other => panic!("Unexpected result {:?}", other)
+            }
+        }
+
+        #[test]
+        fn test_type_name() {
+            assert_eq!(type_name(b"char *[4]"), IResult::Done(&[] as &[u8], TypeName {
+                specifiers: vec![DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Char).into()],
+                declarator: Some(Declarator {
+                    pointers: vec![pointer(vec![])],
+                    direct: DirectDeclarator::Array(Box::new(DirectDeclarator::Abstract.into()), ArrayDeclarator {
+                        qualifiers: vec![],
+                        is_static: false,
+                        size: ArraySize::Expression(int_literal(4)),
+                    }).into(),
+                }.into()),
+            }.into()));
+        }
+
+        #[test]
+        fn test_alignment_specifiers() {
+            use ::parser::keywords::{with_standard, Standard};
+
+            let aligned = |source: &[u8]| match declaration(source) {
+                IResult::Done(rest, d) => {
+                    assert_eq!(rest, b"");
+
+                    d.specifiers.iter().any(|s| match s.node {
+                        DeclarationSpecifier::Alignment(AlignmentSpecifier::Expression(ref e)) => *e == int_literal(8),
+                        _ => false,
+                    })
+                },
+                _ => false,
+            };
+
+            assert!(aligned(b"_Alignas(8) int x;"));
+            assert!(aligned(b"alignas(8) int x;"));
+
+            with_standard(Standard::C11, || {
+                assert!(aligned(b"_Alignas(8) int x;"));
+                assert!(!aligned(b"alignas(8) int x;"));
+            });
+        }
+
+        #[test]
+        fn test_declarator_spans() {
+            use ::parser::span::{self, FileId};
+
+            let source = b"int *p, a[10] = 0;";
+
+            span::with_source(FileId(0), source, || {
+                let d = declaration(source).unwrap().1;
+
+                let text = |span: ::parser::span::Span| &source[span.start..span.end];
+
+                assert_eq!(text(d.span), &source[..]);
+                assert_eq!(text(d.specifiers[0].span), b"int");
+                assert_eq!(text(d.declarators[0].span), b"*p");
+                assert_eq!(text(d.declarators[1].span), b"a[10] = 0");
+                assert_eq!(text(d.declarators[1].declarator.span), b"a[10]");
+                assert_eq!(text(d.declarators[1].declarator.direct.span), b"a[10]");
+                assert_eq!(text(d.declarators[1].initializer.as_ref().unwrap().span), b"0");
+            });
+        }
+    }
+}
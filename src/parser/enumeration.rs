@@ -1,10 +1,208 @@
 use super::ident::Ident;
+use super::expression::Expression;
+use super::constant::{self, Constant, EvaluationError};
+use super::span::Spanned;
+use super::comments::Comment;
 
-#[derive(Debug, Clone, PartialEq, Hash)]
-pub struct Enumerator;
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enumerator {
+    pub ident: Ident,
+    /// Explicit `= value` initializer, if any
+    pub value: Option<Spanned<Expression>>,
+    /// Documentation comments, which are only kept when parsing with `ParseOptions::keep_comments`
+    pub doc: Vec<Spanned<Comment>>,
+}
 
-#[derive(Debug, Clone, PartialEq, Hash)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Enum {
-    ident: Ident,
-    enumerators: Vec<Enumerator>,
-}
\ No newline at end of file
+    pub ident: Option<Ident>,
+    /// `None` for forward declarations and references such as `enum tag`
+    pub enumerators: Option<Vec<Spanned<Enumerator>>>,
+}
+
+impl Enum {
+    /// Computes the value of every enumerator
+    ///
+    /// Enumerators without an initializer are one greater than the previous one,
+    /// and the first defaults to zero. See `resolve_values_with` for resolving
+    /// identifiers declared outside of this enum.
+    pub fn resolve_values(&self) -> Result<Vec<(Ident, i64)>, EvaluationError> {
+        self.resolve_values_with(&|_| None)
+    }
+
+    /// Like `resolve_values`, but initializers may also refer to constants given by `lookup`
+    pub fn resolve_values_with(&self, lookup: &Fn(&Ident) -> Option<Constant>) -> Result<Vec<(Ident, i64)>, EvaluationError> {
+        let mut values: Vec<(Ident, i64)> = Vec::new();
+
+        let mut next = Some(0i64);
+
+        if let Some(ref enumerators) = self.enumerators {
+            for enumerator in enumerators {
+                let value = match enumerator.value {
+                    Some(ref expr) => {
+                        let value = constant::evaluate(expr, &|ident| {
+                            values.iter()
+                                .rev()
+                                .find(|&&(ref name, _)| name == ident)
+                                .map(|&(_, value)| Constant::Signed(value))
+                                .or_else(|| lookup(ident))
+                        })?;
+
+                        value.as_i64().ok_or(EvaluationError::Overflow)?
+                    },
+                    None => next.ok_or(EvaluationError::Overflow)?,
+                };
+
+                next = value.checked_add(1);
+
+                values.push((enumerator.ident.clone(), value));
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+pub mod parsing {
+    use nom::*;
+    use ::wse;
+    use super::*;
+
+    use ::parser::error::ParseError;
+    use ::parser::ident::parsing::identifier;
+    use ::parser::expression::parsing::constant_expression;
+    use ::parser::span::{self, Spanned};
+
+    named!(
+        #[doc = "Matches an enumeration constant and its optional value"],
+        pub enumerator<&[u8], Spanned<Enumerator>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidEnumerator.into_nom(),
+            map!(spanned!(wse!(do_parse!(
+                ident: identifier >>
+                value: opt!(complete!(wse!(preceded!(punct!('='), constant_expression)))) >>
+                (Enumerator { ident: ident, value: value, doc: Vec::new() })
+            ))), |mut enumerator: Spanned<Enumerator>| {
+                enumerator.node.doc = span::doc_comments(enumerator.span);
+                enumerator
+            })
+        )
+    );
+
+    named!(enumerator_list<&[u8], Vec<Spanned<Enumerator>>, ParseError>, wse!(delimited!(
+        punct!('{'),
+        terminated!(
+            separated_nonempty_list!(punct!(','), enumerator),
+            opt!(punct!(','))
+        ),
+        punct!('}')
+    )));
+
+    /// Matches an enum definition, forward declaration or reference
+    pub fn enum_specifier(input: &[u8]) -> IResult<&[u8], Enum, ParseError> {
+        let (rest, (_, ident, enumerators)) = try_parse!(input, add_return_error!(
+            ParseError::InvalidEnum.into_nom(),
+            wse!(tuple!(
+                keyword!("enum"),
+                opt!(identifier),
+                opt!(enumerator_list)
+            ))
+        ));
+
+        // Either a tag or an enumerator list is required
+        if ident.is_none() && enumerators.is_none() {
+            return IResult::Error(error_position!(ParseError::InvalidEnum.into_nom(), input));
+        }
+
+        // The enumeration constants are declared by `declaration::parsing::declare_enumerators`
+        // once the enclosing declaration has matched, since this may still be backtracked over
+
+        IResult::Done(rest, Enum { ident: ident, enumerators: enumerators })
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        fn parse(source: &[u8]) -> Enum {
+            match enum_specifier(source) {
+                IResult::Done(rest, e) => {
+                    assert_eq!(rest, b"");
+                    e
+                },
+                res => panic!("Unexpected result {:?}", res)
+            }
+        }
+
+        #[test]
+        fn test_enum_specifier() {
+            let e = parse(b"enum color { RED, GREEN = 4, BLUE, }");
+
+            assert_eq!(e.ident, Some(Ident::from("color")));
+            assert_eq!(e.enumerators.as_ref().map(|e| e.len()), Some(3));
+
+            assert_eq!(parse(b"enum color"), Enum { ident: Some(Ident::from("color")), enumerators: None });
+
+            assert!(enum_specifier(b"enum { }").is_err());
+        }
+
+        #[test]
+        fn test_enumerators_hide_typedefs() {
+            use ::parser::scope::{self, Symbol};
+            use ::parser::span::FileId;
+            use ::parser::translation_unit::{ExternalDeclaration, parse_partial};
+
+            scope::isolated(|| {
+                let t = Ident::from("T");
+
+                scope::declare(&t, Symbol::Typedef);
+
+                // Matching the specifier alone declares nothing
+                assert!(enum_specifier(b"enum { T }").is_done());
+                assert!(scope::is_typedef(&t));
+            });
+
+            // The first declaration fails after its enum has matched, so `T` still names a type after it
+            let (unit, diagnostics) = parse_partial(FileId::default(), b"typedef int T; enum { T } +; T a; enum { T } b; T * c;");
+
+            assert_eq!(diagnostics.len(), 2);
+
+            let names: Vec<_> = unit.items.iter().map(|item| match *item {
+                ExternalDeclaration::Declaration(ref d) => d.declarators[0].declarator.ident().unwrap().to_string(),
+                ref other => panic!("Unexpected item {:?}", other)
+            }).collect();
+
+            assert_eq!(names, vec!["T", "a", "b"]);
+        }
+
+        #[test]
+        fn test_resolve_values() {
+            let e = parse(b"enum { A, B = 10, C, D = A + C, E = -1, F }");
+
+            assert_eq!(e.resolve_values(), Ok(vec![
+                (Ident::from("A"), 0),
+                (Ident::from("B"), 10),
+                (Ident::from("C"), 11),
+                (Ident::from("D"), 11),
+                (Ident::from("E"), -1),
+                (Ident::from("F"), 0),
+            ]));
+
+            let e = parse(b"enum { A = (int)1, B = sizeof(int), C = (unsigned char)-1 }");
+
+            assert_eq!(e.resolve_values(), Ok(vec![
+                (Ident::from("A"), 1),
+                (Ident::from("B"), 4),
+                (Ident::from("C"), 255),
+            ]));
+
+            let e = parse(b"enum { X = EXTERNAL * 2, Y = UNKNOWN }");
+
+            assert_eq!(e.resolve_values(), Err(EvaluationError::UnknownIdentifier(Ident::from("EXTERNAL"))));
+            assert_eq!(
+                e.resolve_values_with(&|ident| if *ident == "EXTERNAL" { Some(Constant::Signed(3)) } else { None }),
+                Err(EvaluationError::UnknownIdentifier(Ident::from("UNKNOWN")))
+            );
+        }
+    }
+}
@@ -1,10 +1,14 @@
+use std::cmp;
 use std::fmt::{Display, Debug, Formatter, Result as FmtResult};
 
 use nom;
 
+use super::span;
+
 #[derive(Debug, Clone, PartialEq, Hash)]
 pub enum ParseError {
     InvalidIdentifier,
+    InvalidTypedefName,
     InvalidString,
     InvalidStringLiteral,
     InvalidEscapeSequence,
@@ -12,11 +16,45 @@ pub enum ParseError {
     InvalidUnicodeValue,
     InvalidUnicodeValueDetail(String),
     InvalidStringPrefix,
+    /// Adjacent string literals with different prefixes, such as `u8"a" L"b"`
+    InvalidStringConcatenation,
     InvalidPunctuation(char),
     InvalidKeyword(&'static str),
     InvalidStringLikeLiteral,
     InvalidIntegerLikeLiteral,
+    InvalidPredefinedConstant,
     InvalidCharacterLiteral,
+    InvalidIntegerLiteral,
+    /// The value of an integer literal does not fit in any type allowed by its suffix
+    IntegerLiteralTooLarge,
+    InvalidFloatLiteral,
+    InvalidOperator(&'static str),
+    InvalidExpression,
+    InvalidPrimaryExpression,
+    InvalidGenericSelection,
+    InvalidStatement,
+    InvalidStorageClassSpecifier,
+    InvalidFunctionSpecifier,
+    InvalidTypeSpecifier,
+    /// A combination of type specifiers that names no type, such as `short double`
+    InvalidTypeSpecifiers(String),
+    InvalidTypeQualifier,
+    InvalidTypeName,
+    InvalidDeclarator,
+    InvalidInitializer,
+    InvalidDesignator,
+    InvalidDeclaration,
+    InvalidStaticAssert,
+    InvalidStructOrUnion,
+    InvalidMemberDeclaration,
+    InvalidEnum,
+    InvalidEnumerator,
+    InvalidFunctionDefinition,
+    InvalidExternalDeclaration,
+    InvalidComment,
+    /// Input that does not form any token, such as `@`
+    InvalidToken,
+    UnexpectedEndOfInput,
 }
 
 impl ParseError {
@@ -29,6 +67,71 @@ impl ParseError {
             Some(value)
         } else { None }
     }
+
+    /// Describes the construct that was being parsed, such as "a declaration"
+    pub fn description(&self) -> String {
+        let description = match *self {
+            ParseError::InvalidIdentifier => "an identifier",
+            ParseError::InvalidTypedefName => "a typedef name",
+            ParseError::InvalidString |
+            ParseError::InvalidStringLiteral => "a string literal",
+            ParseError::InvalidEscapeSequence |
+            ParseError::InvalidEscapeSequenceDetail(_) => "an escape sequence",
+            ParseError::InvalidUnicodeValue |
+            ParseError::InvalidUnicodeValueDetail(_) => "a unicode character value",
+            ParseError::InvalidStringPrefix => "a string prefix",
+            ParseError::InvalidStringConcatenation => "a string literal with a compatible prefix",
+            ParseError::InvalidPunctuation(expected) => return format!("`{}`", expected),
+            ParseError::InvalidKeyword(expected) => return format!("`{}`", expected),
+            ParseError::InvalidStringLikeLiteral => "a string or character literal",
+            ParseError::InvalidIntegerLikeLiteral => "an integer-like literal",
+            ParseError::InvalidPredefinedConstant => "`true`, `false` or `nullptr`",
+            ParseError::InvalidCharacterLiteral => "a character literal",
+            ParseError::InvalidIntegerLiteral |
+            ParseError::IntegerLiteralTooLarge => "an integer literal",
+            ParseError::InvalidFloatLiteral => "a floating literal",
+            ParseError::InvalidOperator(expected) => return format!("`{}`", expected),
+            ParseError::InvalidExpression => "an expression",
+            ParseError::InvalidPrimaryExpression => "a primary expression",
+            ParseError::InvalidGenericSelection => "a generic selection",
+            ParseError::InvalidStatement => "a statement",
+            ParseError::InvalidStorageClassSpecifier => "a storage class specifier",
+            ParseError::InvalidFunctionSpecifier => "a function specifier",
+            ParseError::InvalidTypeSpecifier => "a type specifier",
+            ParseError::InvalidTypeSpecifiers(_) => "a valid combination of type specifiers",
+            ParseError::InvalidTypeQualifier => "a type qualifier",
+            ParseError::InvalidTypeName => "a type name",
+            ParseError::InvalidDeclarator => "a declarator",
+            ParseError::InvalidInitializer => "an initializer",
+            ParseError::InvalidDesignator => "a designator",
+            ParseError::InvalidDeclaration => "a declaration",
+            ParseError::InvalidStaticAssert => "a static assertion",
+            ParseError::InvalidStructOrUnion => "a struct or union specifier",
+            ParseError::InvalidMemberDeclaration => "a member declaration",
+            ParseError::InvalidEnum => "an enum specifier",
+            ParseError::InvalidEnumerator => "an enumerator",
+            ParseError::InvalidFunctionDefinition => "a function definition",
+            ParseError::InvalidExternalDeclaration => "a declaration or function definition",
+            ParseError::InvalidComment => "a comment",
+            ParseError::InvalidToken => "a token",
+            ParseError::UnexpectedEndOfInput => "more input",
+        };
+
+        description.to_owned()
+    }
+
+    /// A human readable message for when this is the innermost error of a diagnostic
+    pub fn message(&self) -> String {
+        match *self {
+            ParseError::InvalidEscapeSequenceDetail(ref detail) => format!("invalid escape sequence `{}`", detail),
+            ParseError::InvalidUnicodeValueDetail(ref detail) => format!("invalid unicode character value `{}`", detail),
+            ParseError::InvalidTypeSpecifiers(ref detail) => format!("invalid combination of type specifiers `{}`", detail),
+            ParseError::InvalidStringConcatenation => "cannot concatenate string literals with different prefixes".to_owned(),
+            ParseError::IntegerLiteralTooLarge => "integer literal is too large for any integer type".to_owned(),
+            ParseError::UnexpectedEndOfInput => "unexpected end of input".to_owned(),
+            _ => format!("expected {}", self.description()),
+        }
+    }
 }
 
 impl Display for ParseError {
@@ -37,10 +140,284 @@ impl Display for ParseError {
             ParseError::InvalidKeyword(expected) => {
                 write!(f, r#"InvalidKeyword - Expected "{}""#, expected)
             },
+            ParseError::InvalidOperator(expected) => {
+                write!(f, r#"InvalidOperator - Expected "{}""#, expected)
+            },
             ParseError::InvalidPunctuation(expected) => {
                 write!(f, "InvalidPunctuation - Expected {}", expected)
             },
             _ => Debug::fmt(self, f)
         }
     }
-}
\ No newline at end of file
+}
+
+/// A single failed parse, located by byte offset into the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Byte offset into the source where the innermost error occurred
+    pub offset: usize,
+    /// The chain of errors, from the outermost context to the innermost cause
+    pub errors: Vec<ParseError>,
+}
+
+impl Diagnostic {
+    pub fn new(offset: usize, error: ParseError) -> Diagnostic {
+        Diagnostic { offset: offset, errors: vec![error] }
+    }
+
+    /// Collects the custom errors of a nom error chain,
+    /// where `source` is the complete input that was given to the parser.
+    pub fn from_nom(source: &[u8], err: &nom::Err<&[u8], ParseError>) -> Diagnostic {
+        let mut errors = Vec::new();
+        let mut remaining = source.len();
+
+        let mut err = err;
+
+        loop {
+            let (kind, position, next) = match *err {
+                nom::Err::Code(ref kind) => (kind, None, None),
+                nom::Err::Node(ref kind, ref next) => (kind, None, Some(next)),
+                nom::Err::Position(ref kind, position) => (kind, Some(position), None),
+                nom::Err::NodePosition(ref kind, position, ref next) => (kind, Some(position), Some(next)),
+            };
+
+            if let Some(error) = ParseError::from_nom(kind) {
+                errors.push(error.clone());
+            }
+
+            // Positions that do not point into `source` cannot be located, so keep the last one that did
+            if let Some(position) = position.filter(|position| is_within(source, position)) {
+                remaining = position.len();
+            }
+
+            match next {
+                Some(next) => err = &**next,
+                None => break,
+            }
+        }
+
+        Diagnostic {
+            offset: source.len() - remaining,
+            errors: errors,
+        }
+    }
+
+    /// The innermost and most specific error, if any
+    pub fn error(&self) -> Option<&ParseError> {
+        self.errors.last()
+    }
+
+    /// Renders the diagnostic in the style of rustc, with the offending source line
+    /// underlined and the enclosing constructs listed as notes.
+    ///
+    /// `name` is only used for display, and `source` must be the input the diagnostic was produced from.
+    ///
+    /// ```text
+    /// error: expected an expression
+    ///  --> example.c:2:9
+    ///   |
+    /// 2 | int b = ;
+    ///   |         ^
+    ///   |
+    ///   = note: while parsing a declaration
+    /// ```
+    pub fn render(&self, name: &str, source: &[u8]) -> String {
+        use std::fmt::Write;
+
+        // Errors are often reported before the whitespace preceding the offending token
+        let offset = cmp::min(self.offset, source.len());
+        let offset = offset + source[offset..].iter().take_while(|c| c.is_ascii_whitespace()).count();
+
+        let line_start = source[..offset].iter().rposition(|&c| c == b'\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..].iter().position(|&c| c == b'\n' || c == b'\r').map_or(source.len(), |i| offset + i);
+
+        let line = source[..line_start].iter().filter(|&&c| c == b'\n').count() + 1;
+
+        let text = &source[line_start..line_end];
+        let prefix = &text[..offset - line_start];
+
+        // Counted in characters, like the columns of spans
+        let column = span::char_count(prefix) + 1;
+
+        // One space per character, keeping tabs so the caret lines up however they are displayed
+        let padding: String = prefix.iter()
+            .filter(|&&c| !span::is_continuation_byte(c))
+            .map(|&c| if c == b'\t' { '\t' } else { ' ' })
+            .collect();
+
+        let gutter = line.to_string().len();
+        let blank = " ".repeat(gutter);
+
+        let mut out = String::new();
+
+        let message = match self.error() {
+            Some(error) => error.message(),
+            None => "parse error".to_owned(),
+        };
+
+        // Writing to a String cannot fail
+        let _ = writeln!(out, "error: {}", message);
+        let _ = writeln!(out, "{}--> {}:{}:{}", blank, name, line, column);
+        let _ = writeln!(out, "{} |", blank);
+        let _ = writeln!(out, "{} | {}", line, String::from_utf8_lossy(text));
+        let _ = writeln!(out, "{} | {}{}", blank, padding, "^".repeat(token_len(&source[offset..line_end])));
+
+        if self.errors.len() > 1 {
+            let _ = writeln!(out, "{} |", blank);
+
+            for error in self.errors[..self.errors.len() - 1].iter().rev() {
+                let _ = writeln!(out, "{} = note: while parsing {}", blank, error.description());
+            }
+        }
+
+        out
+    }
+}
+
+/// Whether `slice` lies within `source`, as the remaining input of a parser given `source` does
+fn is_within(source: &[u8], slice: &[u8]) -> bool {
+    let start = source.as_ptr() as usize;
+    let position = slice.as_ptr() as usize;
+
+    position >= start && position + slice.len() == start + source.len()
+}
+
+/// Length of the token at the start of `rest` to underline, which is at least one character
+fn token_len(rest: &[u8]) -> usize {
+    let word = rest.iter().take_while(|&&c| c == b'_' || c.is_ascii_alphanumeric()).count();
+
+    cmp::max(word, 1)
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.error() {
+            Some(error) => write!(f, "{} at byte {}", error, self.offset),
+            None => write!(f, "Parse error at byte {}", self.offset),
+        }
+    }
+}
+
+/// All diagnostics produced while parsing a source file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics(Vec::new())
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.0.push(diagnostic)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<Diagnostic> {
+        self.0.iter()
+    }
+
+    /// Renders every diagnostic with `Diagnostic::render`, separated by blank lines
+    pub fn render(&self, name: &str, source: &[u8]) -> String {
+        self.0.iter()
+            .map(|diagnostic| diagnostic.render(name, source))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Diagnostics {
+        Diagnostics(vec![diagnostic])
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for diagnostic in &self.0 {
+            writeln!(f, "{}", diagnostic)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let source = b"int a;\nint b = ;\n";
+
+        let diagnostic = Diagnostic {
+            offset: 14,
+            errors: vec![ParseError::InvalidExternalDeclaration, ParseError::InvalidDeclaration, ParseError::InvalidExpression],
+        };
+
+        assert_eq!(diagnostic.render("example.c", source), concat!(
+            "error: expected an expression\n",
+            " --> example.c:2:9\n",
+            "  |\n",
+            "2 | int b = ;\n",
+            "  |         ^\n",
+            "  |\n",
+            "  = note: while parsing a declaration\n",
+            "  = note: while parsing a declaration or function definition\n",
+        ));
+    }
+
+    #[test]
+    fn test_render_end_of_input() {
+        let source = b"int main(void) {\n\treturn";
+
+        let diagnostic = Diagnostic::new(source.len(), ParseError::UnexpectedEndOfInput);
+
+        assert_eq!(diagnostic.render("main.c", source), concat!(
+            "error: unexpected end of input\n",
+            " --> main.c:2:8\n",
+            "  |\n",
+            "2 | \treturn\n",
+            "  | \t      ^\n",
+        ));
+
+        let diagnostic = Diagnostic::new(17, ParseError::InvalidKeyword("break"));
+
+        assert!(diagnostic.render("main.c", source).contains("  | \t^^^^^^\n"));
+    }
+
+    #[test]
+    fn test_render_multibyte() {
+        let source = "char *s = \"é\"; int = 1;\n".as_bytes();
+
+        // Byte 20 is `=`, which is only the 20th character because `é` takes two bytes
+        let rendered = Diagnostic::new(20, ParseError::InvalidIdentifier).render("utf8.c", source);
+
+        assert!(rendered.contains(" --> utf8.c:1:20\n"));
+        assert!(rendered.contains(&format!("  | {}^\n", " ".repeat(19))));
+    }
+
+    #[test]
+    fn test_from_nom_foreign_position() {
+        use nom::ErrorKind;
+
+        let source = b"int a";
+        let other = b"some other, longer input";
+
+        let err = nom::Err::NodePosition(
+            ErrorKind::Custom(ParseError::InvalidDeclaration), &source[4..],
+            Box::new(nom::Err::Position(ErrorKind::Custom(ParseError::InvalidIdentifier), &other[..]))
+        );
+
+        let diagnostic = Diagnostic::from_nom(source, &err);
+
+        assert_eq!(diagnostic.offset, 4);
+        assert_eq!(diagnostic.errors, vec![ParseError::InvalidDeclaration, ParseError::InvalidIdentifier]);
+    }
+}
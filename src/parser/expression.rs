@@ -1,11 +1,528 @@
-#[derive(Debug, Clone, PartialEq, Hash)]
-pub struct Expression {
+use super::ident::Ident;
+use super::lit::Lit;
+use super::operator::{AssignmentOperator, BinaryOperator, UnaryOperator};
+use super::declaration::TypeName;
+use super::initializer::DesignatedInitializer;
+use super::span::Spanned;
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Identifier(Ident),
+    Literal(Lit),
+    /// `a[b]`
+    Index(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    /// `f(a, b)`
+    Call(Box<Spanned<Expression>>, Vec<Spanned<Expression>>),
+    /// `a.b`
+    Member(Box<Spanned<Expression>>, Ident),
+    /// `a->b`
+    PointerMember(Box<Spanned<Expression>>, Ident),
+    /// `a++`
+    PostIncrement(Box<Spanned<Expression>>),
+    /// `a--`
+    PostDecrement(Box<Spanned<Expression>>),
+    /// `++a`
+    PreIncrement(Box<Spanned<Expression>>),
+    /// `--a`
+    PreDecrement(Box<Spanned<Expression>>),
+    Unary(UnaryOperator, Box<Spanned<Expression>>),
+    /// `sizeof a`
+    SizeOf(Box<Spanned<Expression>>),
+    /// `sizeof(type-name)`
+    SizeOfType(Box<Spanned<TypeName>>),
+    /// `_Alignof(type-name)` or C23 `alignof(type-name)`
+    AlignOf(Box<Spanned<TypeName>>),
+    /// `(type-name) a`
+    Cast(Box<Spanned<TypeName>>, Box<Spanned<Expression>>),
+    /// `(type-name) { ... }`
+    CompoundLiteral(Box<Spanned<TypeName>>, Vec<Spanned<DesignatedInitializer>>),
+    /// `_Generic(a, int: b, default: c)`
+    Generic(Box<Spanned<Expression>>, Vec<Spanned<GenericAssociation>>),
+    Binary(BinaryOperator, Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    /// `a ? b : c`
+    Conditional(Box<Spanned<Expression>>, Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Assignment(AssignmentOperator, Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    /// `a, b, c`
+    Comma(Vec<Spanned<Expression>>),
+}
+
+/// A single case of a `_Generic` selection
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenericAssociation {
+    /// `type-name: expression`
+    Type(Spanned<TypeName>, Spanned<Expression>),
+    /// `default: expression`
+    Default(Spanned<Expression>),
+}
+
+impl Expression {
+    /// Returns true if the expression can appear on the left side of an assignment
+    /// according to the grammar, which only permits unary-expressions there.
+    pub fn is_unary(&self) -> bool {
+        match *self {
+            Expression::Cast(..) |
+            Expression::Binary(..) |
+            Expression::Conditional(..) |
+            Expression::Assignment(..) |
+            Expression::Comma(..) => false,
+            _ => true,
+        }
+    }
 }
 
 pub mod parsing {
     use nom::*;
+    use ::wse;
     use super::*;
 
+    use ::parser::error::ParseError;
+    use ::parser::ident::parsing::identifier;
+    use ::parser::lit::parsing::{string, char_literal, float_literal, integer_literal, integer_like_literal, predefined_constant};
+    use ::parser::operator::parsing::{assignment, unary_operator, binary_operator};
+    use ::parser::declaration::parsing::type_name;
+    use ::parser::initializer::parsing::initializer_list;
+    use ::parser::span::Spanned;
+
+    // Floats first, since their integer part would also match as an integer
+    named!(constant<&[u8], Lit, ParseError>, alt_complete!(
+        float_literal        |
+        integer_literal      |
+        char_literal         |
+        integer_like_literal |
+        predefined_constant
+    ));
+
+    // Only names declared with `typedef` begin a type name, so `(T)` is a type while `(x)` is an expression
+    named!(parenthesized_type_name<&[u8], Spanned<TypeName>, ParseError>,
+        wse!(delimited!(punct!('('), type_name, punct!(')'))));
+
+    named!(compound_literal<&[u8], Expression, ParseError>, wse!(do_parse!(
+        ty: parenthesized_type_name     >>
+        initializers: initializer_list  >>
+        (Expression::CompoundLiteral(Box::new(ty), initializers))
+    )));
+
+    named!(generic_association<&[u8], Spanned<GenericAssociation>, ParseError>, spanned!(wse!(alt_complete!(
+        separated_pair!(keyword!("default"), punct!(':'), assignment_expression) => {
+            |(_, e)| GenericAssociation::Default(e)
+        } |
+        separated_pair!(type_name, punct!(':'), assignment_expression) => {
+            |(ty, e)| GenericAssociation::Type(ty, e)
+        }
+    ))));
+
+    named!(generic_selection_raw<&[u8], (Spanned<Expression>, Vec<Spanned<GenericAssociation>>), ParseError>, wse!(do_parse!(
+        keyword!("_Generic")        >>
+        punct!('(')                 >>
+        controlling: assignment_expression >>
+        punct!(',')                 >>
+        associations: separated_nonempty_list!(punct!(','), generic_association) >>
+        punct!(')')                 >>
+        (controlling, associations)
+    )));
+
+    /// Matches `_Generic(expression, type-name: expression, ..., default: expression)`
+    ///
+    /// At most one `default` association is allowed.
+    pub fn generic_selection(input: &[u8]) -> IResult<&[u8], Expression, ParseError> {
+        let (rest, (controlling, associations)) = try_parse!(input, add_return_error!(
+            ParseError::InvalidGenericSelection.into_nom(),
+            generic_selection_raw
+        ));
+
+        let defaults = associations.iter().filter(|a| match a.node {
+            GenericAssociation::Default(_) => true,
+            GenericAssociation::Type(..) => false,
+        }).count();
+
+        if defaults > 1 {
+            return IResult::Error(error_position!(ParseError::InvalidGenericSelection.into_nom(), input));
+        }
+
+        IResult::Done(rest, Expression::Generic(Box::new(controlling), associations))
+    }
+
+    named!(primary<&[u8], Expression, ParseError>, wse!(alt_complete!(
+        constant    => { |lit| Expression::Literal(lit) } |
+        string      => { |lit| Expression::Literal(lit) } |
+        identifier  => { |ident| Expression::Identifier(ident) } |
+        generic_selection |
+        compound_literal |
+        delimited!(punct!('('), expression, punct!(')')) => { |e: Spanned<Expression>| e.node }
+    )));
+
+    named!(
+        #[doc = "Matches identifiers, constants, string literals, generic selections, compound literals and parenthesized expressions"],
+        pub primary_expression<&[u8], Spanned<Expression>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidPrimaryExpression.into_nom(),
+            spanned!(primary)
+        )
+    );
+
+    enum Postfix {
+        Index(Spanned<Expression>),
+        Call(Vec<Spanned<Expression>>),
+        Member(Ident),
+        PointerMember(Ident),
+        Increment,
+        Decrement,
+    }
+
+    named!(postfix<&[u8], Postfix, ParseError>, wse!(alt_complete!(
+        delimited!(punct!('['), expression, punct!(']'))    => { |e| Postfix::Index(e) } |
+        delimited!(
+            punct!('('),
+            separated_list!(punct!(','), assignment_expression),
+            punct!(')')
+        )                                                   => { |args| Postfix::Call(args) } |
+        preceded!(punct!('.'), identifier)                  => { |ident| Postfix::Member(ident) } |
+        preceded!(op!("->"), identifier)                    => { |ident| Postfix::PointerMember(ident) } |
+        op!("++")                                           => { |_| Postfix::Increment } |
+        op!("--")                                           => { |_| Postfix::Decrement }
+    )));
+
+    named!(
+        #[doc = "Matches a primary expression followed by any number of postfix operators"],
+        pub postfix_expression<&[u8], Spanned<Expression>, ParseError>,
+        wse!(do_parse!(
+            primary: primary_expression         >>
+            ops:     many0!(spanned!(postfix))  >> ({
+                ops.into_iter().fold(primary, |expr, op| {
+                    let span = expr.span.to(op.span);
+                    let expr = Box::new(expr);
+
+                    Spanned::new(match op.node {
+                        Postfix::Index(index) => Expression::Index(expr, Box::new(index)),
+                        Postfix::Call(args) => Expression::Call(expr, args),
+                        Postfix::Member(ident) => Expression::Member(expr, ident),
+                        Postfix::PointerMember(ident) => Expression::PointerMember(expr, ident),
+                        Postfix::Increment => Expression::PostIncrement(expr),
+                        Postfix::Decrement => Expression::PostDecrement(expr),
+                    }, span)
+                })
+            })
+        ))
+    );
+
+    named!(prefix_expression<&[u8], Expression, ParseError>, wse!(alt_complete!(
+        preceded!(op!("++"), unary_expression)      => { |e| Expression::PreIncrement(Box::new(e)) } |
+        preceded!(op!("--"), unary_expression)      => { |e| Expression::PreDecrement(Box::new(e)) } |
+        pair!(unary_operator, cast_expression)      => { |(op, e)| Expression::Unary(op, Box::new(e)) } |
+        // `sizeof (T){ ... }` is the size of a compound literal
+        preceded!(
+            keyword!("sizeof"),
+            terminated!(parenthesized_type_name, not!(punct!('{')))
+        )                                           => { |ty| Expression::SizeOfType(Box::new(ty)) } |
+        preceded!(keyword!("sizeof"), unary_expression) => { |e| Expression::SizeOf(Box::new(e)) } |
+        preceded!(
+            alt_complete!(keyword!("_Alignof") | keyword!("alignof")),
+            parenthesized_type_name
+        )                                           => { |ty| Expression::AlignOf(Box::new(ty)) }
+    )));
+
+    named!(
+        #[doc = "Matches prefix increments, decrements, unary operators, `sizeof` and `_Alignof`"],
+        pub unary_expression<&[u8], Spanned<Expression>, ParseError>,
+        wse!(alt_complete!(
+            spanned!(prefix_expression) |
+            postfix_expression
+        ))
+    );
+
+    named!(cast<&[u8], Expression, ParseError>, wse!(do_parse!(
+        ty: parenthesized_type_name >>
+        expr: cast_expression       >>
+        (Expression::Cast(Box::new(ty), Box::new(expr)))
+    )));
+
+    named!(
+        #[doc = "Matches a unary expression preceded by any number of casts"],
+        pub cast_expression<&[u8], Spanned<Expression>, ParseError>,
+        wse!(alt_complete!(
+            spanned!(cast) |
+            unary_expression
+        ))
+    );
+
+    /// Precedence climbing over all left-associative binary operators
+    ///
+    /// Only operators binding at least as tightly as `min_precedence` are consumed.
+    pub fn binary_expression(input: &[u8], min_precedence: u8) -> IResult<&[u8], Spanned<Expression>, ParseError> {
+        let (mut input, mut lhs) = try_parse!(input, cast_expression);
+
+        loop {
+            let (rest, op) = match binary_operator(input) {
+                IResult::Done(rest, op) if op.precedence() >= min_precedence => (rest, op),
+                _ => break,
+            };
+
+            let (rest, rhs) = try_parse!(rest, apply!(binary_expression, op.precedence() + 1));
+
+            let span = lhs.span.to(rhs.span);
+
+            lhs = Spanned::new(Expression::Binary(op, Box::new(lhs), Box::new(rhs)), span);
+            input = rest;
+        }
+
+        IResult::Done(input, lhs)
+    }
+
+    named!(
+        #[doc = "Matches any binary expression and the ternary conditional operator"],
+        pub conditional_expression<&[u8], Spanned<Expression>, ParseError>,
+        wse!(do_parse!(
+            condition: apply!(binary_expression, 1) >>
+            branches: opt!(complete!(wse!(do_parse!(
+                punct!('?')                     >>
+                then: expression                >>
+                punct!(':')                     >>
+                otherwise: conditional_expression >>
+                (then, otherwise)
+            )))) >> ({
+                match branches {
+                    Some((then, otherwise)) => {
+                        let span = condition.span.to(otherwise.span);
+
+                        Spanned::new(Expression::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)), span)
+                    },
+                    None => condition
+                }
+            })
+        ))
+    );
+
+    named!(
+        #[doc = "Matches an expression that must be constant, such as array sizes and case labels"],
+        pub constant_expression<&[u8], Spanned<Expression>, ParseError>,
+        call!(conditional_expression)
+    );
+
+    /// Matches assignments, which are right-associative, or a conditional-expression
+    pub fn assignment_expression(input: &[u8]) -> IResult<&[u8], Spanned<Expression>, ParseError> {
+        let (input, lhs) = try_parse!(input, conditional_expression);
+
+        if lhs.is_unary() {
+            if let IResult::Done(rest, op) = assignment(input) {
+                let (rest, rhs) = try_parse!(rest, assignment_expression);
+
+                let span = lhs.span.to(rhs.span);
 
-}
\ No newline at end of file
+                return IResult::Done(rest, Spanned::new(Expression::Assignment(op, Box::new(lhs), Box::new(rhs)), span));
+            }
+        }
+
+        IResult::Done(input, lhs)
+    }
+
+    named!(
+        #[doc = "Matches a full expression, including the comma operator"],
+        pub expression<&[u8], Spanned<Expression>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidExpression.into_nom(),
+            map!(
+                separated_nonempty_list!(punct!(','), assignment_expression),
+                |mut exprs: Vec<Spanned<Expression>>| {
+                    if exprs.len() == 1 {
+                        exprs.pop().unwrap()
+                    } else {
+                        let span = exprs[0].span.to(exprs[exprs.len() - 1].span);
+
+                        Spanned::new(Expression::Comma(exprs), span)
+                    }
+                }
+            )
+        )
+    );
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::lit::*;
+        use ::parser::operator::*;
+
+        fn ident(name: &str) -> Box<Spanned<Expression>> {
+            boxed(Expression::Identifier(Ident::from(name)))
+        }
+
+        fn int(value: i32) -> Box<Spanned<Expression>> {
+            boxed(Expression::Literal(Lit::Integer(IntegerLiteral::Signed(SignedIntegerValue::Int(value)))))
+        }
+
+        fn boxed(e: Expression) -> Box<Spanned<Expression>> {
+            Box::new(e.into())
+        }
+
+        fn expr(source: &[u8]) -> Expression {
+            match expression(source) {
+                IResult::Done(rest, e) => {
+                    assert_eq!(rest, b"");
+                    e.node
+                },
+                res => panic!("Unexpected result {:?}", res)
+            }
+        }
+
+        #[test]
+        fn test_precedence() {
+            assert_eq!(expr(b"a + b * 2"), Expression::Binary(
+                BinaryOperator::Arithmetic(Operator::ADD),
+                ident("a"),
+                boxed(Expression::Binary(BinaryOperator::Arithmetic(Operator::MUL), ident("b"), int(2)))
+            ));
+
+            assert_eq!(expr(b"a - b - c"), Expression::Binary(
+                BinaryOperator::Arithmetic(Operator::SUB),
+                boxed(Expression::Binary(BinaryOperator::Arithmetic(Operator::SUB), ident("a"), ident("b"))),
+                ident("c")
+            ));
+
+            assert_eq!(expr(b"a && b == c"), Expression::Binary(
+                BinaryOperator::Logical(LogicalOperator::AND),
+                ident("a"),
+                boxed(Expression::Binary(BinaryOperator::Relational(RelationalOperator::EQ), ident("b"), ident("c")))
+            ));
+        }
+
+        #[test]
+        fn test_assignment() {
+            assert_eq!(expr(b"a = b += c"), Expression::Assignment(
+                AssignmentOperator(None),
+                ident("a"),
+                boxed(Expression::Assignment(AssignmentOperator(Some(Operator::ADD)), ident("b"), ident("c")))
+            ));
+
+            assert_eq!(expr(b"a <<= & &b"), Expression::Assignment(
+                AssignmentOperator(Some(Operator::LEFT)),
+                ident("a"),
+                boxed(Expression::Unary(UnaryOperator::ADDR, boxed(Expression::Unary(UnaryOperator::ADDR, ident("b")))))
+            ));
+
+            assert_eq!(expr(b"x ? y : z, w"), Expression::Comma(vec![
+                Expression::Conditional(ident("x"), ident("y"), ident("z")).into(),
+                *ident("w"),
+            ]));
+        }
+
+        #[test]
+        fn test_postfix_unary() {
+            assert_eq!(expr(b"-f(a, 1)[0]->b++"), Expression::Unary(
+                UnaryOperator::MINUS,
+                boxed(Expression::PostIncrement(boxed(Expression::PointerMember(
+                    boxed(Expression::Index(
+                        boxed(Expression::Call(ident("f"), vec![*ident("a"), *int(1)])),
+                        int(0)
+                    )),
+                    Ident::from("b")
+                ))))
+            ));
+
+            assert_eq!(expr(b"a+++b"), Expression::Binary(
+                BinaryOperator::Arithmetic(Operator::ADD),
+                boxed(Expression::PostIncrement(ident("a"))),
+                ident("b")
+            ));
+        }
+
+        #[test]
+        fn test_generic_selection() {
+            match expr(b"_Generic(x + 1, int: f, const char *: g, default: 0)(x)") {
+                Expression::Call(callee, args) => {
+                    assert_eq!(args.len(), 1);
+
+                    match callee.node {
+                        Expression::Generic(controlling, associations) => {
+                            assert_eq!(controlling.node, Expression::Binary(BinaryOperator::Arithmetic(Operator::ADD), ident("x"), int(1)));
+                            assert_eq!(associations.len(), 3);
+
+                            match associations[1].node {
+                                GenericAssociation::Type(ref ty, ref e) => {
+                                    assert!(ty.declarator.is_some());
+                                    assert_eq!(*e, *ident("g"));
+                                },
+                                ref other => panic!("Unexpected association {:?}", other)
+                            }
+
+                            assert_eq!(associations[2].node, GenericAssociation::Default(*int(0)));
+                        },
+                        other => panic!("Unexpected expression {:?}", other)
+                    }
+                },
+                other => panic!("Unexpected expression {:?}", other)
+            }
+
+            assert!(generic_selection(b"_Generic(x, default: 1, default: 2)").is_err());
+            assert!(generic_selection(b"_Generic(x)").is_err());
+            assert!(generic_selection(b"_Generic(x, y: 1)").is_err());
+        }
+
+        #[test]
+        fn test_type_names() {
+            use ::parser::declaration::{DeclarationSpecifier, TypeName};
+            use ::parser::types::TypeSpecifier;
+            use ::parser::scope::{self, Symbol};
+
+            fn type_name(specifier: TypeSpecifier) -> Box<Spanned<TypeName>> {
+                Box::new(TypeName {
+                    specifiers: vec![DeclarationSpecifier::TypeSpecifier(specifier).into()],
+                    declarator: None,
+                }.into())
+            }
+
+            let t = || type_name(TypeSpecifier::TypedefName(Ident::from("T")));
+
+            scope::scoped(|| {
+                scope::declare(&Ident::from("T"), Symbol::Typedef);
+
+                assert_eq!(expr(b"(T)*p"), Expression::Cast(t(), boxed(Expression::Unary(UnaryOperator::DEREF, ident("p")))));
+                assert_eq!(expr(b"(x)*p"), Expression::Binary(BinaryOperator::Arithmetic(Operator::MUL), ident("x"), ident("p")));
+
+                assert_eq!(expr(b"sizeof (T)"), Expression::SizeOfType(t()));
+                assert_eq!(expr(b"sizeof (x)"), Expression::SizeOf(ident("x")));
+                assert_eq!(expr(b"sizeof(T) * 2"), Expression::Binary(
+                    BinaryOperator::Arithmetic(Operator::MUL),
+                    boxed(Expression::SizeOfType(t())),
+                    int(2)
+                ));
+                assert_eq!(expr(b"_Alignof(int)"), Expression::AlignOf(type_name(TypeSpecifier::Int)));
+                assert_eq!(expr(b"(long)(unsigned)x"), Expression::Cast(
+                    type_name(TypeSpecifier::Long),
+                    boxed(Expression::Cast(type_name(TypeSpecifier::Unsigned), ident("x")))
+                ));
+
+                match expr(b"(struct point){1, 2}.x") {
+                    Expression::Member(literal, member) => {
+                        assert_eq!(member, Ident::from("x"));
+
+                        match literal.node {
+                            Expression::CompoundLiteral(ty, initializers) => {
+                                assert_eq!(ty.specifiers.len(), 1);
+                                assert_eq!(initializers.len(), 2);
+                            },
+                            other => panic!("Unexpected expression {:?}", other)
+                        }
+                    },
+                    other => panic!("Unexpected expression {:?}", other)
+                }
+
+                match expr(b"sizeof (T){0}") {
+                    Expression::SizeOf(e) => match e.node {
+                        Expression::CompoundLiteral(..) => {},
+                        other => panic!("Unexpected expression {:?}", other)
+                    },
+                    other => panic!("Unexpected expression {:?}", other)
+                }
+
+                // A cast is not a unary expression, so cannot be assigned to
+                assert!(match expression(b"(T)x = 1") {
+                    IResult::Done(rest, _) => !rest.is_empty(),
+                    _ => true,
+                });
+            });
+
+            // Once out of scope, `T` is an ordinary identifier again
+            assert_eq!(expr(b"(T)*p"), Expression::Binary(BinaryOperator::Arithmetic(Operator::MUL), ident("T"), ident("p")));
+        }
+    }
+}
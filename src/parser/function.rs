@@ -1,16 +1,332 @@
+use super::declaration::{Declaration, Declarator, DeclarationSpecifier, DirectDeclarator, ParameterList, TypeName};
+use super::ident::Ident;
+use super::types::TypeSpecifier;
+use super::statement::Statement;
+use super::span::Spanned;
+use super::comments::Comment;
+
 #[derive(Debug, Clone, Copy, PartialEq, Hash)]
 pub enum FunctionSpecifier {
     Inline,
     NoReturn,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDefinition {
+    pub specifiers: Vec<Spanned<DeclarationSpecifier>>,
+    pub declarator: Spanned<Declarator>,
+    /// Old-style parameter declarations between the declarator and the body, as in `int f(a) char a; { ... }`
+    pub declarations: Vec<Spanned<Declaration>>,
+    /// Always a `Statement::Compound`
+    pub body: Spanned<Statement>,
+    /// Documentation comments, which are only kept when parsing with `ParseOptions::keep_comments`
+    pub doc: Vec<Spanned<Comment>>,
+}
+
+/// A single parameter of a function signature
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    /// `None` for unnamed parameters of a prototype
+    pub ident: Option<Ident>,
+    pub ty: TypeName,
+}
+
+/// The name, return type and parameters of a function definition, independent of how they were written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub ident: Ident,
+    pub return_type: TypeName,
+    /// Empty for both `f(void)` and `f()`
+    pub parameters: Vec<Parameter>,
+    pub variadic: bool,
+}
+
+// The parameters of the innermost function declarator belong to the function itself,
+// as in `int (*f(int a))(char)`
+fn parameters(direct: &DirectDeclarator) -> Option<&ParameterList> {
+    match *direct {
+        DirectDeclarator::Function(ref inner, ref params) => parameters(inner).or(Some(params)),
+        DirectDeclarator::Array(ref inner, _) => parameters(inner),
+        DirectDeclarator::Parenthesized(ref declarator) => parameters(&declarator.direct),
+        _ => None,
+    }
+}
+
+// Only the specifiers that contribute to a type, leaving out storage classes such as `static`
+fn type_specifiers(specifiers: &[Spanned<DeclarationSpecifier>]) -> Vec<Spanned<DeclarationSpecifier>> {
+    specifiers.iter().filter(|s| match s.node {
+        DeclarationSpecifier::TypeSpecifier(_) | DeclarationSpecifier::TypeQualifier(_) => true,
+        _ => false,
+    }).cloned().collect()
+}
+
+// Removes the innermost function declarator, leaving the declarator of the return type,
+// so `(*f(int a))(char)` becomes `(*f)(char)`
+fn without_parameters(direct: &Spanned<DirectDeclarator>) -> Spanned<DirectDeclarator> {
+    let node = match direct.node {
+        DirectDeclarator::Function(ref inner, ref params) => {
+            if parameters(inner).is_some() {
+                DirectDeclarator::Function(Box::new(without_parameters(inner)), params.clone())
+            } else {
+                return (**inner).clone();
+            }
+        },
+        DirectDeclarator::Array(ref inner, ref array) => {
+            DirectDeclarator::Array(Box::new(without_parameters(inner)), array.clone())
+        },
+        DirectDeclarator::Parenthesized(ref declarator) if declarator.pointers.is_empty() => {
+            return without_parameters(&declarator.direct);
+        },
+        DirectDeclarator::Parenthesized(ref declarator) => {
+            DirectDeclarator::Parenthesized(Box::new(Spanned::new(Declarator {
+                pointers: declarator.pointers.clone(),
+                direct: without_parameters(&declarator.direct),
+            }, declarator.span)))
+        },
+        ref other => other.clone(),
+    };
+
+    Spanned::new(node, direct.span)
+}
+
+impl FunctionDefinition {
+    pub fn ident(&self) -> Option<&Ident> {
+        self.declarator.ident()
+    }
+
+    /// The parameter list of the function itself, rather than that of any function type it returns
+    pub fn parameters(&self) -> Option<&ParameterList> {
+        parameters(&self.declarator.direct)
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        match self.parameters() {
+            Some(&ParameterList::Prototype { variadic, .. }) => variadic,
+            _ => false,
+        }
+    }
+
+    /// Collects the name, return type and parameter types of the function
+    ///
+    /// Old-style parameters take their types from `declarations`, and are `int` if not declared there.
+    pub fn signature(&self) -> Option<Signature> {
+        let ident = match self.ident() {
+            Some(ident) => ident.clone(),
+            None => return None,
+        };
+
+        let return_declarator = Declarator {
+            pointers: self.declarator.pointers.clone(),
+            direct: without_parameters(&self.declarator.direct),
+        };
+
+        let (_, return_type) = TypeName::from_declarator(type_specifiers(&self.specifiers), Spanned::new(return_declarator, self.declarator.span));
+
+        let parameters = match self.parameters() {
+            Some(&ParameterList::Prototype { ref parameters, .. }) => {
+                let is_void = |ty: &TypeName| ty.declarator.is_none() && ty.specifiers.len() == 1 &&
+                    ty.specifiers[0].node == DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Void);
+
+                let parameters: Vec<Parameter> = parameters.iter().map(|parameter| {
+                    let specifiers = type_specifiers(&parameter.specifiers);
+
+                    let (ident, ty) = match parameter.declarator {
+                        Some(ref declarator) => TypeName::from_declarator(specifiers, declarator.clone()),
+                        None => (None, TypeName { specifiers: specifiers, declarator: None }),
+                    };
+
+                    Parameter { ident: ident, ty: ty }
+                }).collect();
+
+                // `(void)` declares that there are no parameters
+                if parameters.len() == 1 && parameters[0].ident.is_none() && is_void(&parameters[0].ty) {
+                    Vec::new()
+                } else {
+                    parameters
+                }
+            },
+            Some(&ParameterList::Identifiers(ref idents)) => idents.iter().map(|ident| {
+                let declared = self.declarations.iter()
+                    .flat_map(|declaration| declaration.declarators.iter().map(move |d| (declaration, d)))
+                    .find(|&(_, d)| d.declarator.ident() == Some(ident));
+
+                let ty = match declared {
+                    Some((declaration, d)) => TypeName::from_declarator(type_specifiers(&declaration.specifiers), d.declarator.clone()).1,
+                    None => TypeName {
+                        specifiers: vec![DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Int).into()],
+                        declarator: None,
+                    },
+                };
+
+                Parameter { ident: Some(ident.clone()), ty: ty }
+            }).collect(),
+            None => Vec::new(),
+        };
+
+        Some(Signature {
+            ident: ident,
+            return_type: return_type,
+            parameters: parameters,
+            variadic: self.is_variadic(),
+        })
+    }
+}
+
 pub mod parsing {
     use nom::*;
+    use ::wse;
     use ::parser::error::ParseError;
     use super::*;
 
-    named!(pub function_specifier<FunctionSpecifier>, ws!(alt_complete!(
-        tag!("inline")      => {|_| FunctionSpecifier::Inline   } |
-        tag!("_Noreturn")   => {|_| FunctionSpecifier::NoReturn }
-    )));
-}
\ No newline at end of file
+    use ::parser::scope::{self, Symbol};
+    use ::parser::span;
+    use ::parser::declaration::parsing::{declaration, declaration_specifiers, declarator, declare_enumerators};
+    use ::parser::statement::parsing::compound_statement;
+
+    named!(pub function_specifier<&[u8], FunctionSpecifier, ParseError>, add_return_error!(
+        ParseError::InvalidFunctionSpecifier.into_nom(),
+        wse!(alt_complete!(
+            keyword!("inline")      => {|_| FunctionSpecifier::Inline   } |
+            keyword!("_Noreturn")   => {|_| FunctionSpecifier::NoReturn }
+        ))
+    ));
+
+    // Only declarators of function type can have a body
+    fn function_declarator(input: &[u8]) -> IResult<&[u8], Spanned<Declarator>, ParseError> {
+        match declarator(input) {
+            IResult::Done(rest, declarator) => match declarator.direct.node {
+                DirectDeclarator::Function(..) => IResult::Done(rest, declarator),
+                _ => IResult::Error(error_position!(ParseError::InvalidDeclarator.into_nom(), input)),
+            },
+            res => res,
+        }
+    }
+
+    named!(parameter_declarations<&[u8], Vec<Spanned<Declaration>>, ParseError>, many0!(declaration));
+
+    // Parameters are visible in the body and hide typedefs of the same name,
+    // and old-style parameters are declared between the declarator and the body
+    fn function_body<'a>(input: &'a [u8], declarator: &Declarator) -> IResult<&'a [u8], (Vec<Spanned<Declaration>>, Spanned<Statement>), ParseError> {
+        scope::scoped(|| {
+            let old_style = match parameters(&declarator.direct) {
+                Some(&ParameterList::Prototype { ref parameters, .. }) => {
+                    for ident in parameters.iter().filter_map(|p| p.declarator.as_ref().and_then(|d| d.ident())) {
+                        scope::declare(ident, Symbol::Ordinary);
+                    }
+
+                    false
+                },
+                Some(&ParameterList::Identifiers(ref idents)) => {
+                    for ident in idents {
+                        scope::declare(ident, Symbol::Ordinary);
+                    }
+
+                    !idents.is_empty()
+                },
+                None => false,
+            };
+
+            wse!(input, do_parse!(
+                declarations: cond!(old_style, parameter_declarations) >>
+                body: compound_statement                               >>
+                (declarations.unwrap_or_default(), body)
+            ))
+        })
+    }
+
+    named!(
+        #[doc = "Matches a function definition with its body"],
+        pub function_definition<&[u8], Spanned<FunctionDefinition>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidFunctionDefinition.into_nom(),
+            map!(spanned!(wse!(do_parse!(
+                specifiers: declaration_specifiers                      >>
+                declarator: function_declarator                         >>
+                body: apply!(function_body, &declarator)                >> ({
+                    let (declarations, body) = body;
+
+                    declare_enumerators(&specifiers);
+
+                    if let Some(ident) = declarator.ident() {
+                        scope::declare(ident, Symbol::Ordinary);
+                    }
+
+                    FunctionDefinition {
+                        specifiers: specifiers,
+                        declarator: declarator,
+                        declarations: declarations,
+                        body: body,
+                        doc: Vec::new(),
+                    }
+                })
+            ))), |mut function: Spanned<FunctionDefinition>| {
+                function.node.doc = span::doc_comments(function.span);
+                function
+            })
+        )
+    );
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::types::{Type, TypeSpecifier};
+        use ::parser::ident::Ident;
+        use ::parser::translation_unit::parse_translation_unit;
+
+        #[test]
+        fn test_signatures() {
+            let unit = parse_translation_unit(b"
+                typedef int T;
+
+                static unsigned long count(const char *s, ...) { return 0; }
+
+                int old(a, b, c) register char *a; T b; { return *a + b + c; }
+
+                int (*handler(int sig))(void) { return 0; }
+
+                void nothing(void) {}
+
+                int after(T x) { return x; }
+            ").unwrap();
+
+            let signatures: Vec<Signature> = unit.functions().iter().map(|f| f.signature().unwrap()).collect();
+
+            let names: Vec<String> = signatures.iter().map(|s| s.ident.to_string()).collect();
+
+            assert_eq!(names, vec!["count", "old", "handler", "nothing", "after"]);
+
+            let count = &signatures[0];
+
+            assert!(count.variadic);
+            assert_eq!(count.return_type.base_type().unwrap().ty, Type::UnsignedLong);
+            assert_eq!(count.return_type.declarator, None);
+            assert_eq!(count.parameters.len(), 1);
+            assert_eq!(count.parameters[0].ident, Some(Ident::from("s")));
+            assert!(count.parameters[0].ty.base_type().unwrap().qualifiers.is_const);
+
+            let old = &signatures[1];
+
+            assert_eq!(unit.functions()[1].declarations.len(), 2);
+            assert!(!old.variadic);
+
+            let types: Vec<Type> = old.parameters.iter().map(|p| p.ty.base_type().unwrap().ty).collect();
+
+            assert_eq!(types, vec![Type::Char, Type::TypedefName(Ident::from("T")), Type::Int]);
+            assert_eq!(old.parameters[0].ty.specifiers.len(), 1);
+            assert!(old.parameters[0].ty.declarator.is_some());
+
+            let handler = &signatures[2];
+
+            assert_eq!(handler.parameters.len(), 1);
+            assert_eq!(handler.parameters[0].ident, Some(Ident::from("sig")));
+            assert!(handler.return_type.declarator.is_some());
+
+            assert!(signatures[3].parameters.is_empty());
+
+            // Old-style parameter declarations must not leak out of the function
+            assert_eq!(signatures[4].parameters[0].ty.specifiers[0].node,
+                       DeclarationSpecifier::TypeSpecifier(TypeSpecifier::TypedefName(Ident::from("T"))));
+        }
+    }
+}
@@ -1,71 +1,98 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Clone, Eq, Hash)]
-pub struct Ident(String);
+use super::span::Span;
+use super::keywords;
+
+/// An identifier and where it was found
+///
+/// Identifiers compare and hash by name only.
+#[derive(Debug, Clone, Eq)]
+pub struct Ident {
+    name: String,
+    span: Span,
+}
 
 impl Ident {
     pub fn new<T>(t: T) -> Self where T: Into<Ident> {
         t.into()
     }
+
+    pub fn with_span(self, span: Span) -> Ident {
+        Ident { name: self.name, span: span }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns true if the identifier is spelled like a keyword of the standard being parsed
+    pub fn is_keyword(&self) -> bool {
+        keywords::is_reserved(&self.name)
+    }
 }
 
 impl<T> From<T> for Ident where T: Into<String> {
     fn from(ident: T) -> Ident {
-        Ident(ident.into())
+        Ident { name: ident.into(), span: Span::default() }
     }
 }
 
 impl AsRef<str> for Ident {
     fn as_ref(&self) -> &str {
-        &self.0
+        &self.name
     }
 }
 
 impl Display for Ident {
     fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
-        self.0.fmt(formatter)
+        self.name.fmt(formatter)
     }
 }
 
 impl<T: ? Sized> PartialEq<T> for Ident where T: AsRef<str>
 {
     fn eq(&self, other: &T) -> bool {
-        self.0 == other.as_ref()
+        self.name == other.as_ref()
+    }
+}
+
+impl Hash for Ident {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state)
     }
 }
 
 pub mod parsing {
     use nom::*;
     use ::parser::error::ParseError;
+    use ::parser::scope;
+    use ::parser::span::Spanned;
+    use ::parser::comments::trivia_len;
+    use ::parser::token::Token;
+    use ::parser::token::parsing::token;
     use super::Ident;
 
-    named!(identifier_start, alt_complete!(
-        tag!("_") | alpha
-    ));
-
-    named!(identifier_character, alt_complete!(
-        identifier_start | digit
-    ));
-
-    named!(identifier_raw<Ident>, wse!(complete!(
-        do_parse!(
-            start: many1!(identifier_start)     >>
-            rest:  many0!(identifier_character) >> ({
-                start.into_iter().chain(rest.into_iter())
-                    .map(|slice| String::from_utf8_lossy(slice))
-                    .fold(String::new(), |acc, s| acc + s.as_ref())
-                    .into()
-            })
-        )
-    )));
-
-    named!(pub identifier<&[u8], Ident, ParseError>, add_return_error!(
-        ParseError::InvalidIdentifier.into_nom(),
-        fix_error!(ParseError, identifier_raw)
-    ));
+    /// Matches an identifier and any whitespace around it
+    ///
+    /// Words reserved by the standard being parsed are keywords, not identifiers.
+    pub fn identifier(input: &[u8]) -> IResult<&[u8], Ident, ParseError> {
+        match token(input) {
+            IResult::Done(rest, Spanned { node: Token::Identifier(ident), .. }) => IResult::Done(&rest[trivia_len(rest)..], ident),
+            _ => IResult::Error(error_position!(ParseError::InvalidIdentifier.into_nom(), input)),
+        }
+    }
 
     named!(pub identifier_list<&[u8], Vec<Ident>, ParseError>,
         separated_nonempty_list!(punct!(','), identifier));
 
-    named!(pub typedef_name<&[u8], Ident, ParseError>, call!(identifier));
+    /// Matches an identifier that is declared as a typedef name in the current scope
+    pub fn typedef_name(input: &[u8]) -> IResult<&[u8], Ident, ParseError> {
+        match identifier(input) {
+            IResult::Done(_, ref ident) if ident.is_keyword() || !scope::is_typedef(ident) => {
+                IResult::Error(error_position!(ParseError::InvalidTypedefName.into_nom(), input))
+            },
+            res => res,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,190 @@
+use super::ident::Ident;
+use super::expression::Expression;
+use super::span::Spanned;
+
+/// Selects the member or element of the current object that an initializer applies to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Designator {
+    /// `.member`
+    Member(Ident),
+    /// `[index]`
+    Index(Spanned<Expression>),
+    /// `[first ... last]`, a GNU extension initializing every element in the inclusive range
+    Range(Spanned<Expression>, Spanned<Expression>),
+}
+
+/// An entry of a brace-enclosed initializer list
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesignatedInitializer {
+    /// Empty if the initializer applies to the next member or element,
+    /// otherwise the chain of designators, as in `.a[2].b = 1`
+    pub designators: Vec<Spanned<Designator>>,
+    pub initializer: Spanned<Initializer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Initializer {
+    Expression(Spanned<Expression>),
+    /// `{ ... }`, which is empty for the `{}` initializers of C23
+    List(Vec<Spanned<DesignatedInitializer>>),
+}
+
+impl Initializer {
+    pub fn as_expression(&self) -> Option<&Spanned<Expression>> {
+        match *self {
+            Initializer::Expression(ref expression) => Some(expression),
+            Initializer::List(_) => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Spanned<DesignatedInitializer>]> {
+        match *self {
+            Initializer::Expression(_) => None,
+            Initializer::List(ref list) => Some(list),
+        }
+    }
+}
+
+pub mod parsing {
+    use nom::*;
+    use ::wse;
+    use super::*;
+
+    use ::parser::error::ParseError;
+    use ::parser::ident::parsing::identifier;
+    use ::parser::expression::parsing::{assignment_expression, constant_expression};
+    use ::parser::span::Spanned;
+
+    named!(index_designator<&[u8], Designator, ParseError>, wse!(delimited!(
+        punct!('['),
+        do_parse!(
+            first: constant_expression                                      >>
+            last: opt!(complete!(wse!(preceded!(op!("..."), constant_expression)))) >>
+            (match last {
+                Some(last) => Designator::Range(first, last),
+                None => Designator::Index(first),
+            })
+        ),
+        punct!(']')
+    )));
+
+    named!(
+        #[doc = "Matches a single `.member`, `[index]` or `[first ... last]` designator"],
+        pub designator<&[u8], Spanned<Designator>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidDesignator.into_nom(),
+            spanned!(wse!(alt_complete!(
+                preceded!(punct!('.'), identifier) => { |ident| Designator::Member(ident) } |
+                index_designator
+            )))
+        )
+    );
+
+    named!(designation<&[u8], Vec<Spanned<Designator>>, ParseError>, wse!(terminated!(
+        many1!(designator),
+        punct!('=')
+    )));
+
+    named!(designated_initializer<&[u8], Spanned<DesignatedInitializer>, ParseError>, spanned!(wse!(do_parse!(
+        designators: opt!(complete!(designation))  >>
+        initializer: initializer                   >>
+        (DesignatedInitializer {
+            designators: designators.unwrap_or_default(),
+            initializer: initializer,
+        })
+    ))));
+
+    named!(
+        #[doc = "Matches a brace-enclosed initializer list, which may be empty or end with a comma"],
+        pub initializer_list<&[u8], Vec<Spanned<DesignatedInitializer>>, ParseError>,
+        wse!(delimited!(
+            punct!('{'),
+            map!(
+                opt!(terminated!(
+                    separated_nonempty_list!(punct!(','), designated_initializer),
+                    opt!(punct!(','))
+                )),
+                |list: Option<Vec<Spanned<DesignatedInitializer>>>| list.unwrap_or_default()
+            ),
+            punct!('}')
+        ))
+    );
+
+    named!(
+        #[doc = "Matches an expression or a brace-enclosed initializer list"],
+        pub initializer<&[u8], Spanned<Initializer>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidInitializer.into_nom(),
+            spanned!(wse!(alt_complete!(
+                initializer_list        => { |list| Initializer::List(list) } |
+                assignment_expression   => { |e| Initializer::Expression(e) }
+            )))
+        )
+    );
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::lit::{Lit, IntegerLiteral, SignedIntegerValue};
+
+        fn int(value: i32) -> Spanned<Expression> {
+            Expression::Literal(Lit::Integer(IntegerLiteral::Signed(SignedIntegerValue::Int(value)))).into()
+        }
+
+        fn value(value: i32) -> Spanned<Initializer> {
+            Initializer::Expression(int(value)).into()
+        }
+
+        fn entry(designators: Vec<Designator>, initializer: Spanned<Initializer>) -> Spanned<DesignatedInitializer> {
+            DesignatedInitializer {
+                designators: designators.into_iter().map(Spanned::from).collect(),
+                initializer: initializer,
+            }.into()
+        }
+
+        #[test]
+        fn test_initializer() {
+            assert_eq!(initializer(b"1"), IResult::Done(&b""[..], value(1)));
+
+            assert_eq!(initializer(b"{ 1, { 2, }, }"), IResult::Done(&b""[..], Initializer::List(vec![
+                entry(vec![], value(1)),
+                entry(vec![], Initializer::List(vec![entry(vec![], value(2))]).into()),
+            ]).into()));
+
+            assert_eq!(initializer(b"{}"), IResult::Done(&b""[..], Initializer::List(vec![]).into()));
+
+            assert!(initializer(b"{,}").is_err());
+            assert!(initializer(b"{1,,}").is_err());
+            assert!(initializer(b"{ .x }").is_err());
+        }
+
+        #[test]
+        fn test_designators() {
+            assert_eq!(initializer(b"{ .f = 1, [3] = 2, .a[2].b = 3, [0 ... 9] = 4, 5 }"), IResult::Done(&b""[..], Initializer::List(vec![
+                entry(vec![Designator::Member(Ident::from("f"))], value(1)),
+                entry(vec![Designator::Index(int(3))], value(2)),
+                entry(vec![
+                    Designator::Member(Ident::from("a")),
+                    Designator::Index(int(2)),
+                    Designator::Member(Ident::from("b")),
+                ], value(3)),
+                entry(vec![Designator::Range(int(0), int(9))], value(4)),
+                entry(vec![], value(5)),
+            ]).into()));
+
+            match initializer(b"{ [1] = { .name = \"a\", .values = { [0] = 1 } } }") {
+                IResult::Done(rest, init) => {
+                    assert_eq!(rest, b"");
+
+                    let inner = &init.as_list().unwrap()[0].initializer;
+                    let values = &inner.as_list().unwrap()[1];
+
+                    assert_eq!(values.designators[0].node, Designator::Member(Ident::from("values")));
+                    assert_eq!(values.initializer.as_list().unwrap()[0].initializer.as_expression(), Some(&int(1)));
+                },
+                res => panic!("Unexpected result {:?}", res)
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Reserved words of each revision of the C standard
+//!
+//! The standard being parsed is kept per-thread, like the typedef names in `scope`,
+//! and is set for a whole file by `parse_with_options`.
+
+use std::cell::Cell;
+
+/// A revision of the C standard, which decides the words that cannot be identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Standard {
+    C89,
+    C99,
+    C11,
+    /// C17 only fixed defects in C11, so reserves the same words
+    C17,
+    C23,
+}
+
+impl Default for Standard {
+    fn default() -> Standard {
+        Standard::C23
+    }
+}
+
+/// Every reserved word, along with the standard that introduced it
+pub const RESERVED_WORDS: &'static [(&'static str, Standard)] = &[
+    ("auto", Standard::C89),
+    ("break", Standard::C89),
+    ("case", Standard::C89),
+    ("char", Standard::C89),
+    ("const", Standard::C89),
+    ("continue", Standard::C89),
+    ("default", Standard::C89),
+    ("do", Standard::C89),
+    ("double", Standard::C89),
+    ("else", Standard::C89),
+    ("enum", Standard::C89),
+    ("extern", Standard::C89),
+    ("float", Standard::C89),
+    ("for", Standard::C89),
+    ("goto", Standard::C89),
+    ("if", Standard::C89),
+    ("int", Standard::C89),
+    ("long", Standard::C89),
+    ("register", Standard::C89),
+    ("return", Standard::C89),
+    ("short", Standard::C89),
+    ("signed", Standard::C89),
+    ("sizeof", Standard::C89),
+    ("static", Standard::C89),
+    ("struct", Standard::C89),
+    ("switch", Standard::C89),
+    ("typedef", Standard::C89),
+    ("union", Standard::C89),
+    ("unsigned", Standard::C89),
+    ("void", Standard::C89),
+    ("volatile", Standard::C89),
+    ("while", Standard::C89),
+    ("inline", Standard::C99),
+    ("restrict", Standard::C99),
+    ("_Bool", Standard::C99),
+    ("_Complex", Standard::C99),
+    ("_Imaginary", Standard::C99),
+    ("_Alignas", Standard::C11),
+    ("_Alignof", Standard::C11),
+    ("_Atomic", Standard::C11),
+    ("_Generic", Standard::C11),
+    ("_Noreturn", Standard::C11),
+    ("_Static_assert", Standard::C11),
+    ("_Thread_local", Standard::C11),
+    ("alignas", Standard::C23),
+    ("alignof", Standard::C23),
+    ("bool", Standard::C23),
+    ("constexpr", Standard::C23),
+    ("false", Standard::C23),
+    ("nullptr", Standard::C23),
+    ("static_assert", Standard::C23),
+    ("thread_local", Standard::C23),
+    ("true", Standard::C23),
+    ("typeof", Standard::C23),
+    ("typeof_unqual", Standard::C23),
+    ("_BitInt", Standard::C23),
+    ("_Decimal32", Standard::C23),
+    ("_Decimal64", Standard::C23),
+    ("_Decimal128", Standard::C23),
+];
+
+/// Finds `word` among the words reserved by `standard`
+pub fn reserved_word(word: &[u8], standard: Standard) -> Option<&'static str> {
+    RESERVED_WORDS.iter()
+        .find(|&&(reserved, since)| since <= standard && reserved.as_bytes() == word)
+        .map(|&(reserved, _)| reserved)
+}
+
+/// Returns true if `word` is reserved by the standard currently being parsed
+pub fn is_reserved(word: &str) -> bool {
+    reserved_word(word.as_bytes(), standard()).is_some()
+}
+
+/// Returns true if `word` is reserved by any standard, so it is only a keyword where that standard reserves it
+pub fn is_ever_reserved(word: &str) -> bool {
+    RESERVED_WORDS.iter().any(|&(reserved, _)| reserved == word)
+}
+
+thread_local! {
+    static STANDARD: Cell<Standard> = Cell::new(Standard::default());
+}
+
+/// The standard currently being parsed, which is C23 unless set by `with_standard`
+pub fn standard() -> Standard {
+    STANDARD.with(|standard| standard.get())
+}
+
+/// Runs `f` while parsing according to `standard`
+pub fn with_standard<F, R>(standard: Standard, f: F) -> R where F: FnOnce() -> R {
+    // Restores the previous standard, even when unwinding from a panic
+    struct Guard(Standard);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            STANDARD.with(|current| current.set(self.0));
+        }
+    }
+
+    let _guard = Guard(STANDARD.with(|current| current.replace(standard)));
+
+    f()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reserved_words() {
+        assert_eq!(reserved_word(b"inline", Standard::C89), None);
+        assert_eq!(reserved_word(b"inline", Standard::C99), Some("inline"));
+        assert_eq!(reserved_word(b"bool", Standard::C17), None);
+        assert_eq!(reserved_word(b"bool", Standard::C23), Some("bool"));
+        assert_eq!(reserved_word(b"inlined", Standard::C23), None);
+
+        assert!(is_reserved("true"));
+        assert!(!with_standard(Standard::C11, || is_reserved("true")));
+        assert!(with_standard(Standard::C11, || is_reserved("_Generic")));
+        assert_eq!(standard(), Standard::C23);
+
+        assert!(is_ever_reserved("restrict"));
+        assert!(!is_ever_reserved("__func__"));
+    }
+}
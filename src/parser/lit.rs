@@ -29,27 +29,56 @@ pub enum FloatLiteral {
     Arbitrary(String),
 }
 
+/// The encoding prefix of a string literal or character constant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StringPrefix {
+    /// No prefix, as in `"text"` or `'c'`
+    None,
+    /// `u8"text"` or C23 `u8'c'`
+    Utf8,
+    /// `u"text"` or `u'c'`
+    Utf16,
+    /// `U"text"` or `U'c'`
+    Utf32,
+    /// `L"text"` or `L'c'`
+    Wide,
+}
+
+impl Default for StringPrefix {
+    fn default() -> StringPrefix {
+        StringPrefix::None
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Lit {
     Str {
         value: String,
-        unicode: bool,
-        wide: bool,
+        prefix: StringPrefix,
     },
     StringLike(Ident),
     Integer(IntegerLiteral),
     IntegerLike(Ident),
     Float(FloatLiteral),
-    Char(char)
+    Char {
+        value: char,
+        prefix: StringPrefix,
+    },
+    /// C23 `true` and `false`
+    Bool(bool),
+    /// C23 `nullptr`
+    Nullptr,
 }
 
 pub mod parsing {
     use nom::*;
+    use ::wse;
 
     use ::parser::error::ParseError;
     use ::parser::ident::Ident;
     use ::parser::lit::*;
     use ::parser::utils::{map_character, map_characters};
+    use ::parser::comments::trivia_len;
 
     named!(
         #[doc = "Matches string-like literals and consumes whitespace"],
@@ -83,21 +112,28 @@ pub mod parsing {
         )
     );
 
-    #[derive(Debug, Clone, Copy, PartialEq, Hash)]
-    enum StringPrefix {
-        Unicode,
-        Wide
-    }
+    named!(
+        #[doc = "Matches the C23 constants `true`, `false` and `nullptr`, which are ordinary identifiers in earlier standards"],
+        pub predefined_constant<&[u8], Lit, ParseError>,
+        add_return_error!(
+            ParseError::InvalidPredefinedConstant.into_nom(),
+            wse!(alt_complete!(
+                keyword!("true")    => { |_| Lit::Bool(true)  } |
+                keyword!("false")   => { |_| Lit::Bool(false) } |
+                keyword!("nullptr") => { |_| Lit::Nullptr     }
+            ))
+        )
+    );
 
-    // String literal prefixes
+    // String literal prefixes, which are case sensitive and written directly before the quote
     named!(string_literal_prefix<&[u8], StringPrefix, ParseError>, add_return_error!(
         ParseError::InvalidStringPrefix.into_nom(),
-        wse!(alt!(
-            tag!("u8") => {|_| StringPrefix::Unicode } |
-            char!('u') => {|_| StringPrefix::Unicode } |
-            char!('l') => {|_| StringPrefix::Wide    } |
-            char!('L') => {|_| StringPrefix::Wide    }
-        ))
+        alt_complete!(
+            tag!("u8") => {|_| StringPrefix::Utf8  } |
+            char!('u') => {|_| StringPrefix::Utf16 } |
+            char!('U') => {|_| StringPrefix::Utf32 } |
+            char!('L') => {|_| StringPrefix::Wide  }
+        )
     ));
 
     // Any valid non-escaped string literal character
@@ -145,63 +181,108 @@ pub mod parsing {
     named!(string_characters<&[u8], Vec<String>, ParseError>,
         many1!(string_character));
 
-    // Zero or more string prefixes
-    named!(raw_string_prefix<&[u8], Vec<StringPrefix>, ParseError>,
-        many1!(string_literal_prefix));
-
     // String literal internals delimited by " characters
+    // Quotes are part of the literal's token, so are matched as bytes rather than with `punct!`
+    macro_rules! quote (
+        ($i:expr, $c:expr) => (
+            add_return_error!($i, ParseError::InvalidPunctuation($c).into_nom(), fix_error!(ParseError, char!($c)))
+        )
+    );
+
     named!(raw_delimited_string_literal<&[u8], Vec<String>, ParseError>, complete!(delimited!(
         // If this doesn't match, we missed an invalid prefix
-        // E.g., h"sdf" will skip the h and pass h" to this, so it fails
+        // E.g., h"sdf", uL"sdf" or u "sdf" will pass h", L" or a space to this, so it fails
         add_return_error!(
             ParseError::InvalidStringPrefix.into_nom(),
-            punct!('"')
+            quote!('"')
         ),
         string_characters,
-        punct!('"'))
+        quote!('"'))
     ));
 
     named!(raw_delimited_char_literal<&[u8], char, ParseError>, complete!(delimited!(
-        punct!('\''),
+        quote!('\''),
         char_character,
-        punct!('\'')
+        quote!('\'')
     )));
 
-    named!(raw_string_literal<&[u8], Lit, ParseError>, add_return_error!(
+    // A string literal without any whitespace around it
+    named!(pub raw_string_literal<&[u8], Lit, ParseError>, add_return_error!(
         ParseError::InvalidStringLiteral.into_nom(),
         do_parse!(
-            prefix: opt!(raw_string_prefix)      >>
-            value:  raw_delimited_string_literal >> ({
+            prefix: opt!(complete!(string_literal_prefix)) >>
+            value:  raw_delimited_string_literal           >> ({
                 let value = value.into_iter().fold(String::new(), |mut acc, s| {
                     acc += s.as_str();
                     acc
                 });
 
-                if let Some(prefix) = prefix {
-                    Lit::Str {
-                        value: value,
-                        unicode: prefix.contains(&StringPrefix::Unicode),
-                        wide: prefix.contains(&StringPrefix::Wide),
-                    }
-                } else {
-                    Lit::Str {value: value, unicode: false, wide: false}
+                Lit::Str {
+                    value: value,
+                    prefix: prefix.unwrap_or_default(),
                 }
             })
         )
     ));
 
-    named!(raw_char_literal<&[u8], Lit, ParseError>, add_return_error!(
+    // A character literal without any whitespace around it, which may have the same prefixes as strings
+    named!(pub raw_char_literal<&[u8], Lit, ParseError>, add_return_error!(
         ParseError::InvalidCharacterLiteral.into_nom(),
-        map!(raw_delimited_char_literal, |character| {
-            Lit::Char(character)
-        })
+        do_parse!(
+            prefix: opt!(complete!(string_literal_prefix)) >>
+            value:  raw_delimited_char_literal             >>
+            (Lit::Char {
+                value: value,
+                prefix: prefix.unwrap_or_default(),
+            })
+        )
     ));
 
-    named!(
-        #[doc = "Matches a string literal and consumes whitespace"],
-        pub string_literal<&[u8], Lit, ParseError>,
-        wse!(raw_string_literal)
-    );
+    // Joins two adjacent string literals, where a literal without a prefix takes on the prefix of the other,
+    // and literals with different prefixes, even `u8` and `u`, cannot be joined
+    fn concatenate(lhs: Lit, rhs: Lit) -> Option<Lit> {
+        match (lhs, rhs) {
+            (Lit::Str { mut value, prefix: lhs_prefix }, Lit::Str { value: rhs_value, prefix: rhs_prefix }) => {
+                let prefix = match (lhs_prefix, rhs_prefix) {
+                    (StringPrefix::None, prefix) | (prefix, StringPrefix::None) => prefix,
+                    (lhs_prefix, rhs_prefix) if lhs_prefix == rhs_prefix => lhs_prefix,
+                    // Such as `u8"a" L"b"`
+                    _ => return None,
+                };
+
+                value += rhs_value.as_str();
+
+                Some(Lit::Str { value: value, prefix: prefix })
+            },
+            _ => None,
+        }
+    }
+
+    /// Matches one or more adjacent string literals as a single literal and consumes whitespace
+    ///
+    /// Adjacent literals are concatenated, as in `"a" L"b"`, which is the same as `L"ab"`.
+    /// Concatenating literals with different prefixes is an error.
+    pub fn string_literal(input: &[u8]) -> IResult<&[u8], Lit, ParseError> {
+        let (mut input, mut lit) = try_parse!(input, wse!(raw_string_literal));
+
+        loop {
+            let next = &input[trivia_len(input)..];
+
+            let (rest, rhs) = match raw_string_literal(next) {
+                IResult::Done(rest, rhs) => (rest, rhs),
+                _ => break,
+            };
+
+            lit = match concatenate(lit, rhs) {
+                Some(lit) => lit,
+                None => return IResult::Error(error_position!(ParseError::InvalidStringConcatenation.into_nom(), next)),
+            };
+
+            input = &rest[trivia_len(rest)..];
+        }
+
+        IResult::Done(input, lit)
+    }
 
     named!(
         #[doc = "Matches a character literal and consumes whitespace"],
@@ -209,6 +290,336 @@ pub mod parsing {
         wse!(raw_char_literal)
     );
 
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    enum IntegerRank {
+        Int,
+        Long,
+        LongLong,
+    }
+
+    // Splits off the radix prefix, where the leading `0` of octal literals is also a digit
+    fn integer_radix(input: &[u8]) -> (u32, &[u8]) {
+        if input.starts_with(b"0x") || input.starts_with(b"0X") {
+            (16, &input[2..])
+        } else if input.starts_with(b"0b") || input.starts_with(b"0B") {
+            (2, &input[2..])
+        } else if input.starts_with(b"0") {
+            (8, input)
+        } else {
+            (10, input)
+        }
+    }
+
+    fn digit_value(c: Option<&u8>, radix: u32) -> Option<u64> {
+        c.and_then(|c| (*c as char).to_digit(radix)).map(|d| d as u64)
+    }
+
+    // Splits off digits of the given radix, allowing single `'` separators between digits
+    fn digit_sequence(input: &[u8], radix: u32) -> (Vec<u64>, &[u8]) {
+        let mut digits = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            if let Some(digit) = digit_value(input.get(pos), radix) {
+                digits.push(digit);
+                pos += 1;
+            } else if !digits.is_empty() && input.get(pos) == Some(&b'\'') && digit_value(input.get(pos + 1), radix).is_some() {
+                pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        (digits, &input[pos..])
+    }
+
+    // The value of a digit sequence, or `None` if it overflows
+    fn integer_value(digits: &[u64], radix: u32) -> Option<u64> {
+        digits.iter().fold(Some(0u64), |value, &digit| {
+            value.and_then(|v| v.checked_mul(radix as u64)).and_then(|v| v.checked_add(digit))
+        })
+    }
+
+    // Anything that would continue a number, such as invalid digits or suffixes
+    fn number_terminated(rest: &[u8]) -> bool {
+        match rest.first() {
+            Some(&c) => !(c.is_ascii_alphanumeric() || c == b'_' || c == b'.' || c == b'\''),
+            None => true,
+        }
+    }
+
+    // Any combination of one `u` and one `l` or `ll` in either order, where `ll` must not mix cases
+    fn integer_suffix(input: &[u8]) -> (bool, IntegerRank, &[u8]) {
+        fn unsigned(input: &[u8]) -> (bool, &[u8]) {
+            match input.first() {
+                Some(&b'u') | Some(&b'U') => (true, &input[1..]),
+                _ => (false, input),
+            }
+        }
+
+        fn rank(input: &[u8]) -> (IntegerRank, &[u8]) {
+            if input.starts_with(b"ll") || input.starts_with(b"LL") {
+                (IntegerRank::LongLong, &input[2..])
+            } else if input.starts_with(b"l") || input.starts_with(b"L") {
+                (IntegerRank::Long, &input[1..])
+            } else {
+                (IntegerRank::Int, input)
+            }
+        }
+
+        let (unsigned_before, input) = unsigned(input);
+        let (rank, input) = rank(input);
+
+        if unsigned_before {
+            (true, rank, input)
+        } else {
+            let (unsigned_after, input) = unsigned(input);
+
+            (unsigned_after, rank, input)
+        }
+    }
+
+    /// Picks the first type in the list given by the C standard that can represent the value
+    ///
+    /// Decimal literals without a `u` suffix only consider signed types,
+    /// while other radixes try the unsigned type of each rank after the signed one.
+    fn integer_type(value: u64, decimal: bool, unsigned: bool, rank: IntegerRank) -> Option<IntegerLiteral> {
+        let ranks = [IntegerRank::Int, IntegerRank::Long, IntegerRank::LongLong];
+
+        for &candidate in ranks.iter().filter(|&&r| r >= rank) {
+            let (signed_max, unsigned_max) = match candidate {
+                IntegerRank::Int => (i32::max_value() as u64, u32::max_value() as u64),
+                _ => (i64::max_value() as u64, u64::max_value()),
+            };
+
+            if !unsigned && value <= signed_max {
+                return Some(IntegerLiteral::Signed(match candidate {
+                    IntegerRank::Int => SignedIntegerValue::Int(value as i32),
+                    IntegerRank::Long => SignedIntegerValue::Long(value as i64),
+                    IntegerRank::LongLong => SignedIntegerValue::LongLong(value as i64),
+                }));
+            }
+
+            if (unsigned || !decimal) && value <= unsigned_max {
+                return Some(IntegerLiteral::Unsigned(match candidate {
+                    IntegerRank::Int => UnsignedIntegerValue::Int(value as u32),
+                    IntegerRank::Long => UnsignedIntegerValue::Long(value),
+                    IntegerRank::LongLong => UnsignedIntegerValue::LongLong(value),
+                }));
+            }
+        }
+
+        None
+    }
+
+    /// Matches decimal, octal, hexadecimal and binary integer constants with optional suffixes
+    pub fn raw_integer_literal(input: &[u8]) -> IResult<&[u8], Lit, ParseError> {
+        let (radix, digits) = integer_radix(input);
+
+        let (digits, rest) = digit_sequence(digits, radix);
+
+        let (unsigned, rank, rest) = integer_suffix(rest);
+
+        if digits.is_empty() || !number_terminated(rest) {
+            return IResult::Error(error_position!(ParseError::InvalidIntegerLiteral.into_nom(), input));
+        }
+
+        match integer_value(&digits, radix).and_then(|value| integer_type(value, radix == 10, unsigned, rank)) {
+            Some(lit) => IResult::Done(rest, Lit::Integer(lit)),
+            None => IResult::Error(error_position!(ParseError::IntegerLiteralTooLarge.into_nom(), input)),
+        }
+    }
+
+    // Collects hexadecimal digits into `mantissa * 2^exponent`,
+    // keeping 60 significant bits and a sticky bit for any digits dropped after them
+    fn hex_mantissa(integer: &[u64], fraction: &[u64], exponent: i64) -> (u64, i64) {
+        let mut mantissa = 0u64;
+        let mut exponent = exponent;
+
+        for (i, &digit) in integer.iter().chain(fraction.iter()).enumerate() {
+            let is_fraction = i >= integer.len();
+
+            if mantissa >> 60 == 0 {
+                mantissa = mantissa * 16 + digit;
+
+                if is_fraction {
+                    exponent -= 4;
+                }
+            } else {
+                if !is_fraction {
+                    exponent += 4;
+                }
+
+                if digit != 0 {
+                    mantissa |= 1;
+                }
+            }
+        }
+
+        (mantissa, exponent)
+    }
+
+    // Rounds `mantissa * 2^exponent` to nearest, ties to even, in a binary floating format with
+    // `precision` significant bits and the given exponent `bias`, returning the bits of the result.
+    //
+    // Rounding happens once, directly to the target width, so subnormals and values close to
+    // a rounding boundary of `f32` are not rounded twice as they would be through `f64`.
+    fn hex_float_bits(mantissa: u64, exponent: i64, precision: u32, bias: i64) -> u64 {
+        if mantissa == 0 {
+            return 0;
+        }
+
+        let min_exponent = 1 - bias;
+        let max_biased = 2 * bias + 1;
+
+        // The exponent of the leading bit, and that of the last bit that can be kept
+        let leading = exponent + 63 - mantissa.leading_zeros() as i64;
+        let mut last = (leading - (precision as i64 - 1)).max(min_exponent - (precision as i64 - 1));
+
+        let shift = last - exponent;
+
+        let mut rounded = if shift <= 0 {
+            mantissa << -shift
+        } else if shift > 64 {
+            0
+        } else {
+            let kept = if shift == 64 { 0 } else { mantissa >> shift };
+            let dropped = if shift == 64 { mantissa } else { mantissa & ((1 << shift) - 1) };
+            let half = 1u64 << (shift - 1);
+
+            if dropped > half || (dropped == half && kept & 1 == 1) { kept + 1 } else { kept }
+        };
+
+        // Rounding up may carry into a new leading bit
+        if rounded >> precision != 0 {
+            rounded >>= 1;
+            last += 1;
+        }
+
+        let hidden = 1u64 << (precision - 1);
+
+        if rounded < hidden {
+            // Subnormal or zero, with a biased exponent of zero
+            return rounded;
+        }
+
+        let biased = last + precision as i64 - 1 + bias;
+
+        if biased >= max_biased {
+            return (max_biased as u64) << (precision - 1);
+        }
+
+        ((biased as u64) << (precision - 1)) | (rounded - hidden)
+    }
+
+    /// Matches decimal and hexadecimal floating constants, which require a `.` or an exponent
+    ///
+    /// `long double` constants keep their spelling, since they may not fit in an `f64`.
+    pub fn raw_float_literal(input: &[u8]) -> IResult<&[u8], Lit, ParseError> {
+        let error = || IResult::Error(error_position!(ParseError::InvalidFloatLiteral.into_nom(), input));
+
+        let hex = input.starts_with(b"0x") || input.starts_with(b"0X");
+
+        let (radix, start) = if hex { (16, &input[2..]) } else { (10, input) };
+
+        let (integer, rest) = digit_sequence(start, radix);
+
+        let (has_point, fraction, rest) = if rest.first() == Some(&b'.') {
+            let (fraction, rest) = digit_sequence(&rest[1..], radix);
+
+            (true, fraction, rest)
+        } else {
+            (false, Vec::new(), rest)
+        };
+
+        if integer.is_empty() && fraction.is_empty() {
+            return error();
+        }
+
+        let exponent_marker = if hex { b"pP" } else { b"eE" };
+
+        let (exponent, rest) = match rest.first() {
+            Some(c) if exponent_marker.contains(c) => {
+                let (negative, rest) = match rest.get(1) {
+                    Some(&b'-') => (true, &rest[2..]),
+                    Some(&b'+') => (false, &rest[2..]),
+                    _ => (false, &rest[1..]),
+                };
+
+                let (digits, rest) = digit_sequence(rest, 10);
+
+                if digits.is_empty() {
+                    return error();
+                }
+
+                // Saturate, since anything this large is infinity or zero anyway
+                let value = digits.iter().fold(0i64, |acc, &digit| (acc * 10 + digit as i64).min(1 << 20));
+
+                (Some(if negative { -value } else { value }), rest)
+            },
+            _ => (None, rest),
+        };
+
+        // Hexadecimal floats require an exponent, and without either this is an integer
+        if (hex && exponent.is_none()) || (!has_point && exponent.is_none()) {
+            return error();
+        }
+
+        let (suffix, rest) = match rest.first() {
+            Some(&c) if b"fFlL".contains(&c) => (Some(c.to_ascii_lowercase()), &rest[1..]),
+            _ => (None, rest),
+        };
+
+        if !number_terminated(rest) {
+            return error();
+        }
+
+        let spelling = &input[..input.len() - rest.len()];
+
+        let exponent = exponent.unwrap_or(0);
+
+        let lit = match suffix {
+            Some(b'l') => FloatLiteral::Arbitrary(String::from_utf8_lossy(spelling).into_owned()),
+            suffix if hex => {
+                let (mantissa, exponent) = hex_mantissa(&integer, &fraction, exponent);
+
+                if suffix == Some(b'f') {
+                    FloatLiteral::F32(f32::from_bits(hex_float_bits(mantissa, exponent, 24, 127) as u32))
+                } else {
+                    FloatLiteral::F64(f64::from_bits(hex_float_bits(mantissa, exponent, 53, 1023)))
+                }
+            },
+            suffix => {
+                let digits = |digits: &[u64]| -> String {
+                    digits.iter().map(|&d| (b'0' + d as u8) as char).collect()
+                };
+
+                let normalized = format!("{}.{}e{}", digits(&integer), digits(&fraction), exponent);
+
+                // Cannot fail, since the digits were already validated
+                if suffix == Some(b'f') {
+                    FloatLiteral::F32(normalized.parse().unwrap())
+                } else {
+                    FloatLiteral::F64(normalized.parse().unwrap())
+                }
+            },
+        };
+
+        IResult::Done(rest, Lit::Float(lit))
+    }
+
+    named!(
+        #[doc = "Matches a floating literal and consumes whitespace"],
+        pub float_literal<&[u8], Lit, ParseError>,
+        wse!(raw_float_literal)
+    );
+
+    named!(
+        #[doc = "Matches an integer literal and consumes whitespace"],
+        pub integer_literal<&[u8], Lit, ParseError>,
+        wse!(raw_integer_literal)
+    );
+
     named!(
         #[doc = "Matches a string literals and string-like literals"],
         pub string<&[u8], Lit, ParseError>,
@@ -216,8 +627,199 @@ pub mod parsing {
             ParseError::InvalidString.into_nom(),
             alt!(
                 string_like_literal |
-                raw_string_literal
+                string_literal
             )
         ))
     );
-}
\ No newline at end of file
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::error::Diagnostic;
+
+        fn int(source: &[u8]) -> IResult<&[u8], Lit, ParseError> {
+            integer_literal(source)
+        }
+
+        fn done(lit: IntegerLiteral) -> IResult<&'static [u8], Lit, ParseError> {
+            IResult::Done(&[] as &[u8], Lit::Integer(lit))
+        }
+
+        #[test]
+        fn test_integer_literal_radix() {
+            assert_eq!(int(b"42"), done(IntegerLiteral::Signed(SignedIntegerValue::Int(42))));
+            assert_eq!(int(b"0"), done(IntegerLiteral::Signed(SignedIntegerValue::Int(0))));
+            assert_eq!(int(b"0755"), done(IntegerLiteral::Signed(SignedIntegerValue::Int(0o755))));
+            assert_eq!(int(b"0x1F"), done(IntegerLiteral::Signed(SignedIntegerValue::Int(31))));
+            assert_eq!(int(b"0b1010"), done(IntegerLiteral::Signed(SignedIntegerValue::Int(10))));
+            assert_eq!(int(b"1'000'000"), done(IntegerLiteral::Signed(SignedIntegerValue::Int(1000000))));
+            assert_eq!(int(b"0xFF'FF"), done(IntegerLiteral::Signed(SignedIntegerValue::Int(0xFFFF))));
+
+            assert!(int(b"09").is_err());
+            assert!(int(b"0x").is_err());
+            assert!(int(b"0b2").is_err());
+            assert!(int(b"1''0").is_err());
+            assert!(int(b"1'").is_err());
+        }
+
+        #[test]
+        fn test_integer_literal_suffix() {
+            assert_eq!(int(b"1u"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::Int(1))));
+            assert_eq!(int(b"1l"), done(IntegerLiteral::Signed(SignedIntegerValue::Long(1))));
+            assert_eq!(int(b"1ll"), done(IntegerLiteral::Signed(SignedIntegerValue::LongLong(1))));
+            assert_eq!(int(b"1ul"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::Long(1))));
+            assert_eq!(int(b"1LLU"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::LongLong(1))));
+            assert_eq!(int(b"1uLL"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::LongLong(1))));
+
+            assert!(int(b"1lL").is_err());
+            assert!(int(b"1uu").is_err());
+            assert!(int(b"1lul").is_err());
+            assert!(int(b"1f").is_err());
+        }
+
+        #[test]
+        fn test_integer_literal_type() {
+            // Hexadecimal constants may be unsigned without a suffix, but decimal ones may not
+            assert_eq!(int(b"0xFFFFFFFF"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::Int(0xFFFFFFFF))));
+            assert_eq!(int(b"4294967295"), done(IntegerLiteral::Signed(SignedIntegerValue::Long(4294967295))));
+            assert_eq!(int(b"2147483648"), done(IntegerLiteral::Signed(SignedIntegerValue::Long(2147483648))));
+            assert_eq!(int(b"0x8000000000000000"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::Long(1 << 63))));
+            assert_eq!(int(b"0x8000000000000000ll"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::LongLong(1 << 63))));
+            assert_eq!(int(b"18446744073709551615u"), done(IntegerLiteral::Unsigned(UnsignedIntegerValue::Long(u64::max_value()))));
+
+            match int(b"9223372036854775808") {
+                IResult::Error(err) => assert_eq!(Diagnostic::from_nom(b"9223372036854775808", &err).error(), Some(&ParseError::IntegerLiteralTooLarge)),
+                res => panic!("Unexpected result {:?}", res)
+            }
+
+            assert!(int(b"18446744073709551616u").is_err());
+        }
+
+        #[test]
+        fn test_float_literal() {
+            fn float(source: &[u8]) -> FloatLiteral {
+                match float_literal(source) {
+                    IResult::Done(rest, Lit::Float(lit)) => {
+                        assert_eq!(rest, b"");
+                        lit
+                    },
+                    res => panic!("Unexpected result {:?}", res)
+                }
+            }
+
+            assert_eq!(float(b"1.5"), FloatLiteral::F64(1.5));
+            assert_eq!(float(b"1."), FloatLiteral::F64(1.0));
+            assert_eq!(float(b".25"), FloatLiteral::F64(0.25));
+            assert_eq!(float(b"1e3"), FloatLiteral::F64(1000.0));
+            assert_eq!(float(b"2.5E-1f"), FloatLiteral::F32(0.25));
+            assert_eq!(float(b"1'000.5"), FloatLiteral::F64(1000.5));
+            assert_eq!(float(b"0x1.8p3"), FloatLiteral::F64(12.0));
+            assert_eq!(float(b"0X.8P-1F"), FloatLiteral::F32(0.25));
+            assert_eq!(float(b"0x1p-1074"), FloatLiteral::F64(5e-324));
+            assert_eq!(float(b"0x1.fffffffffffff8p1023"), FloatLiteral::F64(::std::f64::INFINITY));
+            assert_eq!(float(b"0x1.fffffffffffff7p1023"), FloatLiteral::F64(::std::f64::MAX));
+            assert_eq!(float(b"0x1.8p-1074"), FloatLiteral::F64(1e-323));
+
+            // Exactly halfway between two floats rounds to even, anything above it rounds up,
+            // which rounding through an `f64` first would get wrong
+            assert_eq!(float(b"0x1.000001p0f"), FloatLiteral::F32(1.0));
+            assert_eq!(float(b"0x1.000001000000001p0f"), FloatLiteral::F32(1.0 + ::std::f32::EPSILON));
+            assert_eq!(float(b"0x1.000003p0f"), FloatLiteral::F32(1.0 + 2.0 * ::std::f32::EPSILON));
+
+            // Subnormal floats are rounded once, at their reduced precision
+            assert_eq!(float(b"0x1p-149f"), FloatLiteral::F32(f32::from_bits(1)));
+            assert_eq!(float(b"0x1.8p-149f"), FloatLiteral::F32(f32::from_bits(2)));
+            assert_eq!(float(b"0x1p-150f"), FloatLiteral::F32(0.0));
+            assert_eq!(float(b"0x1.0000000001p-150f"), FloatLiteral::F32(f32::from_bits(1)));
+            assert_eq!(float(b"0x1.fffffep-127f"), FloatLiteral::F32(f32::from_bits(0x0080_0000)));
+            assert_eq!(float(b"0x1p128f"), FloatLiteral::F32(::std::f32::INFINITY));
+            assert_eq!(float(b"0x0.0p0"), FloatLiteral::F64(0.0));
+            assert_eq!(float(b"0.1000000000000000000000000001L"), FloatLiteral::Arbitrary("0.1000000000000000000000000001L".to_owned()));
+
+            assert!(float_literal(b"1").is_err());
+            assert!(float_literal(b"0x1.8").is_err());
+            assert!(float_literal(b"1e").is_err());
+            assert!(float_literal(b"1.0ff").is_err());
+            assert!(float_literal(b".").is_err());
+        }
+
+        #[test]
+        fn test_keyword_like_literals() {
+            assert_eq!(integer_like_literal(b"__LINE__ "), IResult::Done(&b""[..], Lit::IntegerLike(Ident::from("__LINE__"))));
+            assert!(integer_like_literal(b"__LINE__X").is_err());
+            assert!(string_like_literal(b"__func__2").is_err());
+
+            assert_eq!(predefined_constant(b"true"), IResult::Done(&b""[..], Lit::Bool(true)));
+            assert_eq!(predefined_constant(b"nullptr"), IResult::Done(&b""[..], Lit::Nullptr));
+            assert!(predefined_constant(b"trueish").is_err());
+
+            ::parser::keywords::with_standard(::parser::keywords::Standard::C17, || {
+                assert!(predefined_constant(b"true").is_err());
+            });
+        }
+
+        #[test]
+        fn test_string_prefixes() {
+            let prefix = |source: &[u8]| match raw_string_literal(source) {
+                IResult::Done(rest, Lit::Str { prefix, .. }) if rest.is_empty() => Some(prefix),
+                _ => None,
+            };
+
+            assert_eq!(prefix(b"\"a\""), Some(StringPrefix::None));
+            assert_eq!(prefix(b"u8\"a\""), Some(StringPrefix::Utf8));
+            assert_eq!(prefix(b"u\"a\""), Some(StringPrefix::Utf16));
+            assert_eq!(prefix(b"U\"a\""), Some(StringPrefix::Utf32));
+            assert_eq!(prefix(b"L\"a\""), Some(StringPrefix::Wide));
+
+            assert_eq!(prefix(b"l\"a\""), None);
+            assert_eq!(prefix(b"uL\"a\""), None);
+            assert_eq!(prefix(b"u8u8\"a\""), None);
+            assert_eq!(prefix(b"L \"a\""), None);
+        }
+
+        #[test]
+        fn test_char_prefixes() {
+            let constant = |value, prefix| IResult::Done(&b""[..], Lit::Char { value: value, prefix: prefix });
+
+            assert_eq!(char_literal(b"'a'"), constant('a', StringPrefix::None));
+            assert_eq!(char_literal(b"u8'a'"), constant('a', StringPrefix::Utf8));
+            assert_eq!(char_literal(b"u'\\n'"), constant('\n', StringPrefix::Utf16));
+            assert_eq!(char_literal(b"U'b'"), constant('b', StringPrefix::Utf32));
+            assert_eq!(char_literal(b"L'c' "), constant('c', StringPrefix::Wide));
+
+            assert!(char_literal(b"l'c'").is_err());
+            assert!(char_literal(b"L 'c'").is_err());
+        }
+
+        #[test]
+        fn test_string_concatenation() {
+            fn str(value: &str, prefix: StringPrefix) -> Lit {
+                Lit::Str { value: value.to_owned(), prefix: prefix }
+            }
+
+            assert_eq!(string_literal(b"\"a\" \"b\"\n/* c */ \"c\";"), IResult::Done(&b";"[..], str("abc", StringPrefix::None)));
+            assert_eq!(string_literal(b"L\"x\" \"y\""), IResult::Done(&b""[..], str("xy", StringPrefix::Wide)));
+            assert_eq!(string_literal(b"\"x\" u8\"y\" u8\"z\""), IResult::Done(&b""[..], str("xyz", StringPrefix::Utf8)));
+            assert_eq!(string_literal(b"u\"x\" \"y\""), IResult::Done(&b""[..], str("xy", StringPrefix::Utf16)));
+            assert_eq!(string_literal(b"U\"x\" U\"y\""), IResult::Done(&b""[..], str("xy", StringPrefix::Utf32)));
+            assert_eq!(string(b"\"a\" \"b\""), IResult::Done(&b""[..], str("ab", StringPrefix::None)));
+
+            assert!(string_literal(b"u8\"a\" u\"b\"").is_err());
+            assert!(string_literal(b"u8\"a\" L\"b\"").is_err());
+            assert!(string_literal(b"u\"a\" U\"b\"").is_err());
+
+            let source = b"u8\"a\" \"b\" L\"c\"";
+
+            match string_literal(source) {
+                IResult::Error(err) => {
+                    let diagnostic = Diagnostic::from_nom(source, &err);
+
+                    assert_eq!(diagnostic.error(), Some(&ParseError::InvalidStringConcatenation));
+                    assert_eq!(diagnostic.offset, 10);
+                },
+                res => panic!("Unexpected result {:?}", res)
+            }
+        }
+    }
+}
@@ -1,3 +1,15 @@
+/// Like `ws!`, but skips comments along with whitespace and works with custom error types
+///
+/// Modules that glob-import `nom` must also `use ::wse;` so this takes precedence over nom's own `wse!`.
+#[macro_export]
+macro_rules! wse (
+    ($i:expr, $($args:tt)*) => ({
+        use $crate::parser::whitespace::parsing::trivia;
+
+        sep!($i, trivia, $($args)*)
+    })
+);
+
 /// Prematurely returns an error from the sub-parser
 ///
 /// Useful to avoiding other combinators eating errors
@@ -17,28 +29,71 @@ macro_rules! forward_error (
     );
 );
 
-/// Wrapper around `char!` that adds a `ParseError::InvalidPunctuation` error to it
+/// Matches a single-character punctuator token, adding a `ParseError::InvalidPunctuation` error to it
+///
+/// Only whole tokens match, so `punct!('<')` does not match the start of `<<=`.
 #[macro_export]
 macro_rules! punct (
     ($i:expr, $c:expr) => ({
         use $crate::parser::error::ParseError;
+        use $crate::parser::token::parsing::punctuator_char;
 
         add_return_error!($i,
             ParseError::InvalidPunctuation($c).into_nom(),
-            fix_error!(ParseError, char!($c))
+            map!(call!(punctuator_char, $c), |_| $c)
         )
     })
 );
 
-/// Wrapper around `tag!` that adds a `ParseError::InvalidKeyword` error to it
+/// Matches a keyword token, adding a `ParseError::InvalidKeyword` error to it
+///
+/// Only whole tokens match, so `keyword!("static")` does not match the start of `statics`.
 #[macro_export]
 macro_rules! keyword (
     ($i:expr, $c:expr) => ({
         use $crate::parser::error::ParseError;
+        use $crate::parser::token::parsing::keyword;
 
         add_return_error!($i,
             ParseError::InvalidKeyword($c).into_nom(),
-            fix_error!(ParseError, tag!($c))
+            call!(keyword, $c)
+        )
+    })
+);
+
+/// Matches a punctuator token, adding a `ParseError::InvalidOperator` error to it
+///
+/// Used for multi-character punctuators such as `<<=` or `->`
+#[macro_export]
+macro_rules! op (
+    ($i:expr, $c:expr) => ({
+        use $crate::parser::error::ParseError;
+        use $crate::parser::token::parsing::punctuator;
+
+        add_return_error!($i,
+            ParseError::InvalidOperator($c).into_nom(),
+            call!(punctuator, $c)
         )
     })
-);
\ No newline at end of file
+);
+/// Wraps the output of the sub-parser in `Spanned`, using the span of the input it consumed
+#[macro_export]
+macro_rules! spanned (
+    ($i:expr, $submac:ident!( $($args:tt)* )) => ({
+        let input = $i;
+
+        match $submac!(input, $($args)*) {
+            ::nom::IResult::Done(rest, node) => {
+                let span = $crate::parser::span::consumed(input, rest);
+
+                ::nom::IResult::Done(rest, $crate::parser::span::Spanned::new(node, span))
+            },
+            ::nom::IResult::Error(e) => ::nom::IResult::Error(e),
+            ::nom::IResult::Incomplete(n) => ::nom::IResult::Incomplete(n),
+        }
+    });
+
+    ($i:expr, $f:expr) => (
+        spanned!($i, call!($f))
+    );
+);
@@ -11,14 +11,21 @@ pub enum StorageClassSpecifier {
 pub mod parsing {
     use super::*;
     use nom::*;
+    use ::wse;
 
-    named!(pub storage_class_specifier<StorageClassSpecifier>, alt!(
-        tag!("typedef")         => {|_| StorageClassSpecifier::Typedef     } |
-        tag!("extern")          => {|_| StorageClassSpecifier::Extern      } |
-        tag!("static")          => {|_| StorageClassSpecifier::Static      } |
-        tag!("thread_local")    => {|_| StorageClassSpecifier::ThreadLocal } |
-        tag!("auto")            => {|_| StorageClassSpecifier::Auto        } |
-        tag!("register")        => {|_| StorageClassSpecifier::Register    }
+    use ::parser::error::ParseError;
+
+    named!(pub storage_class_specifier<&[u8], StorageClassSpecifier, ParseError>, add_return_error!(
+        ParseError::InvalidStorageClassSpecifier.into_nom(),
+        wse!(alt_complete!(
+            keyword!("typedef")         => {|_| StorageClassSpecifier::Typedef     } |
+            keyword!("extern")          => {|_| StorageClassSpecifier::Extern      } |
+            keyword!("static")          => {|_| StorageClassSpecifier::Static      } |
+            keyword!("thread_local")    => {|_| StorageClassSpecifier::ThreadLocal } |
+            keyword!("_Thread_local")   => {|_| StorageClassSpecifier::ThreadLocal } |
+            keyword!("auto")            => {|_| StorageClassSpecifier::Auto        } |
+            keyword!("register")        => {|_| StorageClassSpecifier::Register    }
+        ))
     ));
 
     #[cfg(test)]
@@ -35,6 +42,7 @@ pub mod parsing {
             assert_eq!(storage_class_specifier(b"thread_local"), Done(&[] as &[u8], StorageClassSpecifier::ThreadLocal));
             assert_eq!(storage_class_specifier(b"auto"), Done(&[] as &[u8], StorageClassSpecifier::Auto));
             assert_eq!(storage_class_specifier(b"static"), Done(&[] as &[u8], StorageClassSpecifier::Static));
+            assert!(storage_class_specifier(b"statics").is_err());
         }
     }
 }
\ No newline at end of file
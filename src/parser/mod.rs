@@ -2,6 +2,7 @@
 pub mod macros;
 
 pub mod ident;
+pub mod keywords;
 pub mod misc;
 pub mod enumeration;
 pub mod operator;
@@ -10,7 +11,15 @@ pub mod comments;
 pub mod error;
 pub mod types;
 pub mod expression;
+pub mod constant;
+pub mod statement;
+pub mod declaration;
+pub mod initializer;
+pub mod translation_unit;
+pub mod scope;
+pub mod span;
 pub mod function;
 pub mod lit;
+pub mod token;
 pub mod whitespace;
 pub mod utils;
\ No newline at end of file
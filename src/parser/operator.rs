@@ -15,28 +15,178 @@ pub enum Operator {
 #[derive(Debug, Clone, Copy, PartialEq, Hash)]
 pub struct AssignmentOperator(pub Option<Operator>);
 
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum RelationalOperator {
+    LT,
+    GT,
+    LE,
+    GE,
+    EQ,
+    NE,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum LogicalOperator {
+    AND,
+    OR,
+}
+
+/// Prefix operators that take a cast-expression operand
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum UnaryOperator {
+    /// `&`
+    ADDR,
+    /// `*`
+    DEREF,
+    /// `+`
+    PLUS,
+    /// `-`
+    MINUS,
+    /// `~`
+    NOT,
+    /// `!`
+    LNOT,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub enum BinaryOperator {
+    Arithmetic(Operator),
+    Relational(RelationalOperator),
+    Logical(LogicalOperator),
+}
+
+impl BinaryOperator {
+    /// Binding strength of the operator, higher values bind tighter.
+    ///
+    /// All binary operators in C are left-associative.
+    pub fn precedence(&self) -> u8 {
+        match *self {
+            BinaryOperator::Arithmetic(op) => match op {
+                Operator::MUL | Operator::DIV | Operator::MOD => 10,
+                Operator::ADD | Operator::SUB => 9,
+                Operator::LEFT | Operator::RIGHT => 8,
+                Operator::AND => 5,
+                Operator::XOR => 4,
+                Operator::OR => 3,
+            },
+            BinaryOperator::Relational(op) => match op {
+                RelationalOperator::LT | RelationalOperator::GT |
+                RelationalOperator::LE | RelationalOperator::GE => 7,
+                RelationalOperator::EQ | RelationalOperator::NE => 6,
+            },
+            BinaryOperator::Logical(LogicalOperator::AND) => 2,
+            BinaryOperator::Logical(LogicalOperator::OR) => 1,
+        }
+    }
+}
+
 pub mod parsing {
     use super::*;
     use nom::*;
+    use ::wse;
+
+    use ::parser::error::ParseError;
+
+    named!(
+        #[doc = "Matches an arithmetic or bitwise operator"],
+        pub operator<&[u8], Operator, ParseError>,
+        wse!(alt_complete!(
+            punct!('*') => {|_| Operator::MUL   } |
+            punct!('/') => {|_| Operator::DIV   } |
+            punct!('%') => {|_| Operator::MOD   } |
+            punct!('+') => {|_| Operator::ADD   } |
+            punct!('-') => {|_| Operator::SUB   } |
+            op!("<<")   => {|_| Operator::LEFT  } |
+            op!(">>")   => {|_| Operator::RIGHT } |
+            punct!('&') => {|_| Operator::AND   } |
+            punct!('^') => {|_| Operator::XOR   } |
+            punct!('|') => {|_| Operator::OR    }
+        ))
+    );
+
+    named!(
+        #[doc = "Matches `=` and all compound assignment operators"],
+        pub assignment<&[u8], AssignmentOperator, ParseError>,
+        wse!(alt_complete!(
+            punct!('=') => {|_| AssignmentOperator(None)                  } |
+            op!("*=")   => {|_| AssignmentOperator(Some(Operator::MUL))   } |
+            op!("/=")   => {|_| AssignmentOperator(Some(Operator::DIV))   } |
+            op!("%=")   => {|_| AssignmentOperator(Some(Operator::MOD))   } |
+            op!("+=")   => {|_| AssignmentOperator(Some(Operator::ADD))   } |
+            op!("-=")   => {|_| AssignmentOperator(Some(Operator::SUB))   } |
+            op!("<<=")  => {|_| AssignmentOperator(Some(Operator::LEFT))  } |
+            op!(">>=")  => {|_| AssignmentOperator(Some(Operator::RIGHT)) } |
+            op!("&=")   => {|_| AssignmentOperator(Some(Operator::AND))   } |
+            op!("^=")   => {|_| AssignmentOperator(Some(Operator::XOR))   } |
+            op!("|=")   => {|_| AssignmentOperator(Some(Operator::OR))    }
+        ))
+    );
 
-    named!(pub operator<Operator>, ws!(alt_complete!(
-        char!('*') => {|_| Operator::MUL   } |
-        char!('/') => {|_| Operator::DIV   } |
-        char!('%') => {|_| Operator::MOD   } |
-        char!('+') => {|_| Operator::ADD   } |
-        char!('-') => {|_| Operator::SUB   } |
-        tag!("<<") => {|_| Operator::LEFT  } |
-        tag!(">>") => {|_| Operator::RIGHT } |
-        char!('&') => {|_| Operator::AND   } |
-        char!('^') => {|_| Operator::XOR   } |
-        char!('|') => {|_| Operator::OR    }
-    )));
-
-    named!(pub assignment<AssignmentOperator>, ws!(
-        do_parse!(
-            op: opt!(operator)  >>
-            char!('=')          >>
-            (AssignmentOperator(op))
-        )
-    ));
-}
\ No newline at end of file
+    named!(
+        #[doc = "Matches unary prefix operators, but not `++` or `--`"],
+        pub unary_operator<&[u8], UnaryOperator, ParseError>,
+        wse!(alt_complete!(
+            punct!('&') => {|_| UnaryOperator::ADDR  } |
+            punct!('*') => {|_| UnaryOperator::DEREF } |
+            punct!('+') => {|_| UnaryOperator::PLUS  } |
+            punct!('-') => {|_| UnaryOperator::MINUS } |
+            punct!('~') => {|_| UnaryOperator::NOT   } |
+            punct!('!') => {|_| UnaryOperator::LNOT  }
+        ))
+    );
+
+    named!(
+        #[doc = "Matches any binary operator, but not assignment operators"],
+        pub binary_operator<&[u8], BinaryOperator, ParseError>,
+        wse!(alt_complete!(
+            op!("||")   => {|_| BinaryOperator::Logical(LogicalOperator::OR)        } |
+            op!("&&")   => {|_| BinaryOperator::Logical(LogicalOperator::AND)       } |
+            op!("<<")   => {|_| BinaryOperator::Arithmetic(Operator::LEFT)          } |
+            op!(">>")   => {|_| BinaryOperator::Arithmetic(Operator::RIGHT)         } |
+            op!("<=")   => {|_| BinaryOperator::Relational(RelationalOperator::LE)  } |
+            op!(">=")   => {|_| BinaryOperator::Relational(RelationalOperator::GE)  } |
+            op!("==")   => {|_| BinaryOperator::Relational(RelationalOperator::EQ)  } |
+            op!("!=")   => {|_| BinaryOperator::Relational(RelationalOperator::NE)  } |
+            punct!('<') => {|_| BinaryOperator::Relational(RelationalOperator::LT)  } |
+            punct!('>') => {|_| BinaryOperator::Relational(RelationalOperator::GT)  } |
+            punct!('*') => {|_| BinaryOperator::Arithmetic(Operator::MUL)           } |
+            punct!('/') => {|_| BinaryOperator::Arithmetic(Operator::DIV)           } |
+            punct!('%') => {|_| BinaryOperator::Arithmetic(Operator::MOD)           } |
+            punct!('+') => {|_| BinaryOperator::Arithmetic(Operator::ADD)           } |
+            punct!('-') => {|_| BinaryOperator::Arithmetic(Operator::SUB)           } |
+            punct!('&') => {|_| BinaryOperator::Arithmetic(Operator::AND)           } |
+            punct!('^') => {|_| BinaryOperator::Arithmetic(Operator::XOR)           } |
+            punct!('|') => {|_| BinaryOperator::Arithmetic(Operator::OR)            }
+        ))
+    );
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_assignment_operators() {
+            let operators: &[(&[u8], Option<Operator>)] = &[
+                (b"=", None),
+                (b"*=", Some(Operator::MUL)),
+                (b"/=", Some(Operator::DIV)),
+                (b"%=", Some(Operator::MOD)),
+                (b"+=", Some(Operator::ADD)),
+                (b"-=", Some(Operator::SUB)),
+                (b"<<=", Some(Operator::LEFT)),
+                (b">>=", Some(Operator::RIGHT)),
+                (b"&=", Some(Operator::AND)),
+                (b"^=", Some(Operator::XOR)),
+                (b"|=", Some(Operator::OR)),
+            ];
+
+            for &(source, op) in operators {
+                assert_eq!(assignment(source), IResult::Done(&b""[..], AssignmentOperator(op)));
+            }
+
+            assert!(assignment(b"<<").is_err());
+            assert!(assignment(b"==").is_err());
+            assert_eq!(binary_operator(b"<< ="), IResult::Done(&b"="[..], BinaryOperator::Arithmetic(Operator::LEFT)));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Scoped tracking of typedef names
+//!
+//! C cannot be parsed without knowing which identifiers currently name types,
+//! since `T * x;` is a declaration if `T` is a typedef and a multiplication otherwise.
+//!
+//! Parsers are plain functions over byte slices, so the symbol table is kept per-thread
+//! and updated by the declaration, compound statement and function definition parsers
+//! as they succeed. Every file is parsed with a fresh table through `isolated`,
+//! so typedef names never leak from one parse into the next.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+
+use super::ident::Ident;
+
+/// What an identifier in the ordinary namespace refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    /// A name declared with `typedef`
+    Typedef,
+    /// Objects, functions and enumeration constants, which hide typedefs of the same name
+    Ordinary,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    scopes: Vec<HashMap<String, Symbol>>,
+}
+
+impl Default for SymbolTable {
+    fn default() -> SymbolTable {
+        SymbolTable::new()
+    }
+}
+
+impl SymbolTable {
+    /// Creates a symbol table containing only the file scope
+    pub fn new() -> SymbolTable {
+        SymbolTable { scopes: vec![HashMap::new()] }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Leaves the innermost scope, but never the file scope
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Declares a symbol in the innermost scope, replacing any previous declaration there
+    pub fn declare(&mut self, ident: &Ident, symbol: Symbol) {
+        let name: &str = ident.as_ref();
+
+        self.scopes.last_mut().unwrap().insert(name.to_owned(), symbol);
+    }
+
+    /// Looks up the innermost declaration of the identifier
+    pub fn lookup(&self, ident: &Ident) -> Option<Symbol> {
+        let name: &str = ident.as_ref();
+
+        self.scopes.iter().rev().filter_map(|scope| scope.get(name)).next().cloned()
+    }
+
+    pub fn is_typedef(&self, ident: &Ident) -> bool {
+        self.lookup(ident) == Some(Symbol::Typedef)
+    }
+}
+
+thread_local! {
+    static SYMBOLS: RefCell<SymbolTable> = RefCell::new(SymbolTable::new());
+}
+
+/// Runs `f` with the symbol table of the current thread
+pub fn with_symbols<F, R>(f: F) -> R where F: FnOnce(&mut SymbolTable) -> R {
+    SYMBOLS.with(|symbols| f(&mut symbols.borrow_mut()))
+}
+
+/// Replaces the symbol table of the current thread with an empty one
+pub fn reset() {
+    with_symbols(|symbols| *symbols = SymbolTable::new())
+}
+
+pub fn declare(ident: &Ident, symbol: Symbol) {
+    with_symbols(|symbols| symbols.declare(ident, symbol))
+}
+
+pub fn is_typedef(ident: &Ident) -> bool {
+    with_symbols(|symbols| symbols.is_typedef(ident))
+}
+
+// Leaves the scope entered by `scoped`, even when unwinding from a panic
+struct ScopeGuard;
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        with_symbols(|symbols| symbols.pop_scope())
+    }
+}
+
+// Puts back the symbol table replaced by `isolated`, even when unwinding from a panic
+struct TableGuard(Option<SymbolTable>);
+
+impl Drop for TableGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            with_symbols(|symbols| *symbols = previous);
+        }
+    }
+}
+
+/// Runs the parser `f` inside a new block scope, which is left again afterwards
+pub fn scoped<F, R>(f: F) -> R where F: FnOnce() -> R {
+    with_symbols(|symbols| symbols.push_scope());
+
+    let _guard = ScopeGuard;
+
+    f()
+}
+
+/// Runs `f` with an empty symbol table, restoring the current one afterwards
+///
+/// Used by every entry point that parses a whole file, and by tests that declare typedef names.
+pub fn isolated<F, R>(f: F) -> R where F: FnOnce() -> R {
+    let previous = with_symbols(|symbols| mem::replace(symbols, SymbolTable::new()));
+
+    let _guard = TableGuard(Some(previous));
+
+    f()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shadowing() {
+        let mut symbols = SymbolTable::new();
+
+        let t = Ident::from("T");
+
+        symbols.declare(&t, Symbol::Typedef);
+        assert!(symbols.is_typedef(&t));
+
+        symbols.push_scope();
+        symbols.declare(&t, Symbol::Ordinary);
+        assert!(!symbols.is_typedef(&t));
+
+        symbols.pop_scope();
+        assert!(symbols.is_typedef(&t));
+
+        // The file scope is never left
+        symbols.pop_scope();
+        assert_eq!(symbols.depth(), 1);
+    }
+
+    #[test]
+    fn test_isolated() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let t = Ident::from("T");
+
+        isolated(|| {
+            declare(&t, Symbol::Typedef);
+
+            isolated(|| assert!(!is_typedef(&t)));
+
+            // Scopes are left and tables restored when a parser panics
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                isolated(|| scoped(|| {
+                    declare(&Ident::from("U"), Symbol::Typedef);
+                    panic!("parser failure");
+                }))
+            }));
+
+            assert!(res.is_err());
+            assert!(is_typedef(&t));
+            assert!(!is_typedef(&Ident::from("U")));
+            assert_eq!(with_symbols(|symbols| symbols.depth()), 1);
+        });
+
+        assert!(!is_typedef(&t));
+    }
+}
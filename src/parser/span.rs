@@ -0,0 +1,395 @@
+//! Source locations of tokens and AST nodes
+//!
+//! Parsers operate on bare byte slices, so locations are recovered from the position of a slice
+//! within the source currently being parsed, which is registered per-thread with `with_source`.
+//! Outside of `with_source` every span is `Span::default()`.
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+
+use super::comments::{self, Comment, CommentRange, trim_trivia};
+use super::token::{self, Token};
+
+/// Identifies a source file, as assigned by the caller
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(pub u32);
+
+/// A byte range within a source file, along with the line and column it starts at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: FileId,
+    /// Byte offset of the first byte
+    pub start: usize,
+    /// Byte offset one past the last byte
+    pub end: usize,
+    /// One-based line of `start`
+    pub line: usize,
+    /// One-based column of `start`, counted in characters as by `char_count`
+    pub column: usize,
+}
+
+impl Span {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns a span from the start of `self` to the end of `other`
+    pub fn to(&self, other: Span) -> Span {
+        if other.end <= self.end {
+            return *self;
+        }
+
+        Span { end: other.end, ..*self }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// An AST node along with its location
+///
+/// Equality and hashing only consider the node, so trees parsed from differently
+/// formatted sources compare equal, and expected trees can be built with `From`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned { node: node, span: span }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.node
+    }
+
+    pub fn map<U, F>(self, f: F) -> Spanned<U> where F: FnOnce(T) -> U {
+        Spanned { node: f(self.node), span: self.span }
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Spanned<T> {
+        Spanned { node: node, span: Span::default() }
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T> DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.node
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Spanned<T>) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+impl<T: Hash> Hash for Spanned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.hash(state)
+    }
+}
+
+struct SourceContext {
+    file: FileId,
+    base: usize,
+    len: usize,
+    /// Byte offset of the start of every line
+    line_starts: Vec<usize>,
+    /// Byte offset of every UTF-8 continuation byte, which do not start a character
+    continuation_bytes: Vec<usize>,
+    /// Every comment, if they are being kept
+    comments: Vec<(CommentRange, Spanned<Comment>)>,
+    tokens: Vec<Spanned<Token>>,
+    /// Index of the token found last, since the grammar mostly looks up the same or the next token
+    last_token: Cell<usize>,
+}
+
+impl SourceContext {
+    fn new(file: FileId, source: &[u8], keep_comments: bool) -> SourceContext {
+        let line_starts = ::std::iter::once(0)
+            .chain(source.iter().enumerate().filter(|&(_, &c)| c == b'\n').map(|(i, _)| i + 1))
+            .collect();
+
+        let continuation_bytes = source.iter().enumerate()
+            .filter(|&(_, &c)| is_continuation_byte(c))
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut context = SourceContext {
+            file: file,
+            base: source.as_ptr() as usize,
+            len: source.len(),
+            line_starts: line_starts,
+            continuation_bytes: continuation_bytes,
+            comments: Vec::new(),
+            tokens: Vec::new(),
+            last_token: Cell::new(0),
+        };
+
+        if keep_comments {
+            context.comments = comments::find_comments(source).into_iter().map(|range| {
+                let comment = Comment {
+                    kind: range.kind(source),
+                    text: String::from_utf8_lossy(&source[range.start..range.end]).into_owned(),
+                };
+
+                (range, Spanned::new(comment, context.span(range.start, range.end)))
+            }).collect();
+        }
+
+        context
+    }
+
+    fn offset_of(&self, slice: &[u8]) -> Option<usize> {
+        let ptr = slice.as_ptr() as usize;
+
+        if ptr >= self.base && ptr + slice.len() <= self.base + self.len {
+            Some(ptr - self.base)
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the comments starting from the first for which `f` is true,
+    /// where `f` must be false for some prefix of the comments and true for the rest
+    fn comments_from<F>(&self, f: F) -> ::std::slice::Iter<(CommentRange, Spanned<Comment>)> where F: Fn(&CommentRange) -> bool {
+        let first = self.comments
+            .binary_search_by(|&(ref range, _)| if f(range) { Ordering::Greater } else { Ordering::Less })
+            .unwrap_err();
+
+        self.comments[first..].iter()
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        let line = match self.line_starts.binary_search(&start) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+
+        // Continuation bytes before an offset, whether or not the offset is one itself
+        let continuations = |offset| match self.continuation_bytes.binary_search(&offset) {
+            Ok(i) | Err(i) => i,
+        };
+
+        let line_start = self.line_starts[line];
+
+        Span {
+            file: self.file,
+            start: start,
+            end: end,
+            line: line + 1,
+            column: start - line_start - (continuations(start) - continuations(line_start)) + 1,
+        }
+    }
+}
+
+/// Whether `c` continues a UTF-8 character rather than starting one
+pub fn is_continuation_byte(c: u8) -> bool {
+    c & 0xC0 == 0x80
+}
+
+/// Number of characters in `text`, which is the number of bytes that are not UTF-8 continuation bytes
+///
+/// This is the unit of `Span::column` and of the columns of rendered diagnostics.
+pub fn char_count(text: &[u8]) -> usize {
+    text.iter().filter(|&&c| !is_continuation_byte(c)).count()
+}
+
+thread_local! {
+    static SOURCE: RefCell<Vec<SourceContext>> = RefCell::new(Vec::new());
+}
+
+/// Runs `f` with `source` registered as the file being parsed,
+/// so spans can be computed for any slice of it
+pub fn with_source<F, R>(file: FileId, source: &[u8], f: F) -> R where F: FnOnce() -> R {
+    with_context(SourceContext::new(file, source, false), source, f)
+}
+
+/// Like `with_source`, but also keeps every comment in `source`,
+/// so they can be found with `comments` and `doc_comments`
+pub fn with_source_comments<F, R>(file: FileId, source: &[u8], f: F) -> R where F: FnOnce() -> R {
+    with_context(SourceContext::new(file, source, true), source, f)
+}
+
+fn with_context<F, R>(context: SourceContext, source: &[u8], f: F) -> R where F: FnOnce() -> R {
+    // Unregisters the context, even when unwinding from a panic
+    struct Guard;
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            SOURCE.with(|contexts| contexts.borrow_mut().pop());
+        }
+    }
+
+    SOURCE.with(|contexts| contexts.borrow_mut().push(context));
+
+    let _guard = Guard;
+
+    // Tokens are located with the context, so it must be registered first
+    let tokens = token::tokenize_partial(source);
+
+    SOURCE.with(|contexts| {
+        if let Some(context) = contexts.borrow_mut().last_mut() {
+            context.tokens = tokens;
+        }
+    });
+
+    f()
+}
+
+/// Computes the span of the bytes consumed between `input` and `rest`, excluding surrounding whitespace and comments
+///
+/// `rest` must be a suffix of `input`, as with the input and remainder of a parser.
+pub fn consumed(input: &[u8], rest: &[u8]) -> Span {
+    let trimmed = trim_trivia(&input[..input.len() - rest.len()]);
+
+    SOURCE.with(|contexts| {
+        match contexts.borrow().last() {
+            Some(context) => match context.offset_of(trimmed) {
+                Some(start) => context.span(start, start + trimmed.len()),
+                None => Span::default(),
+            },
+            None => Span::default(),
+        }
+    })
+}
+
+/// Computes the location of an offset into the source currently being parsed
+pub fn location(offset: usize) -> Span {
+    SOURCE.with(|contexts| {
+        match contexts.borrow().last() {
+            Some(context) if offset <= context.len => context.span(offset, offset),
+            _ => Span::default(),
+        }
+    })
+}
+
+/// Calls `f` with the token at the start of `input`, if `input` is within the source currently
+/// being parsed and a token starts there
+pub fn cached_token<F, R>(input: &[u8], f: &F) -> Option<R> where F: Fn(&Spanned<Token>) -> R {
+    SOURCE.with(|contexts| {
+        let contexts = contexts.borrow();
+
+        let context = contexts.last()?;
+        let offset = context.offset_of(input)?;
+
+        let last = context.last_token.get();
+
+        let index = match context.tokens.get(last..last + 2) {
+            Some(near) if near[0].span.start == offset => last,
+            Some(near) if near[1].span.start == offset => last + 1,
+            _ => context.tokens.binary_search_by_key(&offset, |token| token.span.start).ok()?,
+        };
+
+        context.last_token.set(index);
+
+        Some(f(&context.tokens[index]))
+    })
+}
+
+/// Every comment in the source currently being parsed, if it was registered with `with_source_comments`
+pub fn comments() -> Vec<Spanned<Comment>> {
+    SOURCE.with(|contexts| {
+        match contexts.borrow().last() {
+            Some(context) => context.comments.iter().map(|&(_, ref comment)| comment.clone()).collect(),
+            None => Vec::new(),
+        }
+    })
+}
+
+/// Finds the documentation comments of a node spanning `span`
+///
+/// These are the documentation comments separated from the start of the node only by whitespace and
+/// other comments, followed by any `///<` or `/**<` comments after its end, which may be separated
+/// from it by a `,` or `;`. Always empty unless comments are being kept, as with `with_source_comments`.
+pub fn doc_comments(span: Span) -> Vec<Spanned<Comment>> {
+    SOURCE.with(|contexts| {
+        let contexts = contexts.borrow();
+
+        let context = match contexts.last() {
+            Some(context) if span != Span::default() && span.file == context.file => context,
+            _ => return Vec::new(),
+        };
+
+        let leading = context.comments_from(|range| range.next >= span.start)
+            .take_while(|&&(ref range, _)| range.next == span.start)
+            .filter(|&&(_, ref comment)| comment.is_doc() && !comment.is_trailing_doc());
+
+        let trailing = context.comments_from(|range| range.previous >= span.end)
+            .take_while(|&&(ref range, _)| range.previous_item <= span.end)
+            .filter(|&&(ref range, ref comment)| {
+                (range.previous == span.end || range.previous_item == span.end) && comment.is_trailing_doc()
+            });
+
+        leading.chain(trailing).map(|&(_, ref comment)| comment.clone()).collect()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_consumed() {
+        let source = b"int a;\n  int b;\n";
+
+        with_source(FileId(3), source, || {
+            let rest = &source[15..];
+
+            assert_eq!(consumed(&source[6..], rest), Span {
+                file: FileId(3),
+                start: 9,
+                end: 15,
+                line: 2,
+                column: 3,
+            });
+        });
+
+        assert_eq!(consumed(&source[6..], &source[15..]), Span::default());
+    }
+
+    #[test]
+    fn test_multibyte_columns() {
+        let source = "/* é */ int a;\n\"→\" b".as_bytes();
+
+        with_source(FileId::default(), source, || {
+            assert_eq!(consumed(&source[9..], &source[12..]).column, 9);
+            assert_eq!(consumed(&source[22..], &source[23..]).column, 5);
+        });
+
+        assert_eq!(char_count("é→x".as_bytes()), 3);
+    }
+
+    #[test]
+    fn test_spanned_equality() {
+        let a = Spanned::new(1, Span { start: 1, end: 2, ..Span::default() });
+
+        assert_eq!(a, Spanned::from(1));
+        assert_eq!(*a + 1, 2);
+    }
+}
@@ -0,0 +1,360 @@
+use super::ident::Ident;
+use super::expression::Expression;
+use super::declaration::{Declaration, StaticAssert};
+use super::span::Spanned;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Label {
+    /// `ident:`
+    Ident(Ident),
+    /// `case expr:`
+    Case(Spanned<Expression>),
+    /// `default:`
+    Default,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockItem {
+    Declaration(Spanned<Declaration>),
+    StaticAssert(Spanned<StaticAssert>),
+    Statement(Spanned<Statement>),
+}
+
+/// The first clause of a `for` loop
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForInit {
+    Expression(Spanned<Expression>),
+    /// C99 `for (int i = 0; ...)`
+    Declaration(Spanned<Declaration>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Labeled(Label, Box<Spanned<Statement>>),
+    /// `{ ... }`
+    Compound(Vec<BlockItem>),
+    /// An expression followed by `;`, or just `;` for the null statement
+    Expression(Option<Spanned<Expression>>),
+    /// `if (condition) then else otherwise`
+    If(Spanned<Expression>, Box<Spanned<Statement>>, Option<Box<Spanned<Statement>>>),
+    Switch(Spanned<Expression>, Box<Spanned<Statement>>),
+    While(Spanned<Expression>, Box<Spanned<Statement>>),
+    /// `do body while (condition);`
+    DoWhile(Box<Spanned<Statement>>, Spanned<Expression>),
+    For {
+        init: Option<ForInit>,
+        condition: Option<Spanned<Expression>>,
+        step: Option<Spanned<Expression>>,
+        body: Box<Spanned<Statement>>,
+    },
+    Goto(Ident),
+    Continue,
+    Break,
+    Return(Option<Spanned<Expression>>),
+}
+
+pub mod parsing {
+    use nom::*;
+    use ::wse;
+    use super::*;
+
+    use ::parser::error::ParseError;
+    use ::parser::ident::parsing::identifier;
+    use ::parser::expression::parsing::{expression, constant_expression};
+    use ::parser::declaration::parsing::{declaration, static_assert_declaration};
+    use ::parser::scope;
+    use ::parser::span::Spanned;
+
+    named!(
+        #[doc = "Matches `ident:`, `case expr:` and `default:` labels"],
+        pub label<&[u8], Label, ParseError>,
+        wse!(alt_complete!(
+            delimited!(keyword!("case"), constant_expression, punct!(':'))  => { |e| Label::Case(e) } |
+            terminated!(keyword!("default"), punct!(':'))                   => { |_| Label::Default } |
+            terminated!(identifier, punct!(':'))                            => { |ident| Label::Ident(ident) }
+        ))
+    );
+
+    named!(
+        #[doc = "Matches a label followed by the statement it labels"],
+        pub labeled_statement<&[u8], Spanned<Statement>, ParseError>,
+        spanned!(wse!(do_parse!(
+            label: label        >>
+            statement: statement  >>
+            (Statement::Labeled(label, Box::new(statement)))
+        )))
+    );
+
+    named!(
+        #[doc = "Matches a declaration or a statement inside a block"],
+        pub block_item<&[u8], BlockItem, ParseError>,
+        wse!(alt_complete!(
+            static_assert_declaration   => { |s| BlockItem::StaticAssert(s) } |
+            declaration                 => { |d| BlockItem::Declaration(d) } |
+            statement                   => { |s| BlockItem::Statement(s) }
+        ))
+    );
+
+    named!(block<&[u8], Statement, ParseError>, wse!(delimited!(
+        punct!('{'),
+        map!(many0!(block_item), Statement::Compound),
+        punct!('}')
+    )));
+
+    /// Matches a brace-enclosed block of declarations and statements
+    ///
+    /// Names declared inside the block go out of scope at its end.
+    pub fn compound_statement(input: &[u8]) -> IResult<&[u8], Spanned<Statement>, ParseError> {
+        scope::scoped(|| spanned!(input, block))
+    }
+
+    named!(
+        #[doc = "Matches an optional expression terminated by `;`"],
+        pub expression_statement<&[u8], Spanned<Statement>, ParseError>,
+        spanned!(wse!(terminated!(
+            map!(opt!(expression), Statement::Expression),
+            punct!(';')
+        )))
+    );
+
+    // Declarations include their own semicolon
+    named!(for_init<&[u8], Option<ForInit>, ParseError>, wse!(alt_complete!(
+        declaration                             => { |d| Some(ForInit::Declaration(d)) } |
+        terminated!(opt!(expression), punct!(';')) => { |e: Option<Spanned<Expression>>| e.map(ForInit::Expression) }
+    )));
+
+    named!(parenthesized_expression<&[u8], Spanned<Expression>, ParseError>,
+        wse!(delimited!(punct!('('), expression, punct!(')'))));
+
+    named!(
+        #[doc = "Matches `if` and `switch` statements"],
+        pub selection_statement<&[u8], Spanned<Statement>, ParseError>,
+        spanned!(wse!(alt_complete!(
+            do_parse!(
+                keyword!("if")                                          >>
+                condition: parenthesized_expression                     >>
+                then: statement                                         >>
+                otherwise: opt!(complete!(preceded!(keyword!("else"), statement))) >>
+                (Statement::If(condition, Box::new(then), otherwise.map(Box::new)))
+            ) |
+            do_parse!(
+                keyword!("switch")                  >>
+                condition: parenthesized_expression >>
+                body: statement                     >>
+                (Statement::Switch(condition, Box::new(body)))
+            )
+        )))
+    );
+
+    named!(for_loop<&[u8], Statement, ParseError>, wse!(do_parse!(
+        keyword!("for")                     >>
+        punct!('(')                         >>
+        init: for_init                      >>
+        condition: opt!(expression)         >>
+        punct!(';')                         >>
+        step: opt!(expression)              >>
+        punct!(')')                         >>
+        body: statement                     >>
+        (Statement::For {
+            init: init,
+            condition: condition,
+            step: step,
+            body: Box::new(body),
+        })
+    )));
+
+    // A declaration in the first clause is only visible inside the loop
+    fn for_statement(input: &[u8]) -> IResult<&[u8], Statement, ParseError> {
+        scope::scoped(|| for_loop(input))
+    }
+
+    named!(
+        #[doc = "Matches `while`, `do`/`while` and `for` loops"],
+        pub iteration_statement<&[u8], Spanned<Statement>, ParseError>,
+        spanned!(wse!(alt_complete!(
+            do_parse!(
+                keyword!("while")                   >>
+                condition: parenthesized_expression >>
+                body: statement                     >>
+                (Statement::While(condition, Box::new(body)))
+            ) |
+            do_parse!(
+                keyword!("do")                      >>
+                body: statement                     >>
+                keyword!("while")                   >>
+                condition: parenthesized_expression >>
+                punct!(';')                         >>
+                (Statement::DoWhile(Box::new(body), condition))
+            ) |
+            for_statement
+        )))
+    );
+
+    named!(
+        #[doc = "Matches `goto`, `continue`, `break` and `return` statements"],
+        pub jump_statement<&[u8], Spanned<Statement>, ParseError>,
+        spanned!(wse!(terminated!(
+            alt_complete!(
+                preceded!(keyword!("goto"), identifier)     => { |ident| Statement::Goto(ident) } |
+                keyword!("continue")                        => { |_| Statement::Continue } |
+                keyword!("break")                           => { |_| Statement::Break } |
+                preceded!(keyword!("return"), opt!(expression)) => { |e| Statement::Return(e) }
+            ),
+            punct!(';')
+        )))
+    );
+
+    named!(
+        #[doc = "Matches any statement"],
+        pub statement<&[u8], Spanned<Statement>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidStatement.into_nom(),
+            wse!(alt_complete!(
+                labeled_statement   |
+                compound_statement  |
+                selection_statement |
+                iteration_statement |
+                jump_statement      |
+                expression_statement
+            ))
+        )
+    );
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::operator::*;
+
+        fn ident(name: &str) -> Spanned<Expression> {
+            Expression::Identifier(Ident::from(name)).into()
+        }
+
+        fn call(name: &str) -> Spanned<Expression> {
+            Expression::Call(Box::new(ident(name)), vec![]).into()
+        }
+
+        fn boxed(s: Statement) -> Box<Spanned<Statement>> {
+            Box::new(s.into())
+        }
+
+        fn stmt(source: &[u8]) -> Statement {
+            match statement(source) {
+                IResult::Done(rest, s) => {
+                    assert_eq!(rest, b"");
+                    s.node
+                },
+                res => panic!("Unexpected result {:?}", res)
+            }
+        }
+
+        #[test]
+        fn test_selection_statement() {
+            assert_eq!(stmt(b"if (a) b; else if (c) { d(); } else ;"), Statement::If(
+                ident("a"),
+                boxed(Statement::Expression(Some(ident("b")))),
+                Some(boxed(Statement::If(
+                    ident("c"),
+                    boxed(Statement::Compound(vec![
+                        BlockItem::Statement(Statement::Expression(Some(call("d"))).into())
+                    ])),
+                    Some(boxed(Statement::Expression(None)))
+                )))
+            ));
+        }
+
+        #[test]
+        fn test_iteration_statement() {
+            assert_eq!(stmt(b"for (;; i++) { if (i) break; continue; }"), Statement::For {
+                init: None,
+                condition: None,
+                step: Some(Expression::PostIncrement(Box::new(ident("i"))).into()),
+                body: boxed(Statement::Compound(vec![
+                    BlockItem::Statement(Statement::If(ident("i"), boxed(Statement::Break), None).into()),
+                    BlockItem::Statement(Statement::Continue.into()),
+                ])),
+            });
+
+            match stmt(b"for (int i = 0; i < n; ++i) {}") {
+                Statement::For { init: Some(ForInit::Declaration(ref d)), .. } => {
+                    assert_eq!(d.declarators.len(), 1);
+                    assert_eq!(d.declarators[0].declarator.ident(), Some(&Ident::from("i")));
+                },
+                ref other => panic!("Unexpected statement {:?}", other)
+            }
+
+            assert_eq!(stmt(b"do x -= 1; while (x);"), Statement::DoWhile(
+                boxed(Statement::Expression(Some(Expression::Assignment(
+                    AssignmentOperator(Some(Operator::SUB)),
+                    Box::new(ident("x")),
+                    Box::new(Expression::Literal(::parser::lit::Lit::Integer(
+                        ::parser::lit::IntegerLiteral::Signed(::parser::lit::SignedIntegerValue::Int(1))
+                    )).into())
+                ).into()))),
+                ident("x")
+            ));
+        }
+
+        #[test]
+        fn test_labeled_statement() {
+            let switch = statement(b"switch (x) { case 1: default: return; } end: goto end;");
+
+            match switch {
+                IResult::Done(rest, Spanned { node: Statement::Switch(_, body), .. }) => {
+                    assert_eq!(rest, b"end: goto end;");
+
+                    match body.node {
+                        Statement::Compound(ref items) => {
+                            assert_eq!(items.len(), 1);
+
+                            match items[0] {
+                                BlockItem::Statement(Spanned { node: Statement::Labeled(Label::Case(_), ref inner), .. }) => {
+                                    assert_eq!(**inner, Statement::Labeled(Label::Default, boxed(Statement::Return(None))).into());
+                                },
+                                ref other => panic!("Unexpected statement {:?}", other)
+                            }
+                        },
+                        ref other => panic!("Unexpected statement {:?}", other)
+                    }
+                },
+                other => panic!("Unexpected result {:?}", other)
+            }
+
+            assert_eq!(stmt(b"end: goto end;"), Statement::Labeled(
+                Label::Ident(Ident::from("end")),
+                boxed(Statement::Goto(Ident::from("end")))
+            ));
+        }
+
+        #[test]
+        fn test_statement_spans() {
+            use ::parser::span::{self, FileId};
+
+            let source = b"{\n    if (a)\n        b++;\n}";
+
+            span::with_source(FileId(1), source, || {
+                let block = statement(source).unwrap().1;
+
+                assert_eq!((block.span.start, block.span.end), (0, source.len()));
+
+                match block.node {
+                    Statement::Compound(ref items) => match items[0] {
+                        BlockItem::Statement(ref s) => {
+                            assert_eq!((s.span.line, s.span.column), (2, 5));
+                            assert_eq!(&source[s.span.start..s.span.end], b"if (a)\n        b++;");
+
+                            match s.node {
+                                Statement::If(_, ref then, _) => {
+                                    assert_eq!((then.span.line, then.span.column), (3, 9));
+                                },
+                                ref other => panic!("Unexpected statement {:?}", other)
+                            }
+                        },
+                        ref other => panic!("Unexpected item {:?}", other)
+                    },
+                    ref other => panic!("Unexpected statement {:?}", other)
+                }
+            });
+        }
+    }
+}
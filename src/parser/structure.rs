@@ -1,28 +1,258 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use super::ident::Ident;
+use super::expression::Expression;
+use super::declaration::{TypeName, StaticAssert};
+use super::span::Spanned;
+use super::comments::Comment;
 
-#[derive(Debug, Clone, PartialEq, Hash)]
+/// A named or anonymous struct or union member
+///
+/// Anonymous members (C11) have no identifier and a struct or union type,
+/// while unnamed bit-fields have no identifier and a width.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Field {
-    ident: Ident,
-    ty: ()
+    pub ident: Option<Ident>,
+    pub ty: TypeName,
+    pub bit_width: Option<Spanned<Expression>>,
+    /// Documentation comments, which are only kept when parsing with `ParseOptions::keep_comments`
+    pub doc: Vec<Spanned<Comment>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Hash)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Member {
+    Field(Field),
+    StaticAssert(StaticAssert),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct VariantData {
-    ident: Ident,
-    fields: Vec<Field>,
+    pub ident: Option<Ident>,
+    /// `None` for forward declarations and references such as `struct tag`
+    pub members: Option<Vec<Spanned<Member>>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Hash)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Variant {
     Struct(VariantData),
     Union(VariantData),
 }
 
+impl Variant {
+    pub fn data(&self) -> &VariantData {
+        match *self {
+            Variant::Struct(ref data) | Variant::Union(ref data) => data,
+        }
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let keyword = match *self {
+            Variant::Struct(_) => "struct",
+            Variant::Union(_) => "union",
+        };
+
+        match self.data().ident {
+            Some(ref ident) => write!(f, "{} {}", keyword, ident),
+            None => write!(f, "{} <anonymous>", keyword),
+        }
+    }
+}
+
 pub mod parsing {
     use nom::*;
+    use ::wse;
     use super::*;
 
+    use ::parser::error::ParseError;
     use ::parser::ident::parsing::*;
-}
\ No newline at end of file
+    use ::parser::expression::parsing::constant_expression;
+    use ::parser::declaration::Declarator;
+    use ::parser::declaration::parsing::{declarator, specifier_qualifier_list, static_assert_declaration};
+    use ::parser::span::{self, Span, Spanned, consumed};
+
+    named!(bit_width<&[u8], Spanned<Expression>, ParseError>,
+        wse!(preceded!(punct!(':'), constant_expression)));
+
+    named!(member_declarator<&[u8], (Option<Spanned<Declarator>>, Option<Spanned<Expression>>), ParseError>, wse!(alt_complete!(
+        pair!(declarator, opt!(complete!(bit_width))) => { |(d, width)| (Some(d), width) } |
+        bit_width                                     => { |width| (None, Some(width)) }
+    )));
+
+    // Each field is located by its declarator, or by the whole declaration for anonymous members.
+    // Documentation before the declaration applies to all of its fields.
+    fn field_declaration(input: &[u8]) -> IResult<&[u8], Vec<Spanned<Member>>, ParseError> {
+        let (rest, (specifiers, declarators, _)) = try_parse!(input, wse!(tuple!(
+            specifier_qualifier_list,
+            separated_list!(punct!(','), spanned!(member_declarator)),
+            punct!(';')
+        )));
+
+        let whole = consumed(input, rest);
+
+        let members = if declarators.is_empty() {
+            vec![Spanned::new(Member::Field(Field {
+                ident: None,
+                ty: TypeName { specifiers: specifiers, declarator: None },
+                bit_width: None,
+                doc: span::doc_comments(whole),
+            }), whole)]
+        } else {
+            declarators.into_iter().map(|member| {
+                let Spanned { node: (declarator, bit_width), span } = member;
+
+                let (ident, ty) = match declarator {
+                    Some(declarator) => TypeName::from_declarator(specifiers.clone(), declarator),
+                    None => (None, TypeName { specifiers: specifiers.clone(), declarator: None }),
+                };
+
+                let doc = span::doc_comments(Span { end: span.end, ..whole });
+
+                Spanned::new(Member::Field(Field { ident: ident, ty: ty, bit_width: bit_width, doc: doc }), span)
+            }).collect()
+        };
+
+        IResult::Done(rest, members)
+    }
+
+    named!(
+        #[doc = "Matches a member declaration, which may declare several fields at once"],
+        pub member_declaration<&[u8], Vec<Spanned<Member>>, ParseError>,
+        add_return_error!(
+            ParseError::InvalidMemberDeclaration.into_nom(),
+            wse!(alt_complete!(
+                static_assert_declaration   => { |s: Spanned<StaticAssert>| vec![s.map(Member::StaticAssert)] } |
+                field_declaration
+            ))
+        )
+    );
+
+    named!(member_list<&[u8], Vec<Spanned<Member>>, ParseError>, wse!(delimited!(
+        punct!('{'),
+        map!(many0!(member_declaration), |members: Vec<Vec<Spanned<Member>>>| {
+            members.into_iter().flat_map(|m| m).collect()
+        }),
+        punct!('}')
+    )));
+
+    named!(struct_or_union<&[u8], fn(VariantData) -> Variant, ParseError>, wse!(alt_complete!(
+        keyword!("struct")  => { |_| Variant::Struct as fn(VariantData) -> Variant } |
+        keyword!("union")   => { |_| Variant::Union as fn(VariantData) -> Variant }
+    )));
+
+    /// Matches a struct or union definition, forward declaration or reference
+    pub fn struct_or_union_specifier(input: &[u8]) -> IResult<&[u8], Variant, ParseError> {
+        let (rest, (variant, ident, members)) = try_parse!(input, add_return_error!(
+            ParseError::InvalidStructOrUnion.into_nom(),
+            wse!(tuple!(
+                struct_or_union,
+                opt!(identifier),
+                opt!(member_list)
+            ))
+        ));
+
+        // Either a tag or a member list is required
+        if ident.is_none() && members.is_none() {
+            return IResult::Error(error_position!(ParseError::InvalidStructOrUnion.into_nom(), input));
+        }
+
+        IResult::Done(rest, variant(VariantData { ident: ident, members: members }))
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::declaration::*;
+        use ::parser::types::TypeSpecifier;
+
+        fn field(member: &Member) -> &Field {
+            match *member {
+                Member::Field(ref field) => field,
+                ref other => panic!("Unexpected member {:?}", other)
+            }
+        }
+
+        #[test]
+        fn test_struct_definition() {
+            let res = struct_or_union_specifier(b"struct point { int x, y; unsigned flags : 3; int : 0; char data[]; }");
+
+            match res {
+                IResult::Done(rest, Variant::Struct(data)) => {
+                    assert_eq!(rest, b"");
+                    assert_eq!(data.ident, Some(Ident::from("point")));
+
+                    let members = data.members.unwrap();
+
+                    assert_eq!(members.len(), 5);
+
+                    assert_eq!(field(&members[1]).ident, Some(Ident::from("y")));
+                    assert_eq!(field(&members[1]).ty, TypeName {
+                        specifiers: vec![DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Int).into()],
+                        declarator: None,
+                    });
+
+                    assert_eq!(field(&members[2]).ident, Some(Ident::from("flags")));
+                    assert!(field(&members[2]).bit_width.is_some());
+
+                    assert_eq!(field(&members[3]).ident, None);
+                    assert!(field(&members[3]).bit_width.is_some());
+
+                    // Flexible array member
+                    assert_eq!(field(&members[4]).ty.declarator, Some(Declarator {
+                        pointers: vec![],
+                        direct: DirectDeclarator::Array(Box::new(DirectDeclarator::Abstract.into()), ArrayDeclarator {
+                            qualifiers: vec![],
+                            is_static: false,
+                            size: ArraySize::Unspecified,
+                        }).into(),
+                    }.into()));
+                },
+                other => panic!("Unexpected result {:?}", other)
+            }
+        }
+
+        #[test]
+        fn test_anonymous_members() {
+            let res = struct_or_union_specifier(b"union { struct { int a; }; _Static_assert(1, \"ok\"); float f; }");
+
+            match res {
+                IResult::Done(_, Variant::Union(data)) => {
+                    assert_eq!(data.ident, None);
+
+                    let members = data.members.unwrap();
+
+                    assert_eq!(members.len(), 3);
+
+                    let anonymous = field(&members[0]);
+
+                    assert_eq!(anonymous.ident, None);
+
+                    match anonymous.ty.specifiers[0].node {
+                        DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Struct(ref variant)) => {
+                            assert_eq!(variant.data().members.as_ref().map(|m| m.len()), Some(1));
+                        },
+                        ref other => panic!("Unexpected specifier {:?}", other)
+                    }
+
+                    match members[1].node {
+                        Member::StaticAssert(_) => {},
+                        ref other => panic!("Unexpected member {:?}", other)
+                    }
+                },
+                other => panic!("Unexpected result {:?}", other)
+            }
+        }
+
+        #[test]
+        fn test_forward_declaration() {
+            assert_eq!(struct_or_union_specifier(b"struct node"), IResult::Done(&[] as &[u8], Variant::Struct(VariantData {
+                ident: Some(Ident::from("node")),
+                members: None,
+            })));
+
+            assert!(struct_or_union_specifier(b"struct ;").is_err());
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Splitting source into tokens
+//!
+//! Every source registered with `span::with_source` is tokenized up front, and the terminals of the
+//! grammar, being `keyword!`, `punct!`, `op!` and `identifier`, match whole tokens from that stream
+//! instead of prefixes of the remaining bytes. So `static` does not match the start of `statics`,
+//! and `<` does not match the start of `<<=`.
+//!
+//! The grammar itself still runs over byte slices rather than over `&[Token]`, so spans and
+//! diagnostics are unaffected. A terminal looks its token up by the offset of the remaining input,
+//! and input outside of `with_source` is tokenized as it is parsed. This fixes matching prefixes of
+//! tokens, but is no faster than matching bytes directly, since whitespace between tokens is still
+//! skipped by every parser. Only converting the grammar to take tokens as input would change that.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use nom::IResult;
+
+use super::ident::Ident;
+use super::lit::Lit;
+use super::comments::trivia_len;
+use super::error::{Diagnostic, ParseError};
+use super::span::{self, FileId, Spanned};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Keyword(&'static str),
+    Identifier(Ident),
+    /// Digraphs are replaced by the punctuator they stand for, so `<:` is `[`
+    Punctuator(&'static str),
+    Literal(Lit),
+}
+
+impl Token {
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        match *self {
+            Token::Keyword(k) => k == keyword,
+            _ => false,
+        }
+    }
+
+    pub fn is_punctuator(&self, punctuator: &str) -> bool {
+        match *self {
+            Token::Punctuator(p) => p == punctuator,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            Token::Keyword(text) | Token::Punctuator(text) => f.write_str(text),
+            Token::Identifier(ref ident) => ident.fmt(f),
+            Token::Literal(ref lit) => write!(f, "{:?}", lit),
+        }
+    }
+}
+
+/// Every punctuator, including digraphs, ordered so that the first one matching is the longest
+pub const PUNCTUATORS: &'static [&'static str] = &[
+    "%:%:", "...", "<<=", ">>=",
+    "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "*=", "/=", "%=", "+=", "-=", "&=", "^=", "|=", "##", "<:", ":>", "<%", "%>", "%:",
+    "[", "]", "(", ")", "{", "}", ".", "&", "*", "+", "-", "~", "!",
+    "/", "%", "<", ">", "^", "|", "?", ":", ";", "=", ",", "#",
+];
+
+fn canonical(punctuator: &'static str) -> &'static str {
+    match punctuator {
+        "<:" => "[",
+        ":>" => "]",
+        "<%" => "{",
+        "%>" => "}",
+        "%:" => "#",
+        "%:%:" => "##",
+        _ => punctuator,
+    }
+}
+
+/// Splits `source` into tokens, skipping whitespace and comments
+pub fn tokenize(file: FileId, source: &[u8]) -> Result<Vec<Spanned<Token>>, Diagnostic> {
+    span::with_source(file, source, || {
+        let mut tokens = Vec::new();
+
+        let mut input = &source[trivia_len(source)..];
+
+        while !input.is_empty() {
+            match self::parsing::token(input) {
+                IResult::Done(rest, token) => {
+                    tokens.push(token);
+                    input = &rest[trivia_len(rest)..];
+                },
+                IResult::Error(err) => return Err(Diagnostic::from_nom(source, &err)),
+                IResult::Incomplete(_) => return Err(Diagnostic::new(source.len(), ParseError::UnexpectedEndOfInput)),
+            }
+        }
+
+        Ok(tokens)
+    })
+}
+
+/// Like `tokenize`, but skips over anything that is not a valid token
+///
+/// Used by `span::with_source`, so the spans are those of the source currently being parsed.
+pub fn tokenize_partial(source: &[u8]) -> Vec<Spanned<Token>> {
+    let mut tokens = Vec::new();
+
+    let mut input = &source[trivia_len(source)..];
+
+    while !input.is_empty() {
+        input = match self::parsing::raw_token(input) {
+            IResult::Done(rest, token) => {
+                tokens.push(token);
+                rest
+            },
+            _ => {
+                let len = input.iter().position(|c| c.is_ascii_whitespace()).unwrap_or(input.len());
+
+                &input[len..]
+            }
+        };
+
+        input = &input[trivia_len(input)..];
+    }
+
+    tokens
+}
+
+pub mod parsing {
+    use nom::*;
+
+    use super::*;
+
+    use ::parser::keywords::{reserved_word, is_ever_reserved, standard};
+    use ::parser::lit::parsing::{raw_string_literal, raw_char_literal, raw_float_literal, raw_integer_literal};
+    use ::parser::span::consumed;
+
+    fn is_identifier_start(c: u8) -> bool {
+        c == b'_' || c.is_ascii_alphabetic()
+    }
+
+    fn is_identifier_character(c: u8) -> bool {
+        is_identifier_start(c) || c.is_ascii_digit()
+    }
+
+    fn word(input: &[u8]) -> IResult<&[u8], Token, ParseError> {
+        let len = input.iter().position(|&c| !is_identifier_character(c)).unwrap_or(input.len());
+
+        let (word, rest) = input.split_at(len);
+
+        // Prefixed string and character literals, as in `u8"text"` and `L'c'`
+        if let b"u8" | b"u" | b"U" | b"L" = word {
+            match rest.first() {
+                Some(&b'"') => return map!(input, raw_string_literal, Token::Literal),
+                Some(&b'\'') => return map!(input, raw_char_literal, Token::Literal),
+                _ => {}
+            }
+        }
+
+        let token = match reserved_word(word, standard()) {
+            Some(keyword) => Token::Keyword(keyword),
+            None => Token::Identifier(Ident::from(String::from_utf8_lossy(word)).with_span(consumed(input, rest))),
+        };
+
+        IResult::Done(rest, token)
+    }
+
+    // Floating constants are tried first, since the integer part of one is an integer constant
+    fn number(input: &[u8]) -> IResult<&[u8], Token, ParseError> {
+        match raw_float_literal(input) {
+            IResult::Done(rest, lit) => IResult::Done(rest, Token::Literal(lit)),
+            _ => map!(input, raw_integer_literal, Token::Literal),
+        }
+    }
+
+    fn punctuator_token(input: &[u8]) -> IResult<&[u8], Token, ParseError> {
+        match PUNCTUATORS.iter().find(|p| input.starts_with(p.as_bytes())) {
+            Some(&punctuator) => IResult::Done(&input[punctuator.len()..], Token::Punctuator(canonical(punctuator))),
+            None => IResult::Error(error_position!(ParseError::InvalidToken.into_nom(), input)),
+        }
+    }
+
+    /// Matches a single token at the very start of `input`
+    pub fn raw_token(input: &[u8]) -> IResult<&[u8], Spanned<Token>, ParseError> {
+        let first = match input.first() {
+            Some(&c) => c,
+            None => return IResult::Error(error_position!(ParseError::InvalidToken.into_nom(), input)),
+        };
+
+        let starts_number = first.is_ascii_digit() || (first == b'.' && input.get(1).map_or(false, |c| c.is_ascii_digit()));
+
+        let res = if is_identifier_start(first) {
+            word(input)
+        } else if starts_number {
+            number(input)
+        } else if first == b'"' {
+            map!(input, raw_string_literal, Token::Literal)
+        } else if first == b'\'' {
+            map!(input, raw_char_literal, Token::Literal)
+        } else if input.starts_with(b"/*") {
+            // Terminated comments are skipped as trivia before any token, so this one is never closed
+            IResult::Error(error_position!(ParseError::InvalidComment.into_nom(), input))
+        } else {
+            punctuator_token(input)
+        };
+
+        match res {
+            IResult::Done(rest, token) => IResult::Done(rest, Spanned::new(token, consumed(input, rest))),
+            IResult::Error(err) => IResult::Error(err),
+            IResult::Incomplete(needed) => IResult::Incomplete(needed),
+        }
+    }
+
+    /// Matches the next token, skipping any whitespace and comments before it
+    ///
+    /// Within `span::with_source`, the token is taken from the tokens of the source instead of being scanned again.
+    pub fn token(input: &[u8]) -> IResult<&[u8], Spanned<Token>, ParseError> {
+        let input = &input[trivia_len(input)..];
+
+        match span::cached_token(input, &|token| token.clone()) {
+            Some(token) => {
+                let len = token.span.len();
+
+                IResult::Done(&input[len..], token)
+            },
+            None => raw_token(input),
+        }
+    }
+
+    // Matches the token at the start of `input` if `f` is true for it, returning the bytes it spans
+    fn matching<'a, F>(input: &'a [u8], error: ErrorKind<ParseError>, f: F) -> IResult<&'a [u8], &'a [u8], ParseError>
+        where F: Fn(&Token) -> bool {
+        let len = match span::cached_token(input, &|token| if f(&token.node) { Some(token.span.len()) } else { None }) {
+            Some(len) => len,
+            None => match raw_token(input) {
+                IResult::Done(rest, ref token) if f(&token.node) => Some(input.len() - rest.len()),
+                _ => None,
+            },
+        };
+
+        match len {
+            Some(len) => IResult::Done(&input[len..], &input[..len]),
+            None => IResult::Error(error_position!(error, input)),
+        }
+    }
+
+    /// Matches `keyword` as a whole token
+    ///
+    /// Words that are not reserved by any standard, such as `__func__`, are matched as identifiers,
+    /// while reserved words only match where the standard being parsed reserves them.
+    pub fn keyword<'a>(input: &'a [u8], keyword: &'static str) -> IResult<&'a [u8], &'a [u8], ParseError> {
+        matching(input, ErrorKind::Tag, |token| match *token {
+            Token::Keyword(k) => k == keyword,
+            Token::Identifier(ref ident) => *ident == keyword && !is_ever_reserved(keyword),
+            _ => false,
+        })
+    }
+
+    /// Matches `punctuator` as a whole token
+    pub fn punctuator<'a>(input: &'a [u8], punctuator: &'static str) -> IResult<&'a [u8], &'a [u8], ParseError> {
+        matching(input, ErrorKind::Tag, |token| token.is_punctuator(punctuator))
+    }
+
+    /// Matches the single-character punctuator `c` as a whole token
+    pub fn punctuator_char(input: &[u8], c: char) -> IResult<&[u8], &[u8], ParseError> {
+        matching(input, ErrorKind::Char, |token| match *token {
+            Token::Punctuator(p) => p.len() == 1 && p.as_bytes()[0] == c as u8,
+            _ => false,
+        })
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::lit::{IntegerLiteral, SignedIntegerValue, StringPrefix};
+
+        fn tokens(source: &[u8]) -> Vec<Token> {
+            tokenize(FileId::default(), source).unwrap().into_iter().map(Spanned::into_inner).collect()
+        }
+
+        #[test]
+        fn test_tokenize() {
+            assert_eq!(tokens(b"static int statics<<=1;// done"), vec![
+                Token::Keyword("static"),
+                Token::Keyword("int"),
+                Token::Identifier(Ident::from("statics")),
+                Token::Punctuator("<<="),
+                Token::Literal(Lit::Integer(IntegerLiteral::Signed(SignedIntegerValue::Int(1)))),
+                Token::Punctuator(";"),
+            ]);
+
+            assert_eq!(tokens(b"a<:0:> %:%: ...")[1], Token::Punctuator("["));
+            assert_eq!(tokens(b"u8\"s\" L'c'").len(), 2);
+            assert_eq!(tokens(b"U\"s\" u'c' U'c' u8'c'"), vec![
+                Token::Literal(Lit::Str { value: "s".to_owned(), prefix: StringPrefix::Utf32 }),
+                Token::Literal(Lit::Char { value: 'c', prefix: StringPrefix::Utf16 }),
+                Token::Literal(Lit::Char { value: 'c', prefix: StringPrefix::Utf32 }),
+                Token::Literal(Lit::Char { value: 'c', prefix: StringPrefix::Utf8 }),
+            ]);
+
+            let spans: Vec<_> = tokenize(FileId(2), b"x /* y */ ->\n  1.5f").unwrap().into_iter().map(|t| (t.span.start, t.span.end, t.span.line)).collect();
+
+            assert_eq!(spans, vec![(0, 1, 1), (10, 12, 1), (15, 19, 2)]);
+
+            assert!(tokenize(FileId::default(), b"int @;").is_err());
+        }
+
+        #[test]
+        fn test_whole_tokens() {
+            assert_eq!(keyword(b"static int", "static"), IResult::Done(&b" int"[..], &b"static"[..]));
+            assert!(keyword(b"statics", "static").is_err());
+            assert!(punctuator_char(b"<<= 1", '<').is_err());
+            assert_eq!(punctuator(b"<<= 1", "<<="), IResult::Done(&b" 1"[..], &b"<<="[..]));
+
+            let source = b"register registers;";
+
+            span::with_source(FileId::default(), source, || {
+                assert!(keyword(&source[..], "register").is_done());
+                assert!(keyword(&source[9..], "register").is_err());
+                assert_eq!(punctuator_char(&source[18..], ';'), IResult::Done(&b""[..], &b";"[..]));
+            });
+        }
+    }
+}
@@ -0,0 +1,604 @@
+use super::declaration::{Declaration, StaticAssert};
+use super::function::FunctionDefinition;
+use super::error::{Diagnostic, Diagnostics};
+use super::span::{self, FileId, Spanned};
+use super::comments::Comment;
+use super::keywords::{self, Standard};
+use super::scope;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternalDeclaration {
+    FunctionDefinition(Spanned<FunctionDefinition>),
+    Declaration(Spanned<Declaration>),
+    StaticAssert(Spanned<StaticAssert>),
+}
+
+/// The contents of an entire source file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TranslationUnit {
+    pub items: Vec<ExternalDeclaration>,
+    /// Every comment in the file, which are only kept when parsing with `ParseOptions::keep_comments`
+    pub comments: Vec<Spanned<Comment>>,
+}
+
+impl TranslationUnit {
+    /// Every function defined in the file, in order of definition
+    pub fn functions(&self) -> Vec<&Spanned<FunctionDefinition>> {
+        self.items.iter().filter_map(|item| match *item {
+            ExternalDeclaration::FunctionDefinition(ref f) => Some(f),
+            _ => None,
+        }).collect()
+    }
+}
+
+/// Options controlling how a file is parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ParseOptions {
+    /// Keep comments in `TranslationUnit::comments`, and attach documentation comments
+    /// to the declarations, struct fields, enumerators and functions they describe
+    pub keep_comments: bool,
+    /// The revision of C to parse, which decides the words that cannot be used as identifiers.
+    /// Defaults to C23.
+    pub standard: Standard,
+}
+
+/// Parses an entire `.c` or `.h` file
+pub fn parse_translation_unit(source: &[u8]) -> Result<TranslationUnit, Diagnostics> {
+    parse_file(FileId::default(), source)
+}
+
+/// Parses an entire file, attributing the spans of all nodes to `file`
+///
+/// Fails if any external declaration could not be parsed. Use `parse_partial`
+/// to also obtain the declarations that could be.
+pub fn parse_file(file: FileId, source: &[u8]) -> Result<TranslationUnit, Diagnostics> {
+    let (unit, diagnostics) = parse_partial(file, source);
+
+    if diagnostics.is_empty() { Ok(unit) } else { Err(diagnostics) }
+}
+
+/// Parses as much of a file as possible, recovering from errors in individual external declarations
+///
+/// When an external declaration fails to parse, its error is recorded and the input is skipped
+/// up to and including the next `;` or balanced `}`, after which parsing resumes.
+/// The returned unit contains every declaration that parsed successfully.
+pub fn parse_partial(file: FileId, source: &[u8]) -> (TranslationUnit, Diagnostics) {
+    parse_with_options(file, source, ParseOptions::default())
+}
+
+/// Like `parse_partial`, but configured by `options`
+pub fn parse_with_options(file: FileId, source: &[u8], options: ParseOptions) -> (TranslationUnit, Diagnostics) {
+    // Typedef names must not leak between files, nor out of this parse
+    scope::isolated(|| keywords::with_standard(options.standard, || {
+        if options.keep_comments {
+            span::with_source_comments(file, source, || {
+                let (mut unit, diagnostics) = parse_items(source);
+
+                unit.comments = span::comments();
+
+                (unit, diagnostics)
+            })
+        } else {
+            span::with_source(file, source, || parse_items(source))
+        }
+    }))
+}
+
+fn parse_items(source: &[u8]) -> (TranslationUnit, Diagnostics) {
+    use nom::IResult;
+
+    use super::error::ParseError;
+    use super::whitespace::parsing::sp_parse_error;
+
+    fn skip_whitespace(input: &[u8]) -> &[u8] {
+        match sp_parse_error(input) {
+            IResult::Done(rest, _) => rest,
+            _ => input,
+        }
+    }
+
+    let mut unit = TranslationUnit::default();
+    let mut diagnostics = Diagnostics::new();
+
+    let mut input = skip_whitespace(source);
+
+    while !input.is_empty() {
+        match self::parsing::external_declaration(input) {
+            IResult::Done(rest, item) => {
+                unit.items.push(item);
+                input = rest;
+            },
+            IResult::Error(err) => {
+                diagnostics.push(Diagnostic::from_nom(source, &err));
+
+                input = skip_whitespace(recover(input));
+            },
+            IResult::Incomplete(_) => {
+                diagnostics.push(Diagnostic::new(source.len(), ParseError::UnexpectedEndOfInput));
+                break;
+            }
+        }
+    }
+
+    (unit, diagnostics)
+}
+
+/// Skips past the end of a malformed external declaration, which is the first `;` outside of
+/// any braces, or the `}` closing a top-level block, as in a function body or a stray `{ ... }`.
+///
+/// Braces of an initializer, after `=`, and of a struct, union or enum, after a name or keyword,
+/// belong to a declaration that continues after them, so those run on to the next `;`.
+///
+/// String and character literals and comments are skipped over, so brackets within them are ignored.
+/// Always consumes at least one byte of non-empty input.
+fn recover(input: &[u8]) -> &[u8] {
+    use super::comments::{comment_len, word_len};
+
+    let mut braces = 0usize;
+    let mut block = false;
+    let mut initializer = false;
+    let mut i = 0;
+
+    while i < input.len() {
+        // Identifiers and numbers are skipped whole, so the digit separators in `1'000` are not quotes
+        if let Some(len) = word_len(&input[i..]) {
+            i += len;
+            continue;
+        }
+
+        match input[i] {
+            quote @ b'"' | quote @ b'\'' => {
+                i += 1;
+
+                while i < input.len() && input[i] != quote && input[i] != b'\n' {
+                    if input[i] == b'\\' {
+                        i += 1;
+                    }
+
+                    i += 1;
+                }
+            },
+            b'/' => if let Some(len) = comment_len(&input[i..]) {
+                i += len;
+                continue;
+            },
+            b'=' if braces == 0 => initializer = true,
+            b'{' => {
+                if braces == 0 {
+                    let previous = input[..i].iter().rev().find(|c| !c.is_ascii_whitespace());
+
+                    block = !initializer && !previous.map_or(false, |&c| c == b'_' || c.is_ascii_alphanumeric());
+                }
+
+                braces += 1;
+            },
+            b'}' => {
+                braces = braces.saturating_sub(1);
+
+                if braces == 0 && block {
+                    return &input[i + 1..];
+                }
+            },
+            b';' if braces == 0 => return &input[i + 1..],
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    &input[input.len()..]
+}
+
+pub mod parsing {
+    use nom::*;
+    use ::wse;
+    use super::*;
+
+    use ::parser::error::ParseError;
+    use ::parser::declaration::parsing::{declaration, declaration_specifiers, static_assert_declaration};
+    use ::parser::function::parsing::function_definition;
+
+    named!(external_declaration_raw<&[u8], ExternalDeclaration, ParseError>, add_return_error!(
+        ParseError::InvalidExternalDeclaration.into_nom(),
+        wse!(alt_complete!(
+            static_assert_declaration   => { |s| ExternalDeclaration::StaticAssert(s) } |
+            function_definition         => { |f| ExternalDeclaration::FunctionDefinition(f) } |
+            declaration                 => { |d| ExternalDeclaration::Declaration(d) }
+        ))
+    ));
+
+    /// Matches a function definition or a declaration
+    pub fn external_declaration(input: &[u8]) -> IResult<&[u8], ExternalDeclaration, ParseError> {
+        let res = external_declaration_raw(input);
+
+        // Both alternatives fail on an invalid combination of type specifiers, which would otherwise
+        // be hidden behind the failed alternative, so it is reported directly
+        if res.is_err() {
+            if let IResult::Error(err) = declaration_specifiers(input) {
+                if let Err::Position(ErrorKind::Custom(ParseError::InvalidTypeSpecifiers(_)), _) = err {
+                    return IResult::Error(error_node_position!(ParseError::InvalidExternalDeclaration.into_nom(), input, err));
+                }
+            }
+        }
+
+        res
+    }
+
+    named!(
+        #[doc = "Matches any number of external declarations"],
+        pub translation_unit<&[u8], TranslationUnit, ParseError>,
+        map!(many0!(external_declaration), |items| TranslationUnit { items: items, comments: Vec::new() })
+    );
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        use ::parser::error::Diagnostic;
+
+        #[test]
+        fn test_parse_translation_unit() {
+            let source = b"
+                typedef unsigned long size_t;
+
+                static int counter;
+
+                size_t length(const char *s) {
+                    size_t n = 0;
+
+                    while (*s++) {
+                        n++;
+                    }
+
+                    return n;
+                }
+            ";
+
+            let unit = parse_translation_unit(source).unwrap();
+
+            assert_eq!(unit.items.len(), 3);
+
+            match unit.items[2] {
+                ExternalDeclaration::FunctionDefinition(ref f) => {
+                    assert_eq!(f.declarator.ident(), Some(&::parser::ident::Ident::from("length")));
+                },
+                ref other => panic!("Unexpected item {:?}", other)
+            }
+        }
+
+        #[test]
+        fn test_typedef_scopes() {
+            use ::parser::statement::{Statement, BlockItem};
+
+            let source = b"
+                typedef int T;
+
+                void f(void) {
+                    T * a;
+                    {
+                        int T;
+                        T * a;
+                    }
+                    T * b;
+                    for (int T = 0;;) T * c;
+                }
+
+                void g(int T) { T * d; }
+            ";
+
+            fn body(item: &ExternalDeclaration) -> &Vec<BlockItem> {
+                match *item {
+                    ExternalDeclaration::FunctionDefinition(ref f) => match f.body.node {
+                        Statement::Compound(ref items) => items,
+                        ref other => panic!("Unexpected body {:?}", other)
+                    },
+                    ref other => panic!("Unexpected item {:?}", other)
+                }
+            }
+
+            fn is_declaration(item: &BlockItem) -> bool {
+                match *item {
+                    BlockItem::Declaration(_) => true,
+                    _ => false,
+                }
+            }
+
+            let unit = parse_translation_unit(source).unwrap();
+
+            let f = body(&unit.items[1]);
+
+            assert!(is_declaration(&f[0]));
+            assert!(is_declaration(&f[2]));
+
+            match f[1] {
+                BlockItem::Statement(Spanned { node: Statement::Compound(ref items), .. }) => {
+                    assert!(is_declaration(&items[0]));
+                    assert!(!is_declaration(&items[1]));
+                },
+                ref other => panic!("Unexpected item {:?}", other)
+            }
+
+            match f[3] {
+                BlockItem::Statement(Spanned { node: Statement::For { ref body, .. }, .. }) => {
+                    assert!(match body.node { Statement::Expression(Some(_)) => true, _ => false });
+                },
+                ref other => panic!("Unexpected item {:?}", other)
+            }
+
+            assert!(!is_declaration(&body(&unit.items[2])[0]));
+
+            // Typedef names do not outlive the parse that declared them
+            assert!(!scope::is_typedef(&::parser::ident::Ident::from("T")));
+        }
+
+        #[test]
+        fn test_parse_file_spans() {
+            let source = b"int x;\n\nstatic void f(void)\n{\n}\n";
+
+            let unit = parse_file(FileId(7), source).unwrap();
+
+            match unit.items[1] {
+                ExternalDeclaration::FunctionDefinition(ref f) => {
+                    assert_eq!(f.span.file, FileId(7));
+                    assert_eq!((f.span.line, f.span.column), (3, 1));
+                    assert_eq!(&source[f.span.start..f.span.end], b"static void f(void)\n{\n}");
+
+                    assert_eq!((f.body.span.line, f.body.span.column), (4, 1));
+
+                    let ident = f.declarator.ident().unwrap();
+
+                    assert_eq!((ident.span().line, ident.span().column, ident.span().len()), (3, 13, 1));
+                },
+                ref other => panic!("Unexpected item {:?}", other)
+            }
+        }
+
+        #[test]
+        fn test_parse_comments() {
+            let source = b"/* header */\nint /* a * b */ a; // trailing \\\n continued\nchar *s = \"/* not a comment */\";\n// end";
+
+            let unit = parse_translation_unit(source).unwrap();
+
+            assert_eq!(unit.items.len(), 2);
+
+            match unit.items[0] {
+                ExternalDeclaration::Declaration(ref d) => {
+                    assert_eq!(&source[d.span.start..d.span.end], &b"int /* a * b */ a;"[..]);
+                },
+                ref other => panic!("Unexpected item {:?}", other)
+            }
+        }
+
+        #[test]
+        fn test_doc_comments() {
+            use ::parser::comments::documentation;
+            use ::parser::structure::Member;
+            use ::parser::declaration::DeclarationSpecifier;
+            use ::parser::types::TypeSpecifier;
+
+            let source = b"
+                // Not documentation
+                /**
+                 * A point
+                 */
+                struct point {
+                    /// Horizontal
+                    int x;
+                    int y; ///< Vertical
+                };
+
+                enum color { RED, /**< Red */ GREEN };
+
+                //! Adds two numbers
+                int add(int a, int b) { return a + b; }
+            ";
+
+            let (unit, diagnostics) = parse_with_options(FileId(1), source, ParseOptions { keep_comments: true, ..ParseOptions::default() });
+
+            assert!(diagnostics.is_empty());
+            assert_eq!(unit.comments.len(), 6);
+            assert_eq!(unit.comments[1].span.line, 3);
+
+            let declaration = |item: &ExternalDeclaration| match *item {
+                ExternalDeclaration::Declaration(ref d) => d.node.clone(),
+                ref other => panic!("Unexpected item {:?}", other)
+            };
+
+            let point = declaration(&unit.items[0]);
+
+            assert_eq!(documentation(&point.doc), "A point");
+
+            match point.specifiers[0].node {
+                DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Struct(ref variant)) => {
+                    let docs: Vec<String> = variant.data().members.as_ref().unwrap().iter().map(|member| match member.node {
+                        Member::Field(ref field) => documentation(&field.doc),
+                        ref other => panic!("Unexpected member {:?}", other)
+                    }).collect();
+
+                    assert_eq!(docs, vec!["Horizontal", "Vertical"]);
+                },
+                ref other => panic!("Unexpected specifier {:?}", other)
+            }
+
+            match declaration(&unit.items[1]).specifiers[0].node {
+                DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Enum(ref e)) => {
+                    let enumerators = e.enumerators.as_ref().unwrap();
+
+                    assert_eq!(documentation(&enumerators[0].doc), "Red");
+                    assert!(enumerators[1].doc.is_empty());
+                },
+                ref other => panic!("Unexpected specifier {:?}", other)
+            }
+
+            match unit.items[2] {
+                ExternalDeclaration::FunctionDefinition(ref f) => assert_eq!(documentation(&f.doc), "Adds two numbers"),
+                ref other => panic!("Unexpected item {:?}", other)
+            }
+
+            // Comments are only kept when asked for
+            let unit = parse_translation_unit(source).unwrap();
+
+            assert!(unit.comments.is_empty());
+            assert!(declaration(&unit.items[0]).doc.is_empty());
+        }
+
+        #[test]
+        fn test_standards() {
+            let parse = |source: &[u8], standard| {
+                parse_with_options(FileId::default(), source, ParseOptions { standard: standard, ..ParseOptions::default() }).1.is_empty()
+            };
+
+            assert!(parse(b"int inline = 1;", Standard::C89));
+            assert!(!parse(b"int inline = 1;", Standard::C99));
+            assert!(parse(b"int bool = true;", Standard::C17));
+            assert!(!parse(b"int bool = true;", Standard::C23));
+            assert!(parse(b"bool b = true; void *p = nullptr;", Standard::C23));
+            assert!(!parse(b"bool b;", Standard::C11));
+        }
+
+        #[test]
+        fn test_parse_translation_unit_error() {
+            let source = b"int a;\nint b = ;\n";
+
+            let diagnostics = parse_translation_unit(source).unwrap_err();
+
+            assert_eq!(diagnostics.len(), 1);
+
+            let Diagnostic { offset, ref errors } = diagnostics.0[0];
+
+            assert!(offset >= 7);
+            assert_eq!(errors.first(), Some(&ParseError::InvalidExternalDeclaration));
+
+            assert!(diagnostics.render("example.c", source).contains(" --> example.c:2:"));
+        }
+
+        #[test]
+        fn test_static_asserts() {
+            use ::parser::statement::{Statement, BlockItem};
+            use ::parser::structure::Member;
+            use ::parser::types::TypeSpecifier;
+            use ::parser::declaration::DeclarationSpecifier;
+
+            let unit = parse_translation_unit(b"
+                _Static_assert(sizeof(int) >= 4, \"int is too small\");
+                static_assert(1);
+
+                struct S { int a; static_assert(sizeof(int), \"member\"); };
+
+                void f(void) { _Static_assert(1, \"block\"); static_assert(2); }
+            ").unwrap();
+
+            let messages: Vec<Option<String>> = unit.items[..2].iter().map(|item| match *item {
+                ExternalDeclaration::StaticAssert(ref s) => s.message.as_ref().map(|m| match m.node {
+                    ::parser::lit::Lit::Str { ref value, .. } => value.clone(),
+                    ref other => panic!("Unexpected message {:?}", other)
+                }),
+                ref other => panic!("Unexpected item {:?}", other)
+            }).collect();
+
+            assert_eq!(messages, vec![Some("int is too small".to_owned()), None]);
+
+            match unit.items[2] {
+                ExternalDeclaration::Declaration(ref d) => match d.specifiers[0].node {
+                    DeclarationSpecifier::TypeSpecifier(TypeSpecifier::Struct(ref variant)) => {
+                        let members = variant.data().members.as_ref().unwrap();
+
+                        match members[1].node {
+                            Member::StaticAssert(ref s) => assert!(s.message.is_some()),
+                            ref other => panic!("Unexpected member {:?}", other)
+                        }
+                    },
+                    ref other => panic!("Unexpected specifier {:?}", other)
+                },
+                ref other => panic!("Unexpected item {:?}", other)
+            }
+
+            match unit.functions()[0].body.node {
+                Statement::Compound(ref items) => {
+                    assert_eq!(items.len(), 2);
+                    assert!(items.iter().all(|item| match *item {
+                        BlockItem::StaticAssert(_) => true,
+                        _ => false,
+                    }));
+                },
+                ref other => panic!("Unexpected body {:?}", other)
+            }
+
+            assert!(parse_translation_unit(b"_Static_assert(1, 2);").is_err());
+            assert!(parse_translation_unit(b"static_assert(1)").is_err());
+
+            let c11 = |source: &[u8]| {
+                parse_with_options(FileId::default(), source, ParseOptions { standard: Standard::C11, ..ParseOptions::default() }).1.is_empty()
+            };
+
+            assert!(c11(b"_Static_assert(1, \"message\");"));
+            assert!(!c11(b"_Static_assert(1);"));
+            assert!(!c11(b"void f(void) { _Static_assert(1); }"));
+        }
+
+        #[test]
+        fn test_invalid_type_specifiers() {
+            let source = b"int a;\nshort double b;\n";
+
+            let diagnostics = parse_translation_unit(source).unwrap_err();
+
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics.0[0].error(), Some(&ParseError::InvalidTypeSpecifiers("short double".to_owned())));
+            assert!(diagnostics.render("example.c", source).contains("error: invalid combination of type specifiers `short double`"));
+        }
+
+        #[test]
+        fn test_parse_partial() {
+            let source = b"
+                int a;
+                int b = ;
+                struct S { int x; char *s = \"}\"; } s;
+                int f(void) { if (1 { return 0; } }
+                typedef int T;
+                T c;
+                int d = (1;
+                int e
+            ";
+
+            let (unit, diagnostics) = parse_partial(FileId::default(), source);
+
+            let names: Vec<_> = unit.items.iter().map(|item| match *item {
+                ExternalDeclaration::Declaration(ref d) => d.declarators[0].declarator.ident().unwrap().to_string(),
+                ExternalDeclaration::FunctionDefinition(ref f) => f.declarator.ident().unwrap().to_string(),
+                ref other => panic!("Unexpected item {:?}", other)
+            }).collect();
+
+            assert_eq!(names, vec!["a", "T", "c"]);
+            assert_eq!(diagnostics.len(), 5);
+
+            let lines: Vec<_> = diagnostics.iter().map(|d| source[..d.offset].iter().filter(|&&c| c == b'\n').count() + 1).collect();
+
+            assert_eq!(lines, vec![3, 4, 5, 8, 9]);
+        }
+
+        #[test]
+        fn test_recover_blocks() {
+            let names = |source: &[u8]| {
+                let (unit, diagnostics) = parse_partial(FileId::default(), source);
+
+                let names: Vec<_> = unit.items.iter().filter_map(|item| match *item {
+                    ExternalDeclaration::Declaration(ref d) => Some(d.declarators[0].declarator.ident().unwrap().to_string()),
+                    _ => None,
+                }).collect();
+
+                (names, diagnostics.len())
+            };
+
+            // Stray blocks and K&R style bodies end at their closing brace
+            assert_eq!(names(b"{ x; } int y;"), (vec!["y".to_owned()], 1));
+            assert_eq!(names(b"f(a) int a; { return a } int y;"), (vec!["y".to_owned()], 2));
+
+            // Initializers and struct bodies run on to the end of their declaration
+            assert_eq!(names(b"int a[] = { 1, } } int y; int z;"), (vec!["z".to_owned()], 1));
+            assert_eq!(names(b"struct { int x; ) } s; int y;"), (vec!["y".to_owned()], 1));
+
+            // Digit separators and comments do not hide the end of a declaration
+            assert_eq!(names(b"int x = 1'000 +; int y; /* ; */ int z;"), (vec!["y".to_owned(), "z".to_owned()], 1));
+        }
+    }
+}
@@ -1,4 +1,13 @@
-#[derive(Debug, Clone, PartialEq, Hash)]
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::declaration::{TypeName, DeclarationSpecifier};
+use super::ident::Ident;
+use super::structure::Variant;
+use super::enumeration::Enum;
+use super::error::ParseError;
+use super::span::Spanned;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum TypeSpecifier {
     Void,
     Char,
@@ -11,10 +20,40 @@ pub enum TypeSpecifier {
     Unsigned,
     Bool,
     Complex,
-    Atomic(Box<TypeSpecifier>),
-    Struct,
-    Enum,
-    Other(Box<TypeSpecifier>)
+    /// `_Atomic(type-name)`
+    Atomic(Box<Spanned<TypeName>>),
+    /// `struct` and `union` specifiers
+    Struct(Variant),
+    Enum(Enum),
+    /// A name previously declared with `typedef`
+    TypedefName(Ident),
+}
+
+impl Display for TypeSpecifier {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let keyword = match *self {
+            TypeSpecifier::Void => "void",
+            TypeSpecifier::Char => "char",
+            TypeSpecifier::Short => "short",
+            TypeSpecifier::Int => "int",
+            TypeSpecifier::Long => "long",
+            TypeSpecifier::Float => "float",
+            TypeSpecifier::Double => "double",
+            TypeSpecifier::Signed => "signed",
+            TypeSpecifier::Unsigned => "unsigned",
+            TypeSpecifier::Bool => "_Bool",
+            TypeSpecifier::Complex => "_Complex",
+            TypeSpecifier::Atomic(_) => "_Atomic(...)",
+            TypeSpecifier::Struct(ref variant) => return variant.fmt(f),
+            TypeSpecifier::Enum(ref e) => return match e.ident {
+                Some(ref ident) => write!(f, "enum {}", ident),
+                None => f.write_str("enum <anonymous>"),
+            },
+            TypeSpecifier::TypedefName(ref ident) => return ident.fmt(f),
+        };
+
+        f.write_str(keyword)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Hash)]
@@ -25,14 +64,256 @@ pub enum TypeQualifier {
     Atomic,
 }
 
+/// The set of qualifiers applied to a type, where repeating a qualifier has no further effect
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Qualifiers {
+    pub is_const: bool,
+    pub is_restrict: bool,
+    pub is_volatile: bool,
+    pub is_atomic: bool,
+}
+
+impl Qualifiers {
+    pub fn add(&mut self, qualifier: TypeQualifier) {
+        match qualifier {
+            TypeQualifier::Const => self.is_const = true,
+            TypeQualifier::Restrict => self.is_restrict = true,
+            TypeQualifier::Volatile => self.is_volatile = true,
+            TypeQualifier::Atomic => self.is_atomic = true,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Qualifiers::default()
+    }
+}
+
+/// The type named by a valid combination of type specifiers, independent of their order and spelling
+///
+/// So `long unsigned int long`, `unsigned long long` and `long long unsigned` are all `UnsignedLongLong`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Void,
+    Bool,
+    /// Plain `char`, which is distinct from both `signed char` and `unsigned char`
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    Float,
+    Double,
+    LongDouble,
+    FloatComplex,
+    DoubleComplex,
+    LongDoubleComplex,
+    Atomic(Box<Spanned<TypeName>>),
+    Struct(Variant),
+    Enum(Enum),
+    TypedefName(Ident),
+}
+
+// How many times each builtin type specifier occurs
+#[derive(Default)]
+struct SpecifierCounts {
+    void: u32,
+    char: u32,
+    short: u32,
+    int: u32,
+    long: u32,
+    float: u32,
+    double: u32,
+    signed: u32,
+    unsigned: u32,
+    bool: u32,
+    complex: u32,
+}
+
+impl Type {
+    /// Builds the type named by a list of type specifiers, given in any order
+    ///
+    /// Returns `ParseError::InvalidTypeSpecifiers` for combinations naming no type, such as `short double`,
+    /// or `ParseError::InvalidTypeSpecifier` if there are no type specifiers at all.
+    pub fn from_specifiers<'a, I>(specifiers: I) -> Result<Type, ParseError> where I: IntoIterator<Item = &'a TypeSpecifier> {
+        let specifiers: Vec<&TypeSpecifier> = specifiers.into_iter().collect();
+
+        let invalid = || {
+            let spelling: Vec<String> = specifiers.iter().map(|s| s.to_string()).collect();
+
+            ParseError::InvalidTypeSpecifiers(spelling.join(" "))
+        };
+
+        let mut n = SpecifierCounts::default();
+
+        for specifier in &specifiers {
+            let count = match **specifier {
+                TypeSpecifier::Void => &mut n.void,
+                TypeSpecifier::Char => &mut n.char,
+                TypeSpecifier::Short => &mut n.short,
+                TypeSpecifier::Int => &mut n.int,
+                TypeSpecifier::Long => &mut n.long,
+                TypeSpecifier::Float => &mut n.float,
+                TypeSpecifier::Double => &mut n.double,
+                TypeSpecifier::Signed => &mut n.signed,
+                TypeSpecifier::Unsigned => &mut n.unsigned,
+                TypeSpecifier::Bool => &mut n.bool,
+                TypeSpecifier::Complex => &mut n.complex,
+                // These name a type on their own, so cannot be combined with any other type specifier
+                _ if specifiers.len() > 1 => return Err(invalid()),
+                TypeSpecifier::Atomic(ref ty) => return Ok(Type::Atomic(ty.clone())),
+                TypeSpecifier::Struct(ref variant) => return Ok(Type::Struct(variant.clone())),
+                TypeSpecifier::Enum(ref e) => return Ok(Type::Enum(e.clone())),
+                TypeSpecifier::TypedefName(ref ident) => return Ok(Type::TypedefName(ident.clone())),
+            };
+
+            *count += 1;
+        }
+
+        if specifiers.is_empty() {
+            return Err(ParseError::InvalidTypeSpecifier);
+        }
+
+        // `long` is the only specifier that may be repeated, and only once
+        let repeated = n.void > 1 || n.char > 1 || n.short > 1 || n.int > 1 || n.long > 2 || n.float > 1 ||
+            n.double > 1 || n.signed > 1 || n.unsigned > 1 || n.bool > 1 || n.complex > 1;
+
+        if repeated || (n.signed > 0 && n.unsigned > 0) {
+            return Err(invalid());
+        }
+
+        let sign = n.signed + n.unsigned;
+        let complex = n.complex > 0;
+
+        let integer = |signed: Type, unsigned: Type| {
+            if complex { None } else if n.unsigned > 0 { Some(unsigned) } else { Some(signed) }
+        };
+
+        let ty = match (n.void, n.bool, n.char, n.short, n.long, n.float, n.double) {
+            (1, 0, 0, 0, 0, 0, 0) if n.int == 0 && sign == 0 && !complex => Some(Type::Void),
+            (0, 1, 0, 0, 0, 0, 0) if n.int == 0 && sign == 0 && !complex => Some(Type::Bool),
+            (0, 0, 1, 0, 0, 0, 0) if n.int == 0 && !complex => Some(match (n.signed, n.unsigned) {
+                (0, 0) => Type::Char,
+                (_, 0) => Type::SignedChar,
+                _ => Type::UnsignedChar,
+            }),
+            (0, 0, 0, 0, 0, 0, 0) if n.int + sign > 0 => integer(Type::Int, Type::UnsignedInt),
+            (0, 0, 0, 1, 0, 0, 0) => integer(Type::Short, Type::UnsignedShort),
+            (0, 0, 0, 0, 1, 0, 0) => integer(Type::Long, Type::UnsignedLong),
+            (0, 0, 0, 0, 2, 0, 0) => integer(Type::LongLong, Type::UnsignedLongLong),
+            (0, 0, 0, 0, 0, 1, 0) if n.int == 0 && sign == 0 => {
+                Some(if complex { Type::FloatComplex } else { Type::Float })
+            },
+            (0, 0, 0, 0, 0, 0, 1) if n.int == 0 && sign == 0 => {
+                Some(if complex { Type::DoubleComplex } else { Type::Double })
+            },
+            (0, 0, 0, 0, 1, 0, 1) if n.int == 0 && sign == 0 => {
+                Some(if complex { Type::LongDoubleComplex } else { Type::LongDouble })
+            },
+            _ => None,
+        };
+
+        ty.ok_or_else(invalid)
+    }
+
+    /// Returns true for the signed and unsigned integer types, including `char` and `_Bool`
+    pub fn is_integer(&self) -> bool {
+        match *self {
+            Type::Bool | Type::Char | Type::SignedChar | Type::UnsignedChar |
+            Type::Short | Type::UnsignedShort | Type::Int | Type::UnsignedInt |
+            Type::Long | Type::UnsignedLong | Type::LongLong | Type::UnsignedLongLong => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true for the real and complex floating types
+    pub fn is_floating(&self) -> bool {
+        match *self {
+            Type::Float | Type::Double | Type::LongDouble |
+            Type::FloatComplex | Type::DoubleComplex | Type::LongDoubleComplex => true,
+            _ => false,
+        }
+    }
+}
+
+/// A canonical type along with its qualifiers
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualifiedType {
+    pub ty: Type,
+    pub qualifiers: Qualifiers,
+}
+
+impl QualifiedType {
+    /// Builds the type named by the type specifiers and qualifiers among `specifiers`,
+    /// ignoring storage class, function and alignment specifiers
+    pub fn from_specifiers(specifiers: &[Spanned<DeclarationSpecifier>]) -> Result<QualifiedType, ParseError> {
+        let mut qualifiers = Qualifiers::default();
+
+        for specifier in specifiers {
+            if let DeclarationSpecifier::TypeQualifier(qualifier) = specifier.node {
+                qualifiers.add(qualifier);
+            }
+        }
+
+        let ty = Type::from_specifiers(specifiers.iter().filter_map(|s| match s.node {
+            DeclarationSpecifier::TypeSpecifier(ref specifier) => Some(specifier),
+            _ => None,
+        }))?;
+
+        Ok(QualifiedType { ty: ty, qualifiers: qualifiers })
+    }
+}
+
 pub mod parsing {
     use nom::*;
+    use ::wse;
     use super::*;
 
-    named!(type_qualifier<TypeQualifier>, ws!(alt!(
-        tag!("const")       => {|_| TypeQualifier::Const   } |
-        tag!("restrict")    => {|_| TypeQualifier::Restrict} |
-        tag!("volatile")    => {|_| TypeQualifier::Volatile} |
-        tag!("_Atomic")     => {|_| TypeQualifier::Atomic  }
-    )));
-}
\ No newline at end of file
+    use ::parser::error::ParseError;
+    use ::parser::declaration::parsing::type_name;
+    use ::parser::structure::parsing::struct_or_union_specifier;
+    use ::parser::enumeration::parsing::enum_specifier;
+
+    named!(pub type_qualifier<&[u8], TypeQualifier, ParseError>, add_return_error!(
+        ParseError::InvalidTypeQualifier.into_nom(),
+        wse!(alt_complete!(
+            keyword!("const")       => {|_| TypeQualifier::Const   } |
+            keyword!("restrict")    => {|_| TypeQualifier::Restrict} |
+            keyword!("volatile")    => {|_| TypeQualifier::Volatile} |
+            terminated!(keyword!("_Atomic"), not!(punct!('('))) => {|_| TypeQualifier::Atomic  }
+        ))
+    ));
+
+    named!(
+        #[doc = "Matches a single builtin type specifier or `_Atomic(type-name)`"],
+        pub type_specifier<&[u8], TypeSpecifier, ParseError>,
+        add_return_error!(
+            ParseError::InvalidTypeSpecifier.into_nom(),
+            wse!(alt_complete!(
+                keyword!("void")        => {|_| TypeSpecifier::Void     } |
+                keyword!("char")        => {|_| TypeSpecifier::Char     } |
+                keyword!("short")       => {|_| TypeSpecifier::Short    } |
+                keyword!("int")         => {|_| TypeSpecifier::Int      } |
+                keyword!("long")        => {|_| TypeSpecifier::Long     } |
+                keyword!("float")       => {|_| TypeSpecifier::Float    } |
+                keyword!("double")      => {|_| TypeSpecifier::Double   } |
+                keyword!("signed")      => {|_| TypeSpecifier::Signed   } |
+                keyword!("unsigned")    => {|_| TypeSpecifier::Unsigned } |
+                keyword!("_Bool")       => {|_| TypeSpecifier::Bool     } |
+                keyword!("bool")        => {|_| TypeSpecifier::Bool     } |
+                keyword!("_Complex")    => {|_| TypeSpecifier::Complex  } |
+                preceded!(
+                    keyword!("_Atomic"),
+                    delimited!(punct!('('), type_name, punct!(')'))
+                )                       => {|ty| TypeSpecifier::Atomic(Box::new(ty)) } |
+                struct_or_union_specifier   => {|v| TypeSpecifier::Struct(v) } |
+                enum_specifier              => {|e| TypeSpecifier::Enum(e) }
+            ))
+        )
+    );
+}
@@ -1,6 +1,27 @@
 pub mod parsing {
     use nom::*;
+    use ::parser::comments::trivia_len;
     use ::parser::error::ParseError;
 
-    named!(pub sp_parse_error<&[u8], &[u8], ParseError>, fix_error!(ParseError, sp));
-}
\ No newline at end of file
+    /// Matches any amount of whitespace and comments, which separate tokens
+    ///
+    /// Used by `wse!` between every sub-parser, so it never fails.
+    pub fn trivia<E>(input: &[u8]) -> IResult<&[u8], &[u8], E> {
+        let len = trivia_len(input);
+
+        IResult::Done(&input[len..], &input[..len])
+    }
+
+    named!(pub sp_parse_error<&[u8], &[u8], ParseError>, call!(trivia));
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_trivia() {
+            assert_eq!(sp_parse_error(b" \t/* a */\n// b\n  c"), IResult::Done(&b"c"[..], &b" \t/* a */\n// b\n  "[..]));
+            assert_eq!(sp_parse_error(b"/ c"), IResult::Done(&b"/ c"[..], &b""[..]));
+        }
+    }
+}
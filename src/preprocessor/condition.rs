@@ -0,0 +1,205 @@
+//! Evaluation of `#if` and `#elif` conditions
+//!
+//! The operators `defined`, `__has_include` and `__has_attribute` are replaced before macro
+//! expansion. After expansion, any remaining identifiers are replaced by `0` and the line is
+//! evaluated as an integer constant expression, with all arithmetic in `intmax_t` or `uintmax_t`.
+
+use nom::IResult;
+
+use ::parser::constant::{self, Constant};
+use ::parser::expression::parsing::constant_expression;
+
+use super::error::ErrorKind;
+use super::token::{Token, TokenKind};
+
+/// Answers the operators that query the implementation
+pub struct Operators<'a> {
+    pub defined: &'a Fn(&str) -> bool,
+    /// Whether the header would be found, given its name and whether it was written `<name>`
+    pub has_include: &'a Fn(&str, bool) -> bool,
+    /// The version of a supported attribute, or zero
+    pub has_attribute: &'a Fn(&str) -> i64,
+}
+
+/// Names of the operators, which `#ifdef` considers to be defined
+pub const OPERATORS: &'static [&'static str] = &["defined", "__has_include", "__has_attribute", "__has_c_attribute"];
+
+fn number(value: i64, at: &Token) -> Token {
+    Token {
+        kind: TokenKind::Number,
+        text: value.to_string(),
+        ..at.clone()
+    }
+}
+
+/// Replaces every operator in a condition by its value
+pub fn replace_operators(tokens: &[Token], operators: &Operators) -> Result<Vec<Token>, ErrorKind> {
+    let mut output = Vec::with_capacity(tokens.len());
+
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+
+        if token.kind != TokenKind::Identifier {
+            output.push(token.clone());
+            i += 1;
+            continue;
+        }
+
+        let invalid = || ErrorKind::InvalidCondition(format!("invalid use of `{}`", token.text));
+
+        match &token.text[..] {
+            "defined" => {
+                // Either `defined NAME` or `defined ( NAME )`
+                let (name, len) = match (tokens.get(i + 1), tokens.get(i + 2), tokens.get(i + 3)) {
+                    (Some(open), Some(name), Some(close)) if open.is_punctuator("(") && close.is_punctuator(")") => (name, 4),
+                    (Some(name), _, _) if !name.is_punctuator("(") => (name, 2),
+                    _ => return Err(invalid()),
+                };
+
+                if name.kind != TokenKind::Identifier {
+                    return Err(invalid());
+                }
+
+                output.push(number((operators.defined)(&name.text) as i64, token));
+                i += len;
+            },
+            "__has_include" => {
+                let close = tokens[i..].iter().position(|token| token.is_punctuator(")")).ok_or_else(&invalid)? + i;
+
+                if !tokens.get(i + 1).map_or(false, |open| open.is_punctuator("(")) {
+                    return Err(invalid());
+                }
+
+                let (header, angled) = header_name(&tokens[i + 2..close]).ok_or_else(&invalid)?;
+
+                output.push(number((operators.has_include)(&header, angled) as i64, token));
+                i = close + 1;
+            },
+            "__has_attribute" | "__has_c_attribute" => {
+                // The attribute may be scoped, as in `gnu::packed`
+                let close = tokens[i..].iter().position(|token| token.is_punctuator(")")).ok_or_else(&invalid)? + i;
+
+                if !tokens.get(i + 1).map_or(false, |open| open.is_punctuator("(")) || close == i + 2 {
+                    return Err(invalid());
+                }
+
+                let name: String = tokens[i + 2..close].iter().map(|token| &token.text[..]).collect();
+
+                output.push(number((operators.has_attribute)(&name), token));
+                i = close + 1;
+            },
+            _ => {
+                output.push(token.clone());
+                i += 1;
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Reads a header name written as a string literal or between `<` and `>`,
+/// returning the name and whether it was in angle brackets
+pub fn header_name(tokens: &[Token]) -> Option<(String, bool)> {
+    match tokens.first() {
+        // Unterminated strings, as in `#include "name`, are lexed without their closing quote
+        Some(token) if token.kind == TokenKind::StringLiteral && tokens.len() == 1 && token.text.len() >= 2
+            && token.text.starts_with('"') && token.text.ends_with('"') => {
+            Some((token.text[1..token.text.len() - 1].to_owned(), false))
+        },
+        Some(token) if token.is_punctuator("<") && tokens.len() > 2 && tokens[tokens.len() - 1].is_punctuator(">") => {
+            let mut name = String::new();
+
+            for token in &tokens[1..tokens.len() - 1] {
+                if token.leading_space && !name.is_empty() {
+                    name.push(' ');
+                }
+
+                name.push_str(&token.text);
+            }
+
+            Some((name, true))
+        },
+        _ => None,
+    }
+}
+
+/// Evaluates a fully macro-expanded condition
+pub fn evaluate(tokens: &[Token]) -> Result<Constant, ErrorKind> {
+    let mut text = String::new();
+
+    for token in tokens {
+        text.push(' ');
+
+        match token.kind {
+            // `true` and `false` are keywords in C23, and any other identifier is zero
+            TokenKind::Identifier => text.push_str(if token.text == "true" { "1" } else { "0" }),
+            _ => text.push_str(&token.text),
+        }
+    }
+
+    let spelling = || tokens.iter().map(|token| &token.text[..]).collect::<Vec<_>>().join(" ");
+
+    match constant_expression(text.as_bytes()) {
+        IResult::Done(rest, ref expr) if rest.iter().all(|c| c.is_ascii_whitespace()) => {
+            constant::evaluate(expr, &|_| None).map_err(ErrorKind::Evaluation)
+        },
+        _ => Err(ErrorKind::InvalidCondition(spelling())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ::parser::constant::EvaluationError;
+    use ::preprocessor::token::tokenize;
+
+    fn eval(source: &str) -> Result<Constant, ErrorKind> {
+        let defined = |name: &str| name == "FOO";
+        let has_include = |name: &str, angled: bool| angled && name == "stdio.h";
+        let has_attribute = |name: &str| if name == "gnu::packed" { 1 } else { 0 };
+
+        let operators = Operators {
+            defined: &defined,
+            has_include: &has_include,
+            has_attribute: &has_attribute,
+        };
+
+        evaluate(&replace_operators(&tokenize(source.as_bytes()).unwrap(), &operators)?)
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(eval("defined FOO && defined(FOO) && !defined BAR"), Ok(Constant::Signed(1)));
+        assert_eq!(eval("__has_include(<stdio.h>) + __has_include(\"stdio.h\")"), Ok(Constant::Signed(1)));
+        assert_eq!(eval("__has_attribute(gnu::packed) && !__has_c_attribute(nodiscard)"), Ok(Constant::Signed(1)));
+        assert_eq!(eval("defined"), Err(ErrorKind::InvalidCondition("invalid use of `defined`".to_owned())));
+    }
+
+    #[test]
+    fn test_header_name() {
+        let header = |source: &str| header_name(&tokenize(source.as_bytes()).unwrap());
+
+        assert_eq!(header("\"dir/a.h\""), Some(("dir/a.h".to_owned(), false)));
+        assert_eq!(header("<sys/types.h>"), Some(("sys/types.h".to_owned(), true)));
+        assert_eq!(header("\""), None);
+        assert_eq!(header("\"abc"), None);
+        assert_eq!(header("L\"abc\""), None);
+    }
+
+    #[test]
+    fn test_intmax_semantics() {
+        assert_eq!(eval("UNDEFINED || false"), Ok(Constant::Signed(0)));
+        assert_eq!(eval("true"), Ok(Constant::Signed(1)));
+        assert_eq!(eval("-1 < 0u"), Ok(Constant::Signed(0)));
+        assert_eq!(eval("0x7FFFFFFFFFFFFFFF + 0 == 9223372036854775807L"), Ok(Constant::Signed(1)));
+        assert_eq!(eval("0xFFFFFFFFFFFFFFFF == -1"), Ok(Constant::Signed(1)));
+        assert_eq!(eval("65536 * 65536"), Ok(Constant::Signed(1 << 32)));
+        assert_eq!(eval("'A' == 65"), Ok(Constant::Signed(1)));
+        assert_eq!(eval("1 / 0"), Err(ErrorKind::Evaluation(EvaluationError::DivisionByZero)));
+        assert_eq!(eval("1 +"), Err(ErrorKind::InvalidCondition("1 +".to_owned())));
+    }
+}
@@ -0,0 +1,831 @@
+//! Preprocessing directives and the preprocessor driving them
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::condition::{self, Operators, OPERATORS};
+use super::error::{PreprocessorError, ErrorKind};
+use super::include::{self, FileProvider, DiskFiles, IncludePaths};
+use super::macros::{Macro, MacroTable, Expander};
+use super::token::{self, Token, TokenKind};
+
+use ::parser::keywords::Standard;
+
+/// Preprocesses source files into text that can be given to the parser
+///
+/// Macros persist between files, as they would for files included into one another.
+#[derive(Clone)]
+pub struct Preprocessor {
+    macros: MacroTable,
+    /// Expansion of `__DATE__`, such as `"Jan  1 1970"`
+    date: String,
+    /// Expansion of `__TIME__`, such as `"00:00:00"`
+    time: String,
+    has_include: Option<Rc<Fn(&str, bool) -> bool>>,
+    has_attribute: Option<Rc<Fn(&str) -> i64>>,
+    files: Rc<FileProvider>,
+    include_paths: IncludePaths,
+    /// Files containing `#pragma once`
+    once: HashSet<PathBuf>,
+    /// Guard macros of files wrapped entirely in an include guard
+    guards: HashMap<PathBuf, String>,
+}
+
+impl Debug for Preprocessor {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Preprocessor")
+            .field("macros", &self.macros)
+            .field("date", &self.date)
+            .field("time", &self.time)
+            .field("include_paths", &self.include_paths)
+            .field("once", &self.once)
+            .field("guards", &self.guards)
+            .finish()
+    }
+}
+
+/// Predefined macros that are not in the macro table, since their values depend on where they appear
+const BUILTINS: &'static [&'static str] = &["__LINE__", "__FILE__", "__DATE__", "__TIME__"];
+
+/// Maximum nesting of `#include`, which stops files that include themselves
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+impl Default for Preprocessor {
+    fn default() -> Preprocessor {
+        Preprocessor::new()
+    }
+}
+
+/// The line in the original source of a line of a file being preprocessed
+struct Location<'a> {
+    file: &'a str,
+    /// Where the file was read from, if it was
+    path: Option<&'a Path>,
+    /// Difference between the presumed line, as set by `#line`, and the actual line
+    line_delta: isize,
+    /// Number of files including this one
+    depth: usize,
+}
+
+/// An `#if`, `#ifdef` or `#ifndef` whose `#endif` has not been reached
+struct Conditional {
+    /// Whether lines of the current group are kept
+    active: bool,
+    /// Whether any group has been kept, or none can be since the enclosing group is skipped
+    taken: bool,
+    seen_else: bool,
+    /// The directive that opened the conditional
+    start: Token,
+}
+
+impl Preprocessor {
+    /// Creates a preprocessor with only the predefined macros of a hosted implementation of
+    /// the default standard, which is also the one the parser defaults to
+    pub fn new() -> Preprocessor {
+        Preprocessor::for_standard(Standard::default())
+    }
+
+    /// Creates a preprocessor with only the predefined macros of a hosted implementation of `standard`
+    pub fn for_standard(standard: Standard) -> Preprocessor {
+        let (date, time) = date_time(SystemTime::now());
+
+        let mut preprocessor = Preprocessor {
+            macros: MacroTable::new(),
+            date: date,
+            time: time,
+            has_include: None,
+            has_attribute: None,
+            files: Rc::new(DiskFiles),
+            include_paths: IncludePaths::new(),
+            once: HashSet::new(),
+            guards: HashMap::new(),
+        };
+
+        for definition in &["__STDC__ 1", "__STDC_HOSTED__ 1"] {
+            preprocessor.define(definition).unwrap();
+        }
+
+        preprocessor.set_standard(standard);
+
+        preprocessor
+    }
+
+    /// Sets the standard announced by `__STDC_VERSION__`, which C89 does not define
+    pub fn set_standard(&mut self, standard: Standard) {
+        let version = match standard {
+            Standard::C89 => None,
+            Standard::C99 => Some("199901L"),
+            Standard::C11 => Some("201112L"),
+            Standard::C17 => Some("201710L"),
+            Standard::C23 => Some("202311L"),
+        };
+
+        self.undefine("__STDC_VERSION__");
+
+        if let Some(version) = version {
+            self.define(&format!("__STDC_VERSION__ {}", version)).unwrap();
+        }
+    }
+
+    /// Overrides the expansions of `__DATE__` and `__TIME__`, given without quotes,
+    /// so the output does not depend on when it was produced
+    pub fn set_date_time(&mut self, date: &str, time: &str) {
+        self.date = format!("\"{}\"", date);
+        self.time = format!("\"{}\"", time);
+    }
+
+    /// Defines a macro as `#define` would, such as `"MAX(a, b) ((a) > (b) ? (a) : (b))"`
+    pub fn define(&mut self, definition: &str) -> Result<(), PreprocessorError> {
+        let tokens = token::tokenize(definition.as_bytes())?;
+
+        Macro::parse(&tokens)
+            .and_then(|m| self.macros.define(m))
+            .map_err(|kind| PreprocessorError::new(kind, 0, 1))
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.macros.undefine(name);
+    }
+
+    /// Returns true if `name` is a macro, including predefined ones, as `defined` would
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.macros.is_defined(name) || BUILTINS.contains(&name) || OPERATORS[1..].contains(&name)
+    }
+
+    /// Sets how `__has_include` finds headers, given the header name and whether it was written `<name>`
+    ///
+    /// Without this, headers are searched for as `#include` would.
+    pub fn set_has_include<F>(&mut self, has_include: F) where F: Fn(&str, bool) -> bool + 'static {
+        self.has_include = Some(Rc::new(has_include));
+    }
+
+    /// Sets the result of `__has_attribute` and `__has_c_attribute`, which is the version of
+    /// a supported attribute or zero. Scoped attributes are given as `vendor::name`.
+    ///
+    /// Without this, no attribute is supported.
+    pub fn set_has_attribute<F>(&mut self, has_attribute: F) where F: Fn(&str) -> i64 + 'static {
+        self.has_attribute = Some(Rc::new(has_attribute));
+    }
+
+    pub fn macros(&self) -> &MacroTable {
+        &self.macros
+    }
+
+    /// Sets where included files are read from, which is the disk by default
+    pub fn set_file_provider<F>(&mut self, files: F) where F: FileProvider + 'static {
+        self.files = Rc::new(files);
+    }
+
+    /// Adds a directory searched by `#include "name"` only, like `-iquote`
+    pub fn add_quote_include_path<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.include_paths.quote.push(dir.into());
+    }
+
+    /// Adds a directory searched by all includes, like `-I`
+    pub fn add_include_path<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.include_paths.user.push(dir.into());
+    }
+
+    /// Adds a directory searched by all includes after those given to `add_include_path`, like `-isystem`
+    pub fn add_system_include_path<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.include_paths.system.push(dir.into());
+    }
+
+    pub fn include_paths(&self) -> &IncludePaths {
+        &self.include_paths
+    }
+
+    /// The macro guarding a previously included file, if its entire contents are within `#ifndef`
+    pub fn include_guard<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        self.guards.get(&include::normalize(path.as_ref())).map(|guard| &guard[..])
+    }
+
+    /// Preprocesses an entire file named `file`, which is used for `__FILE__`
+    ///
+    /// Lines of the output correspond to lines of `source`, so the locations of
+    /// parsed nodes are also the locations of their tokens in the original source.
+    /// Included files are inserted on lines of their own, so any lines after an
+    /// `#include` are shifted by the lines of the included file.
+    ///
+    /// Includes with quotes are first searched for relative to the working directory.
+    pub fn preprocess(&mut self, file: &str, source: &[u8]) -> Result<String, PreprocessorError> {
+        let mut output = Output::new();
+
+        let location = Location { file: file, path: None, line_delta: 0, depth: 0 };
+
+        self.process(location, source, &mut output).map_err(|err| err.in_file(file))?;
+
+        Ok(output.finish())
+    }
+
+    /// Reads and preprocesses the file at `path` using the file provider
+    pub fn preprocess_file<P: AsRef<Path>>(&mut self, path: P) -> Result<String, PreprocessorError> {
+        let path = include::normalize(path.as_ref());
+        let name = path.to_string_lossy().into_owned();
+
+        let source = match self.files.read(&path) {
+            Some(source) => source,
+            None => return Err(PreprocessorError::new(ErrorKind::IncludeNotFound(name.clone()), 0, 1).in_file(&name)),
+        };
+
+        let mut output = Output::new();
+
+        let location = Location { file: &name, path: Some(&path), line_delta: 0, depth: 0 };
+
+        self.process(location, &source, &mut output).map_err(|err| err.in_file(&name))?;
+
+        Ok(output.finish())
+    }
+
+    fn process(&mut self, mut location: Location, source: &[u8], output: &mut Output) -> Result<(), PreprocessorError> {
+        let tokens = token::tokenize(source)?;
+
+        if let Some(path) = location.path {
+            if let Some(guard) = include::include_guard(&tokens) {
+                self.guards.insert(path.to_owned(), guard);
+            }
+        }
+
+        let mut conditionals = Vec::new();
+
+        // Text lines between directives, which are expanded together since invocations may span lines
+        let mut text = Vec::new();
+
+        let mut i = 0;
+
+        while i < tokens.len() {
+            let end = tokens[i + 1..].iter().position(|token| token.line_start).map_or(tokens.len(), |end| i + 1 + end);
+
+            let line = &tokens[i..end];
+
+            i = end;
+
+            if !(line[0].line_start && is_directive_start(&line[0])) {
+                if is_active(&conditionals) {
+                    text.extend_from_slice(line);
+                }
+
+                continue;
+            }
+
+            let expanded = self.expand(&location, ::std::mem::replace(&mut text, Vec::new()))?;
+
+            output.write(&expanded);
+
+            self.directive(&mut location, &mut conditionals, line, output)?;
+        }
+
+        if let Some(conditional) = conditionals.first() {
+            return Err(PreprocessorError::new(ErrorKind::UnterminatedConditional, conditional.start.offset, conditional.start.line));
+        }
+
+        let expanded = self.expand(&location, text)?;
+
+        output.write(&expanded);
+
+        Ok(())
+    }
+
+    /// Finds an included file, given its name and whether it was written `<name>`
+    fn resolve(&self, location: &Location, name: &str, angled: bool) -> Option<PathBuf> {
+        // Files without a path are treated as being in the working directory
+        let dir = match location.path {
+            Some(path) => path.parent(),
+            None => Some(Path::new("")),
+        };
+
+        self.include_paths.resolve(&*self.files, name, angled, dir)
+    }
+
+    /// Executes `#include`, where `args` follow the directive name
+    fn include(&mut self, location: &Location, args: &[Token], output: &mut Output) -> Result<(), ErrorKind> {
+        let header = match condition::header_name(args) {
+            Some(header) => header,
+            // Otherwise the directive is macro-expanded first
+            None => {
+                let expanded = self.expand(location, args.to_vec()).map_err(|err| err.kind)?;
+
+                condition::header_name(&expanded).ok_or_else(|| ErrorKind::InvalidInclude(spell(args.iter())))?
+            },
+        };
+
+        let (name, angled) = header;
+
+        let path = self.resolve(location, &name, angled).ok_or_else(|| ErrorKind::IncludeNotFound(name.clone()))?;
+
+        if self.once.contains(&path) {
+            return Ok(());
+        }
+
+        if let Some(guard) = self.guards.get(&path) {
+            if self.macros.is_defined(guard) {
+                return Ok(());
+            }
+        }
+
+        if location.depth >= MAX_INCLUDE_DEPTH {
+            return Err(ErrorKind::IncludeTooDeep(name));
+        }
+
+        let source = self.files.read(&path).ok_or_else(|| ErrorKind::IncludeNotFound(name.clone()))?;
+
+        let file = path.to_string_lossy().into_owned();
+
+        let included = Location {
+            file: &file,
+            path: Some(&path),
+            line_delta: 0,
+            depth: location.depth + 1,
+        };
+
+        output.enter_file();
+
+        // Errors within the included file are reported there, and passed through unchanged
+        self.process(included, &source, output).map_err(|err| ErrorKind::Included(Box::new(err.in_file(&file))))?;
+
+        output.leave_file(args.first().map_or(0, |token| token.line));
+
+        Ok(())
+    }
+
+    fn expand(&self, location: &Location, tokens: Vec<Token>) -> Result<Vec<Token>, PreprocessorError> {
+        let builtin = |token: &Token| self.builtin(location, token);
+
+        Expander::new(&self.macros, &builtin).expand(tokens)
+    }
+
+    /// Replacements of predefined macros that depend on where they appear
+    fn builtin(&self, location: &Location, token: &Token) -> Option<Token> {
+        Some(match &token.text[..] {
+            "__LINE__" => Token::new(TokenKind::Number, (token.line as isize + location.line_delta).to_string()),
+            "__FILE__" => Token::new(TokenKind::StringLiteral, quote(location.file)),
+            "__DATE__" => Token::new(TokenKind::StringLiteral, self.date.clone()),
+            "__TIME__" => Token::new(TokenKind::StringLiteral, self.time.clone()),
+            _ => return None,
+        })
+    }
+
+    /// Evaluates the condition of `#if` or `#elif`
+    fn condition(&self, location: &Location, tokens: &[Token]) -> Result<bool, ErrorKind> {
+        let defined = |name: &str| self.is_defined(name);
+        let has_include = |name: &str, angled: bool| match self.has_include {
+            Some(ref has_include) => has_include(name, angled),
+            None => self.resolve(location, name, angled).is_some(),
+        };
+        let has_attribute = |name: &str| self.has_attribute.as_ref().map_or(0, |has_attribute| has_attribute(name));
+
+        let tokens = condition::replace_operators(tokens, &Operators {
+            defined: &defined,
+            has_include: &has_include,
+            has_attribute: &has_attribute,
+        })?;
+
+        let tokens = self.expand(location, tokens).map_err(|err| err.kind)?;
+
+        condition::evaluate(&tokens).map(|value| !value.is_zero())
+    }
+
+    /// Executes the directive on `line`, whose first token is `#`
+    fn directive(&mut self, location: &mut Location, conditionals: &mut Vec<Conditional>, line: &[Token], output: &mut Output) -> Result<(), PreprocessorError> {
+        let hash = &line[0];
+
+        // The null directive
+        let name = match line.get(1) {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+
+        let error = |kind: ErrorKind| PreprocessorError::new(kind, name.offset, name.line);
+
+        let args = &line[2..];
+
+        // Whether the enclosing group, or the group of a new conditional, is kept
+        let active = is_active(conditionals);
+
+        if name.kind == TokenKind::Identifier {
+            // Whether the group of a conditional is kept, given its directive is reached
+            let kept = |this: &Preprocessor| -> Result<bool, PreprocessorError> {
+                Ok(match &name.text[..] {
+                    "if" | "elif" => this.condition(location, args).map_err(&error)?,
+                    "ifdef" | "elifdef" | "ifndef" | "elifndef" => {
+                        let defined = match args.first() {
+                            Some(macro_name) if macro_name.kind == TokenKind::Identifier => this.is_defined(&macro_name.text),
+                            _ => return Err(error(ErrorKind::MissingMacroName)),
+                        };
+
+                        defined == name.text.ends_with("ifdef")
+                    },
+                    _ => true,
+                })
+            };
+
+            match &name.text[..] {
+                "if" | "ifdef" | "ifndef" => {
+                    let kept = active && kept(self)?;
+
+                    conditionals.push(Conditional {
+                        active: kept,
+                        taken: kept || !active,
+                        seen_else: false,
+                        start: hash.clone(),
+                    });
+
+                    return Ok(());
+                },
+                "elif" | "elifdef" | "elifndef" | "else" => {
+                    let unmatched = match conditionals.last() {
+                        Some(conditional) => conditional.seen_else,
+                        None => true,
+                    };
+
+                    if unmatched {
+                        return Err(error(ErrorKind::UnmatchedConditional(name.text.clone())));
+                    }
+
+                    // Conditions are not evaluated once a group has been kept
+                    let kept = !conditionals.last().unwrap().taken && kept(self)?;
+
+                    let conditional = conditionals.last_mut().unwrap();
+
+                    conditional.active = kept;
+                    conditional.taken |= kept;
+                    conditional.seen_else = name.text == "else";
+
+                    return Ok(());
+                },
+                "endif" => {
+                    return match conditionals.pop() {
+                        Some(_) => Ok(()),
+                        None => Err(error(ErrorKind::UnmatchedConditional(name.text.clone()))),
+                    };
+                },
+                _ => {}
+            }
+        }
+
+        // Any other directive in a skipped group is ignored, even if it is not valid
+        if !active {
+            return Ok(());
+        }
+
+        if name.kind != TokenKind::Identifier {
+            return Err(PreprocessorError::new(ErrorKind::InvalidDirective(name.text.clone()), hash.offset, hash.line));
+        }
+
+        match &name.text[..] {
+            "define" => {
+                Macro::parse(args).and_then(|m| self.macros.define(m)).map_err(error)
+            },
+            "undef" => {
+                match args.first() {
+                    Some(name) if name.kind == TokenKind::Identifier => {
+                        self.macros.undefine(&name.text);
+                        Ok(())
+                    },
+                    _ => Err(error(ErrorKind::MissingMacroName)),
+                }
+            },
+            "line" => {
+                let args = self.expand(location, args.to_vec())?;
+
+                let number = match args.first() {
+                    Some(number) if number.kind == TokenKind::Number => number.text.parse::<isize>().ok(),
+                    _ => None,
+                };
+
+                match number {
+                    Some(number) => {
+                        // The line following the directive has the given number
+                        location.line_delta = number - (name.line as isize + 1);
+
+                        Ok(())
+                    },
+                    None => Err(error(ErrorKind::InvalidDirective(spell(args.iter())))),
+                }
+            },
+            "include" => {
+                match self.include(location, args, output) {
+                    Err(ErrorKind::Included(err)) => Err(*err),
+                    res => res.map_err(error),
+                }
+            },
+            "error" => Err(error(ErrorKind::ErrorDirective(spell(args.iter())))),
+            "pragma" => {
+                if args.len() == 1 && args[0].is_identifier("once") {
+                    if let Some(path) = location.path {
+                        self.once.insert(path.to_owned());
+                    }
+                }
+
+                // Unrecognized pragmas are ignored
+                Ok(())
+            },
+            "warning" => Ok(()),
+            _ => Err(error(ErrorKind::UnknownDirective(name.text.clone()))),
+        }
+    }
+}
+
+fn is_active(conditionals: &[Conditional]) -> bool {
+    conditionals.last().map_or(true, |conditional| conditional.active)
+}
+
+fn is_directive_start(token: &Token) -> bool {
+    token.is_punctuator("#") || token.is_punctuator("%:")
+}
+
+/// Spells tokens with single spaces where there was any whitespace
+fn spell<'a, I>(tokens: I) -> String where I: Iterator<Item = &'a Token> {
+    let mut text = String::new();
+
+    for token in tokens {
+        if token.leading_space && !text.is_empty() {
+            text.push(' ');
+        }
+
+        text.push_str(&token.text);
+    }
+
+    text
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Formats a time as `__DATE__` and `__TIME__` would, in UTC
+fn date_time(time: SystemTime) -> (String, String) {
+    const MONTHS: [&'static str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let seconds = time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0);
+
+    let days = (seconds / 86400) as i64;
+    let seconds = seconds % 86400;
+
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (
+        format!("\"{} {:2} {}\"", MONTHS[month as usize - 1], day, year),
+        format!("\"{:02}:{:02}:{:02}\"", seconds / 3600, seconds / 60 % 60, seconds % 60),
+    )
+}
+
+/// Writes tokens as text, keeping them on the lines they were found on
+struct Output {
+    text: String,
+    line: usize,
+    last: Option<Token>,
+}
+
+impl Output {
+    fn new() -> Output {
+        Output { text: String::new(), line: 1, last: None }
+    }
+
+    fn write(&mut self, tokens: &[Token]) {
+        for token in tokens {
+            if token.line > self.line {
+                for _ in self.line..token.line {
+                    self.text.push('\n');
+                }
+
+                self.line = token.line;
+                self.last = None;
+            }
+
+            let space = match self.last {
+                Some(ref last) => token.leading_space || token.line_start || token::needs_space(last, token),
+                None => false,
+            };
+
+            if space {
+                self.text.push(' ');
+            }
+
+            self.text.push_str(&token.text);
+            self.last = Some(token.clone());
+        }
+    }
+
+    /// Starts writing an included file on a new line
+    fn enter_file(&mut self) {
+        if self.last.is_some() {
+            self.text.push('\n');
+        }
+
+        self.line = 1;
+        self.last = None;
+    }
+
+    /// Resumes writing the including file after the directive on `line`
+    fn leave_file(&mut self, line: usize) {
+        self.enter_file();
+
+        self.line = line + 1;
+    }
+
+    fn finish(mut self) -> String {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+
+        self.text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn preprocess(source: &str) -> Result<String, ErrorKind> {
+        let mut preprocessor = Preprocessor::new();
+
+        preprocessor.set_date_time("Oct 18 2026", "12:34:56");
+        preprocessor.preprocess("test.c", source.as_bytes()).map_err(|err| err.kind)
+    }
+
+    #[test]
+    fn test_define_undef() {
+        assert_eq!(preprocess("#define N 10\nint a[N];\n#undef N\nint b[N];\n"), Ok("\nint a[10];\n\nint b[N];\n".to_owned()));
+
+        assert_eq!(preprocess("# define MAX(a, b) \\\n ((a) > (b) ? (a) : (b))\nint m = MAX(1,\n 2);"),
+            Ok("\n\nint m = ((1) > (2) ? (1) : (2))\n;\n".to_owned()));
+
+        assert_eq!(preprocess("#define P +\nint x = +P 1;\n"), Ok("\nint x = + + 1;\n".to_owned()));
+    }
+
+    #[test]
+    fn test_builtins() {
+        assert_eq!(preprocess("__LINE__ __FILE__\n#line 100\n__LINE__ __DATE__ __TIME__ __STDC_VERSION__"),
+            Ok("1 \"test.c\"\n\n100 \"Oct 18 2026\" \"12:34:56\" 202311L\n".to_owned()));
+
+        assert_eq!(date_time(UNIX_EPOCH + ::std::time::Duration::from_secs(951_782_400 + 3_661)),
+            ("\"Feb 29 2000\"".to_owned(), "\"01:01:01\"".to_owned()));
+    }
+
+    #[test]
+    fn test_standard_version() {
+        let version = |preprocessor: &mut Preprocessor| preprocessor.preprocess("test.c", b"__STDC_VERSION__").unwrap();
+
+        assert_eq!(version(&mut Preprocessor::for_standard(Standard::C99)), "199901L\n");
+        assert_eq!(version(&mut Preprocessor::for_standard(Standard::C11)), "201112L\n");
+
+        let mut preprocessor = Preprocessor::for_standard(Standard::C17);
+
+        assert_eq!(version(&mut preprocessor), "201710L\n");
+
+        preprocessor.set_standard(Standard::C89);
+
+        assert!(!preprocessor.is_defined("__STDC_VERSION__"));
+        assert_eq!(version(&mut preprocessor), "__STDC_VERSION__\n");
+    }
+
+    #[test]
+    fn test_directive_errors() {
+        assert_eq!(preprocess("#error stop here\n"), Err(ErrorKind::ErrorDirective("stop here".to_owned())));
+        assert_eq!(preprocess("#frobnicate\n"), Err(ErrorKind::UnknownDirective("frobnicate".to_owned())));
+        assert_eq!(preprocess("#define\n"), Err(ErrorKind::MissingMacroName));
+        assert_eq!(preprocess("#\n#pragma anything\nx # y\n"), Ok("\n\nx # y\n".to_owned()));
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let source = "
+            #define VERSION 3
+            #if VERSION >= 4
+            four
+            #elif VERSION == 3 && defined(VERSION)
+            three
+            #  if 0
+            #    frobnicate
+            #    if 1 / 0
+            #    endif
+            #  elifndef VERSION
+            not here
+            #  else
+            nested
+            #  endif
+            #elif 1 / 0
+            #else
+            other
+            #endif
+            #ifdef __has_include
+            has_include
+            #endif
+            #if __has_include(<missing.h>) || !__has_include(\"here.h\")
+            wrong
+            #elifdef __FILE__
+            file
+            #endif
+        ";
+
+        let mut preprocessor = Preprocessor::new();
+
+        preprocessor.set_has_include(|name, angled| !angled && name == "here.h");
+
+        let output = preprocessor.preprocess("test.c", source.as_bytes()).unwrap();
+
+        let words: Vec<_> = output.split_whitespace().collect();
+
+        assert_eq!(words, vec!["three", "nested", "has_include", "file"]);
+    }
+
+    #[test]
+    fn test_conditional_errors() {
+        assert_eq!(preprocess("#if 1\n#else\n#else\n#endif\n"), Err(ErrorKind::UnmatchedConditional("else".to_owned())));
+        assert_eq!(preprocess("#endif\n"), Err(ErrorKind::UnmatchedConditional("endif".to_owned())));
+        assert_eq!(preprocess("#ifdef X\n"), Err(ErrorKind::UnterminatedConditional));
+        assert_eq!(preprocess("#ifdef\n#endif\n"), Err(ErrorKind::MissingMacroName));
+        assert_eq!(preprocess("#if\n#endif\n"), Err(ErrorKind::InvalidCondition("".to_owned())));
+    }
+
+    #[test]
+    fn test_include() {
+        use ::preprocessor::include::MemoryFiles;
+
+        let mut files = MemoryFiles::new();
+
+        files.insert("/src/main.c", "#include \"config.h\"\n#include <lib.h>\n#include HEADER\n#include <lib.h>\n#include \"config.h\"\nint main;\n");
+        files.insert("/src/config.h", "#pragma once\n#define HEADER <util.h>\nint config;\n");
+        files.insert("/src/util.h", "#include \"config.h\"\nint src_util;\n");
+        files.insert("/include/util.h", "#include \"lib.h\"\n#if __has_include(\"lib.h\") && !__has_include(<main.c>)\nint util;\n#endif\n");
+        files.insert("/include/lib.h", "#ifndef LIB_H\n#define LIB_H\nint lib;\n#endif\n");
+        files.insert("/include/config.h", "#error wrong config.h\n");
+
+        let mut preprocessor = Preprocessor::new();
+
+        preprocessor.set_file_provider(files);
+        preprocessor.add_include_path("/include");
+
+        let output = preprocessor.preprocess_file("/src/main.c").unwrap();
+
+        let words: Vec<_> = output.split(|c: char| c.is_whitespace() || c == ';').filter(|word| !word.is_empty() && *word != "int").collect();
+
+        assert_eq!(words, vec!["config", "lib", "util", "main"]);
+        assert_eq!(preprocessor.include_guard("/include/lib.h"), Some("LIB_H"));
+        assert_eq!(preprocessor.include_guard("/src/config.h"), None);
+    }
+
+    #[test]
+    fn test_include_errors() {
+        use ::preprocessor::include::MemoryFiles;
+
+        let mut files = MemoryFiles::new();
+
+        files.insert("/a.h", "\n#include \"b.h\"\n");
+        files.insert("/b.h", "int b;\n#error in b\n");
+        files.insert("/self.h", "#include \"self.h\"\n");
+
+        let mut preprocessor = Preprocessor::new();
+
+        preprocessor.set_file_provider(files);
+
+        let err = preprocessor.preprocess_file("/a.h").unwrap_err();
+
+        assert_eq!((&err.file[..], err.line, err.kind), ("/b.h", 2, ErrorKind::ErrorDirective("in b".to_owned())));
+
+        assert_eq!(preprocessor.preprocess_file("/self.h").unwrap_err().kind, ErrorKind::IncludeTooDeep("self.h".to_owned()));
+        assert_eq!(preprocessor.preprocess("x.c", b"#include <missing.h>").unwrap_err().kind, ErrorKind::IncludeNotFound("missing.h".to_owned()));
+        assert_eq!(preprocessor.preprocess("x.c", b"#include missing").unwrap_err().kind, ErrorKind::InvalidInclude("missing".to_owned()));
+    }
+
+    #[test]
+    fn test_preprocess_and_parse() {
+        let source = "
+            #define DECLARE(type, ...) type __VA_ARGS__;
+            #define CONCAT(a, b) a ## b
+
+            DECLARE(static int, CONCAT(count, er), *p)
+        ";
+
+        let output = preprocess(source).unwrap();
+
+        let unit = ::parser::translation_unit::parse_translation_unit(output.as_bytes()).unwrap();
+
+        assert_eq!(unit.items.len(), 1);
+        assert_eq!(output.lines().nth(4).map(str::trim), Some("static int counter, *p;"));
+    }
+}
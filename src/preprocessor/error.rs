@@ -0,0 +1,110 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use ::parser::constant::EvaluationError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    UnterminatedComment,
+    /// A `#` at the start of a line followed by something other than a directive name
+    InvalidDirective(String),
+    UnknownDirective(String),
+    /// `#define` or `#undef` without an identifier to name the macro
+    MissingMacroName,
+    InvalidMacroParameters(String),
+    /// A macro was redefined with a different replacement list
+    MacroRedefinition(String),
+    /// `#` in a function-like macro not followed by a parameter
+    InvalidStringize(String),
+    /// `##` at either end of a replacement list
+    InvalidPasteOperand(String),
+    /// Pasting two tokens did not produce a single valid token
+    InvalidPaste(String, String),
+    InvalidVaOpt(String),
+    /// The arguments of a function-like macro invocation were never closed
+    UnterminatedInvocation(String),
+    WrongArgumentCount { name: String, expected: usize, found: usize },
+    /// `#error`, with its message
+    ErrorDirective(String),
+    /// `#if` or `#elif` whose condition is not a valid expression
+    InvalidCondition(String),
+    /// A condition could not be evaluated, such as when dividing by zero
+    Evaluation(EvaluationError),
+    /// `#elif`, `#else` or `#endif` without a matching `#if`, or following `#else`
+    UnmatchedConditional(String),
+    /// `#if` without a matching `#endif`
+    UnterminatedConditional,
+    /// `#include` not followed by `"name"` or `<name>`, even after macro expansion
+    InvalidInclude(String),
+    IncludeNotFound(String),
+    /// Includes were nested too deeply, such as a file including itself
+    IncludeTooDeep(String),
+    /// An error within an included file
+    Included(Box<PreprocessorError>),
+}
+
+/// An error found while preprocessing, located in the original source
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreprocessorError {
+    pub kind: ErrorKind,
+    /// Name of the file containing the error, which is empty until it is known
+    pub file: String,
+    /// Byte offset into the original source
+    pub offset: usize,
+    /// One-based line in the original source
+    pub line: usize,
+}
+
+impl PreprocessorError {
+    pub fn new(kind: ErrorKind, offset: usize, line: usize) -> PreprocessorError {
+        PreprocessorError { kind: kind, file: String::new(), offset: offset, line: line }
+    }
+
+    /// Attributes the error to `file`, unless it already belongs to a file
+    pub fn in_file(mut self, file: &str) -> PreprocessorError {
+        if self.file.is_empty() {
+            self.file = file.to_owned();
+        }
+
+        self
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            ErrorKind::UnterminatedComment => write!(f, "unterminated comment"),
+            ErrorKind::InvalidDirective(ref found) => write!(f, "invalid preprocessing directive `{}`", found),
+            ErrorKind::UnknownDirective(ref name) => write!(f, "unknown preprocessing directive `#{}`", name),
+            ErrorKind::MissingMacroName => write!(f, "macro names must be identifiers"),
+            ErrorKind::InvalidMacroParameters(ref name) => write!(f, "invalid parameter list for macro `{}`", name),
+            ErrorKind::MacroRedefinition(ref name) => write!(f, "macro `{}` redefined differently", name),
+            ErrorKind::InvalidStringize(ref name) => write!(f, "`#` is not followed by a macro parameter in macro `{}`", name),
+            ErrorKind::InvalidPasteOperand(ref name) => write!(f, "`##` cannot appear at either end of macro `{}`", name),
+            ErrorKind::InvalidPaste(ref lhs, ref rhs) => write!(f, "pasting `{}` and `{}` does not give a valid preprocessing token", lhs, rhs),
+            ErrorKind::InvalidVaOpt(ref name) => write!(f, "invalid use of `__VA_OPT__` in macro `{}`", name),
+            ErrorKind::UnterminatedInvocation(ref name) => write!(f, "unterminated argument list invoking macro `{}`", name),
+            ErrorKind::WrongArgumentCount { ref name, expected, found } => {
+                write!(f, "macro `{}` expects {} arguments, but {} were given", name, expected, found)
+            },
+            ErrorKind::ErrorDirective(ref message) => write!(f, "#error {}", message),
+            ErrorKind::InvalidCondition(ref condition) => write!(f, "invalid preprocessor condition `{}`", condition),
+            ErrorKind::Evaluation(ref err) => write!(f, "could not evaluate preprocessor condition: {:?}", err),
+            ErrorKind::UnmatchedConditional(ref name) => write!(f, "`#{}` without matching `#if`", name),
+            ErrorKind::UnterminatedConditional => write!(f, "unterminated conditional directive"),
+            ErrorKind::InvalidInclude(ref args) => write!(f, "`#include` expects \"FILENAME\" or <FILENAME>, found `{}`", args),
+            ErrorKind::IncludeNotFound(ref name) => write!(f, "`{}` file not found", name),
+            ErrorKind::IncludeTooDeep(ref name) => write!(f, "`#include` nested too deeply including `{}`", name),
+            ErrorKind::Included(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Display for PreprocessorError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if self.file.is_empty() {
+            write!(f, "{} on line {}", self.kind, self.line)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.kind)
+        }
+    }
+}
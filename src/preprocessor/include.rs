@@ -0,0 +1,214 @@
+//! Finding and reading included files
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::token::Token;
+
+/// Provides the contents of files to the preprocessor
+pub trait FileProvider {
+    /// Reads the entire file at `path`, or returns `None` if it does not exist
+    fn read(&self, path: &Path) -> Option<Vec<u8>>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.read(path).is_some()
+    }
+}
+
+/// Reads files from disk
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskFiles;
+
+impl FileProvider for DiskFiles {
+    fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        fs::read(path).ok()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+}
+
+/// Files held in memory, such as for tests or editor buffers that have not been saved
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFiles {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFiles {
+    pub fn new() -> MemoryFiles {
+        MemoryFiles::default()
+    }
+
+    pub fn insert<P, C>(&mut self, path: P, contents: C) where P: AsRef<Path>, C: Into<Vec<u8>> {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+    }
+
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> Option<Vec<u8>> {
+        self.files.remove(&normalize(path.as_ref()))
+    }
+}
+
+impl FileProvider for MemoryFiles {
+    fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files.get(&normalize(path)).cloned()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(&normalize(path))
+    }
+}
+
+/// Removes `.` and resolves `..` components without touching the file system
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                match normalized.components().next_back() {
+                    Some(Component::Normal(_)) => { normalized.pop(); },
+                    Some(Component::RootDir) | Some(Component::Prefix(_)) => {},
+                    _ => normalized.push(".."),
+                }
+            },
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+/// Directories searched for included files, as given by `-iquote`, `-I` and `-isystem`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IncludePaths {
+    /// Searched only for `#include "name"`, after the directory of the including file
+    pub quote: Vec<PathBuf>,
+    pub user: Vec<PathBuf>,
+    /// Searched last, for system headers
+    pub system: Vec<PathBuf>,
+}
+
+impl IncludePaths {
+    pub fn new() -> IncludePaths {
+        IncludePaths::default()
+    }
+
+    /// Finds the file named by `#include "name"` or `#include <name>`, where `current_dir`
+    /// is the directory of the including file, if it came from a file
+    pub fn resolve(&self, files: &FileProvider, name: &str, angled: bool, current_dir: Option<&Path>) -> Option<PathBuf> {
+        let name = Path::new(name);
+
+        if name.is_absolute() {
+            let path = normalize(name);
+
+            return if files.exists(&path) { Some(path) } else { None };
+        }
+
+        let quoted = if angled { None } else { current_dir };
+
+        let mut dirs = quoted.into_iter()
+            .chain(self.quote.iter().filter(|_| !angled).map(|dir| dir.as_path()))
+            .chain(self.user.iter().map(|dir| dir.as_path()))
+            .chain(self.system.iter().map(|dir| dir.as_path()));
+
+        dirs.find(|dir| files.exists(&normalize(&dir.join(name)))).map(|dir| normalize(&dir.join(name)))
+    }
+}
+
+/// Detects a file wrapped entirely in an include guard, returning the guard macro
+///
+/// The first directive must be `#ifndef NAME` or `#if !defined NAME`, and its `#endif` must end the file
+/// with no `#else` or `#elif` in between, so including the file again has no effect while `NAME` is defined.
+pub fn include_guard(tokens: &[Token]) -> Option<String> {
+    let is_directive = |token: &Token| token.line_start && (token.is_punctuator("#") || token.is_punctuator("%:"));
+
+    let lines: Vec<&[Token]> = {
+        let mut starts: Vec<usize> = tokens.iter().enumerate().filter(|&(_, token)| token.line_start).map(|(i, _)| i).collect();
+
+        starts.push(tokens.len());
+
+        starts.windows(2).map(|range| &tokens[range[0]..range[1]]).collect()
+    };
+
+    let first = lines.first()?;
+
+    if !is_directive(&first[0]) {
+        return None;
+    }
+
+    let texts: Vec<&str> = first[1..].iter().map(|token| &token.text[..]).collect();
+
+    let guard = match texts[..] {
+        ["ifndef", name] | ["if", "!", "defined", name] | ["if", "!", "defined", "(", name, ")"] => name.to_owned(),
+        _ => return None,
+    };
+
+    let mut depth = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        if !is_directive(&line[0]) || line.len() < 2 {
+            continue;
+        }
+
+        match &line[1].text[..] {
+            "if" | "ifdef" | "ifndef" => depth += 1,
+            "elif" | "elifdef" | "elifndef" | "else" if depth == 1 => return None,
+            "endif" => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return if i == lines.len() - 1 { Some(guard) } else { None };
+                }
+            },
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ::preprocessor::token::tokenize;
+
+    #[test]
+    fn test_resolve() {
+        let mut files = MemoryFiles::new();
+
+        files.insert("/project/src/local.h", "");
+        files.insert("/project/include/local.h", "");
+        files.insert("/project/include/lib.h", "");
+        files.insert("/usr/include/stdio.h", "");
+
+        let paths = IncludePaths {
+            quote: vec![],
+            user: vec![PathBuf::from("/project/src/../include")],
+            system: vec![PathBuf::from("/usr/include")],
+        };
+
+        let src = Some(Path::new("/project/src"));
+
+        assert_eq!(paths.resolve(&files, "local.h", false, src), Some(PathBuf::from("/project/src/local.h")));
+        assert_eq!(paths.resolve(&files, "local.h", true, src), Some(PathBuf::from("/project/include/local.h")));
+        assert_eq!(paths.resolve(&files, "./../include/lib.h", false, src), Some(PathBuf::from("/project/include/lib.h")));
+        assert_eq!(paths.resolve(&files, "stdio.h", true, None), Some(PathBuf::from("/usr/include/stdio.h")));
+        assert_eq!(paths.resolve(&files, "/usr/include/stdio.h", true, None), Some(PathBuf::from("/usr/include/stdio.h")));
+        assert_eq!(paths.resolve(&files, "missing.h", false, src), None);
+    }
+
+    #[test]
+    fn test_include_guard() {
+        let guard = |source: &str| include_guard(&tokenize(source.as_bytes()).unwrap());
+
+        assert_eq!(guard("#ifndef A_H\n#define A_H\n#if X\n#else\n#endif\nint a;\n#endif\n"), Some("A_H".to_owned()));
+        assert_eq!(guard("#if !defined(B_H)\n#define B_H\n#endif"), Some("B_H".to_owned()));
+        assert_eq!(guard("#ifndef A_H\n#define A_H\n#endif\nint a;\n"), None);
+        assert_eq!(guard("#ifndef A_H\n#else\n#endif\n"), None);
+        assert_eq!(guard("int a;\n#ifndef A_H\n#endif\n"), None);
+    }
+}
@@ -0,0 +1,732 @@
+//! Macro definitions and expansion
+//!
+//! Expansion follows Prosser's algorithm: every token carries a hide set of the macros it
+//! resulted from, and an identifier is never expanded by a macro in its own hide set. This
+//! implements the rescanning rules of C11 6.10.3.4, including tokens being "painted blue"
+//! and never expanded again once they were found while their macro was being replaced.
+
+use std::collections::{HashMap, VecDeque};
+
+use super::error::{PreprocessorError, ErrorKind};
+use super::token::{Token, TokenKind, HideSet, single_token};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MacroKind {
+    Object,
+    Function {
+        params: Vec<String>,
+        /// Whether the parameter list ends in `...`, whose arguments are named `__VA_ARGS__`
+        variadic: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub name: String,
+    pub kind: MacroKind,
+    /// The replacement list, where the first token never has leading whitespace
+    pub body: Vec<Token>,
+}
+
+impl Macro {
+    /// Parses the tokens of a `#define` directive following the directive name
+    pub fn parse(tokens: &[Token]) -> Result<Macro, ErrorKind> {
+        let name = match tokens.first() {
+            Some(token) if token.kind == TokenKind::Identifier && token.text != "defined" => token.text.clone(),
+            _ => return Err(ErrorKind::MissingMacroName),
+        };
+
+        let mut rest = &tokens[1..];
+
+        // Function-like macros have no whitespace between their name and parameters
+        let kind = match rest.first() {
+            Some(token) if token.is_punctuator("(") && !token.leading_space => {
+                let (params, variadic, len) = parse_params(rest).ok_or_else(|| ErrorKind::InvalidMacroParameters(name.clone()))?;
+
+                rest = &rest[len..];
+
+                MacroKind::Function { params: params, variadic: variadic }
+            },
+            _ => MacroKind::Object,
+        };
+
+        let mut body = rest.to_vec();
+
+        if let Some(first) = body.first_mut() {
+            first.leading_space = false;
+        }
+
+        let m = Macro { name: name, kind: kind, body: body };
+
+        m.validate()?;
+
+        Ok(m)
+    }
+
+    pub fn is_function_like(&self) -> bool {
+        match self.kind {
+            MacroKind::Function { .. } => true,
+            MacroKind::Object => false,
+        }
+    }
+
+    fn is_variadic(&self) -> bool {
+        match self.kind {
+            MacroKind::Function { variadic, .. } => variadic,
+            MacroKind::Object => false,
+        }
+    }
+
+    /// Index of the argument substituted for `token`, if it names a parameter
+    fn param(&self, token: &Token) -> Option<usize> {
+        if token.kind != TokenKind::Identifier {
+            return None;
+        }
+
+        match self.kind {
+            MacroKind::Function { ref params, variadic } => {
+                if variadic && token.text == "__VA_ARGS__" {
+                    Some(params.len())
+                } else {
+                    params.iter().position(|param| *param == token.text)
+                }
+            },
+            MacroKind::Object => None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), ErrorKind> {
+        let body = &self.body;
+
+        let paste_at = |token: Option<&Token>| token.map_or(false, is_paste);
+
+        if paste_at(body.first()) || paste_at(body.last()) {
+            return Err(ErrorKind::InvalidPasteOperand(self.name.clone()));
+        }
+
+        if !self.is_function_like() {
+            return Ok(());
+        }
+
+        for (i, token) in body.iter().enumerate() {
+            if is_stringize(token) {
+                match body.get(i + 1) {
+                    Some(next) if self.param(next).is_some() => {},
+                    Some(next) if self.is_variadic() && next.is_identifier("__VA_OPT__") => {},
+                    _ => return Err(ErrorKind::InvalidStringize(self.name.clone())),
+                }
+            }
+
+            if token.is_identifier("__VA_OPT__") {
+                let end = if self.is_variadic() { va_opt_end(body, i) } else { None };
+
+                match end {
+                    Some(end) if !paste_at(body.get(i + 2)) && !paste_at(body.get(end - 2)) => {},
+                    _ => return Err(ErrorKind::InvalidVaOpt(self.name.clone())),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if `other` is an identical redefinition, which is permitted
+    pub fn same_as(&self, other: &Macro) -> bool {
+        self.kind == other.kind &&
+            self.body.len() == other.body.len() &&
+            self.body.iter().zip(other.body.iter()).all(|(a, b)| a.same_as(b))
+    }
+}
+
+fn is_stringize(token: &Token) -> bool {
+    token.is_punctuator("#") || token.is_punctuator("%:")
+}
+
+fn is_paste(token: &Token) -> bool {
+    token.is_punctuator("##") || token.is_punctuator("%:%:")
+}
+
+/// Parses a parameter list starting at `(`, returning the names,
+/// whether it is variadic and the number of tokens consumed
+fn parse_params(tokens: &[Token]) -> Option<(Vec<String>, bool, usize)> {
+    let mut params: Vec<String> = Vec::new();
+
+    if tokens.get(1).map_or(false, |token| token.is_punctuator(")")) {
+        return Some((params, false, 2));
+    }
+
+    let mut i = 1;
+
+    loop {
+        let token = tokens.get(i)?;
+
+        if token.is_punctuator("...") {
+            return match tokens.get(i + 1) {
+                Some(token) if token.is_punctuator(")") => Some((params, true, i + 2)),
+                _ => None,
+            };
+        }
+
+        if token.kind != TokenKind::Identifier || token.text == "__VA_ARGS__" || params.contains(&token.text) {
+            return None;
+        }
+
+        params.push(token.text.clone());
+
+        let next = tokens.get(i + 1)?;
+
+        if next.is_punctuator(")") {
+            return Some((params, false, i + 2));
+        } else if !next.is_punctuator(",") {
+            return None;
+        }
+
+        i += 2;
+    }
+}
+
+/// Index one past the `)` closing the `__VA_OPT__` at `start`
+fn va_opt_end(tokens: &[Token], start: usize) -> Option<usize> {
+    if !tokens.get(start + 1).map_or(false, |token| token.is_punctuator("(")) {
+        return None;
+    }
+
+    let mut depth = 0;
+
+    for (i, token) in tokens.iter().enumerate().skip(start + 1) {
+        if token.is_punctuator("(") {
+            depth += 1;
+        } else if token.is_punctuator(")") {
+            depth -= 1;
+
+            if depth == 0 {
+                return Some(i + 1);
+            }
+        }
+    }
+
+    None
+}
+
+/// The set of currently defined macros
+#[derive(Debug, Clone, Default)]
+pub struct MacroTable {
+    macros: HashMap<String, Macro>,
+}
+
+impl MacroTable {
+    pub fn new() -> MacroTable {
+        MacroTable::default()
+    }
+
+    /// Defines a macro, failing if it was previously defined differently
+    pub fn define(&mut self, m: Macro) -> Result<(), ErrorKind> {
+        if let Some(previous) = self.macros.get(&m.name) {
+            if !previous.same_as(&m) {
+                return Err(ErrorKind::MacroRedefinition(m.name.clone()));
+            }
+        }
+
+        self.macros.insert(m.name.clone(), m);
+
+        Ok(())
+    }
+
+    pub fn undefine(&mut self, name: &str) -> Option<Macro> {
+        self.macros.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Macro> {
+        self.macros.get(name)
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.macros.contains_key(name)
+    }
+}
+
+/// Parts of a replacement list before `##` is applied
+enum Item {
+    Token(Token),
+    Paste,
+}
+
+/// Expands macros within sequences of tokens
+pub struct Expander<'a> {
+    macros: &'a MacroTable,
+    /// Produces the replacement of predefined macros such as `__LINE__`, whose value depends on where they appear
+    builtin: &'a Fn(&Token) -> Option<Token>,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(macros: &'a MacroTable, builtin: &'a Fn(&Token) -> Option<Token>) -> Expander<'a> {
+        Expander { macros: macros, builtin: builtin }
+    }
+
+    /// Fully macro-expands `tokens`
+    ///
+    /// Invocations of function-like macros may extend to any later token.
+    pub fn expand(&self, tokens: Vec<Token>) -> Result<Vec<Token>, PreprocessorError> {
+        let mut input: VecDeque<Token> = tokens.into();
+        let mut output = Vec::new();
+
+        while let Some(token) = input.pop_front() {
+            if token.kind != TokenKind::Identifier || token.hide_set.contains(&token.text) {
+                output.push(token);
+                continue;
+            }
+
+            if let Some(mut replacement) = (self.builtin)(&token) {
+                replacement.leading_space = token.leading_space;
+                replacement.line_start = token.line_start;
+                replacement.offset = token.offset;
+                replacement.line = token.line;
+
+                output.push(replacement);
+                continue;
+            }
+
+            let m = match self.macros.get(&token.text) {
+                Some(m) => m,
+                None => {
+                    output.push(token);
+                    continue;
+                }
+            };
+
+            let replacement = if m.is_function_like() {
+                // A function-like macro name not followed by `(` is an ordinary identifier
+                if !input.front().map_or(false, |next| next.is_punctuator("(")) {
+                    output.push(token);
+                    continue;
+                }
+
+                let (args, rparen) = self.collect_args(m, &token, &mut input)?;
+
+                let mut hide_set: HideSet = token.hide_set.intersection(&rparen.hide_set).cloned().collect();
+
+                hide_set.insert(m.name.clone());
+
+                self.substitute(m, &token, &args, &hide_set)?
+            } else {
+                let mut hide_set = token.hide_set.clone();
+
+                hide_set.insert(m.name.clone());
+
+                self.substitute(m, &token, &[], &hide_set)?
+            };
+
+            // Rescan the replacement along with the rest of the input
+            for token in replacement.into_iter().rev() {
+                input.push_front(token);
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn error(&self, kind: ErrorKind, token: &Token) -> PreprocessorError {
+        PreprocessorError::new(kind, token.offset, token.line)
+    }
+
+    /// Collects the arguments of an invocation of `m`, whose `(` is the next input token,
+    /// returning one argument per parameter followed by the variable arguments, and the closing `)`
+    fn collect_args(&self, m: &Macro, name: &Token, input: &mut VecDeque<Token>) -> Result<(Vec<Vec<Token>>, Token), PreprocessorError> {
+        let (params, variadic) = match m.kind {
+            MacroKind::Function { ref params, variadic } => (params.len(), variadic),
+            MacroKind::Object => unreachable!(),
+        };
+
+        input.pop_front();
+
+        let mut args = Vec::new();
+        let mut current = Vec::new();
+        let mut depth = 0;
+
+        let rparen = loop {
+            let token = match input.pop_front() {
+                Some(token) => token,
+                None => return Err(self.error(ErrorKind::UnterminatedInvocation(m.name.clone()), name)),
+            };
+
+            if token.is_punctuator("(") {
+                depth += 1;
+            } else if token.is_punctuator(")") {
+                if depth == 0 {
+                    args.push(current);
+                    break token;
+                }
+
+                depth -= 1;
+            } else if token.is_punctuator(",") && depth == 0 && (!variadic || args.len() < params) {
+                args.push(::std::mem::replace(&mut current, Vec::new()));
+                continue;
+            }
+
+            current.push(token);
+        };
+
+        // `f()` passes a single empty argument, which is no arguments at all for `f` without parameters
+        if params == 0 && !variadic && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+
+        // The variable arguments may be omitted entirely
+        if variadic && args.len() == params {
+            args.push(Vec::new());
+        }
+
+        let expected = params + variadic as usize;
+
+        if args.len() != expected {
+            return Err(self.error(ErrorKind::WrongArgumentCount {
+                name: m.name.clone(),
+                expected: params,
+                found: args.len(),
+            }, name));
+        }
+
+        Ok((args, rparen))
+    }
+
+    /// Substitutes `args` into the replacement list of `m` invoked by `name`,
+    /// adding `hide_set` to every resulting token
+    fn substitute(&self, m: &Macro, name: &Token, args: &[Vec<Token>], hide_set: &HideSet) -> Result<Vec<Token>, PreprocessorError> {
+        let mut expanded = vec![None; args.len()];
+
+        let tokens = self.replace(m, name, &m.body, args, &mut expanded)?;
+
+        let mut output: Vec<Token> = tokens.into_iter().filter(|token| token.kind != TokenKind::Placemarker).collect();
+
+        for (i, token) in output.iter_mut().enumerate() {
+            token.leading_space = if i == 0 { name.leading_space } else { token.leading_space || token.line_start };
+            token.line_start = i == 0 && name.line_start;
+            token.offset = name.offset;
+            token.line = name.line;
+            token.hide_set.extend(hide_set.iter().cloned());
+        }
+
+        Ok(output)
+    }
+
+    /// Replaces parameters within `body` and applies the `#`, `##` and `__VA_OPT__` operators
+    ///
+    /// `expanded` caches the fully expanded arguments, which are only computed when used.
+    /// The result may contain placemarkers.
+    fn replace(&self, m: &Macro, name: &Token, body: &[Token], args: &[Vec<Token>], expanded: &mut Vec<Option<Vec<Token>>>) -> Result<Vec<Token>, PreprocessorError> {
+        let function_like = m.is_function_like();
+
+        let mut items = Vec::new();
+        let mut i = 0;
+
+        while i < body.len() {
+            let token = &body[i];
+
+            if is_paste(token) {
+                items.push(Item::Paste);
+                i += 1;
+                continue;
+            }
+
+            if function_like && is_stringize(token) {
+                let operand = &body[i + 1];
+
+                let string = if operand.is_identifier("__VA_OPT__") {
+                    let end = va_opt_end(body, i + 1).unwrap();
+                    let tokens = self.va_opt(m, name, &body[i + 3..end - 1], args, expanded)?;
+
+                    i = end;
+
+                    stringize(&tokens)
+                } else {
+                    i += 2;
+
+                    stringize(&args[m.param(operand).unwrap()])
+                };
+
+                items.push(Item::Token(Token { leading_space: token.leading_space, ..string }));
+                continue;
+            }
+
+            if function_like && m.is_variadic() && token.is_identifier("__VA_OPT__") {
+                let end = va_opt_end(body, i).unwrap();
+                let tokens = self.va_opt(m, name, &body[i + 2..end - 1], args, expanded)?;
+
+                push_group(&mut items, tokens, token);
+
+                i = end;
+                continue;
+            }
+
+            if let Some(param) = m.param(token) {
+                let pasted = match items.last() { Some(&Item::Paste) => true, _ => false } ||
+                    body.get(i + 1).map_or(false, is_paste);
+
+                // Operands of `##` are substituted without being expanded first
+                let tokens = if pasted {
+                    args[param].clone()
+                } else {
+                    self.expanded(param, args, expanded)?
+                };
+
+                if pasted {
+                    push_group(&mut items, tokens, token);
+                } else {
+                    for (j, mut arg) in tokens.into_iter().enumerate() {
+                        if j == 0 {
+                            arg.leading_space = token.leading_space;
+                            arg.line_start = false;
+                        }
+
+                        items.push(Item::Token(arg));
+                    }
+                }
+
+                i += 1;
+                continue;
+            }
+
+            items.push(Item::Token(token.clone()));
+            i += 1;
+        }
+
+        self.paste(items, name)
+    }
+
+    fn expanded(&self, param: usize, args: &[Vec<Token>], expanded: &mut Vec<Option<Vec<Token>>>) -> Result<Vec<Token>, PreprocessorError> {
+        if expanded[param].is_none() {
+            expanded[param] = Some(self.expand(args[param].clone())?);
+        }
+
+        Ok(expanded[param].clone().unwrap())
+    }
+
+    /// Replaces the contents of `__VA_OPT__(...)` if the variable arguments expand to any tokens
+    fn va_opt(&self, m: &Macro, name: &Token, content: &[Token], args: &[Vec<Token>], expanded: &mut Vec<Option<Vec<Token>>>) -> Result<Vec<Token>, PreprocessorError> {
+        let va = args.len() - 1;
+
+        if self.expanded(va, args, expanded)?.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tokens = self.replace(m, name, content, args, expanded)?;
+
+        Ok(tokens.into_iter().filter(|token| token.kind != TokenKind::Placemarker).collect())
+    }
+
+    /// Applies every `##` operator in `items`
+    fn paste(&self, items: Vec<Item>, name: &Token) -> Result<Vec<Token>, PreprocessorError> {
+        let mut output: Vec<Token> = Vec::new();
+        let mut items = items.into_iter();
+
+        while let Some(item) = items.next() {
+            match item {
+                Item::Token(token) => output.push(token),
+                Item::Paste => {
+                    let lhs = output.pop();
+                    let rhs = match items.next() {
+                        Some(Item::Token(token)) => Some(token),
+                        _ => None,
+                    };
+
+                    let (lhs, rhs) = match (lhs, rhs) {
+                        (Some(lhs), Some(rhs)) => (lhs, rhs),
+                        _ => return Err(self.error(ErrorKind::InvalidPasteOperand(name.text.clone()), name)),
+                    };
+
+                    output.push(if lhs.kind == TokenKind::Placemarker {
+                        Token { leading_space: lhs.leading_space, ..rhs }
+                    } else if rhs.kind == TokenKind::Placemarker {
+                        lhs
+                    } else {
+                        let mut token = match single_token(&format!("{}{}", lhs.text, rhs.text)) {
+                            Some(token) => token,
+                            None => return Err(self.error(ErrorKind::InvalidPaste(lhs.text, rhs.text), name)),
+                        };
+
+                        token.leading_space = lhs.leading_space;
+                        token.hide_set = lhs.hide_set.intersection(&rhs.hide_set).cloned().collect();
+                        token
+                    });
+                },
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// Adds an operand of `##`, which is a placemarker if it is empty
+fn push_group(items: &mut Vec<Item>, tokens: Vec<Token>, at: &Token) {
+    if tokens.is_empty() {
+        let mut placemarker = Token::new(TokenKind::Placemarker, "");
+
+        placemarker.leading_space = at.leading_space;
+
+        items.push(Item::Token(placemarker));
+    }
+
+    for (j, mut token) in tokens.into_iter().enumerate() {
+        if j == 0 {
+            token.leading_space = at.leading_space;
+            token.line_start = false;
+        }
+
+        items.push(Item::Token(token));
+    }
+}
+
+/// Spells `tokens` as a string literal, as done by the `#` operator
+pub fn stringize(tokens: &[Token]) -> Token {
+    let mut string = String::from("\"");
+
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && (token.leading_space || token.line_start) {
+            string.push(' ');
+        }
+
+        match token.kind {
+            TokenKind::StringLiteral | TokenKind::CharacterConstant => {
+                for c in token.text.chars() {
+                    if c == '"' || c == '\\' {
+                        string.push('\\');
+                    }
+
+                    string.push(c);
+                }
+            },
+            _ => string.push_str(&token.text),
+        }
+    }
+
+    string.push('"');
+
+    Token::new(TokenKind::StringLiteral, string)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ::preprocessor::token::tokenize;
+
+    fn table(definitions: &[&str]) -> MacroTable {
+        let mut macros = MacroTable::new();
+
+        for definition in definitions {
+            macros.define(Macro::parse(&tokenize(definition.as_bytes()).unwrap()).unwrap()).unwrap();
+        }
+
+        macros
+    }
+
+    fn expand(macros: &MacroTable, source: &str) -> Result<String, ErrorKind> {
+        let no_builtins = |_: &Token| None;
+
+        let tokens = Expander::new(macros, &no_builtins)
+            .expand(tokenize(source.as_bytes()).unwrap())
+            .map_err(|err| err.kind)?;
+
+        let mut output = String::new();
+
+        for token in tokens {
+            if token.leading_space && !output.is_empty() {
+                output.push(' ');
+            }
+
+            output.push_str(&token.text);
+        }
+
+        Ok(output)
+    }
+
+    #[test]
+    fn test_object_like() {
+        let macros = table(&["A B + 1", "B A * 2", "EMPTY", "SELF SELF"]);
+
+        assert_eq!(expand(&macros, "A; B; EMPTY x SELF"), Ok("A * 2 + 1; B + 1 * 2; x SELF".to_owned()));
+    }
+
+    #[test]
+    fn test_rescanning() {
+        // C11 6.10.3.5 EXAMPLE 3
+        let macros = table(&[
+            "x 2", "f(a) f(x * (a))", "z z[0]", "g f", "h g(~", "m(a) a(w)", "w 0,1",
+            "t(a) a", "p() int", "q(x) x", "r(x,y) x ## y", "str(x) # x",
+        ]);
+
+        assert_eq!(
+            expand(&macros, "f(y+1) + f(f(z)) % t(t(g)(0) + t)(1);"),
+            Ok("f(2 * (y+1)) + f(2 * (f(2 * (z[0])))) % f(2 * (0)) + t(1);".to_owned()),
+        );
+
+        assert_eq!(expand(&macros, "g(x+(3,4)-w) | h 5) & m\n(f)^m(m);"),
+            Ok("f(2 * (2+(3,4)-0,1)) | f(2 * (~ 5)) & f(2 * (0,1))^m(0,1);".to_owned()));
+
+        assert_eq!(expand(&macros, "p() i[q()] = { q(1), r(2,3), r(4,), r(,5), r(,) };"),
+            Ok("int i[] = { 1, 23, 4, 5, };".to_owned()));
+    }
+
+    #[test]
+    fn test_stringize_and_paste() {
+        // C11 6.10.3.5 EXAMPLE 4 and 5
+        let macros = table(&[
+            "str(s) # s", "xstr(s) str(s)",
+            "debug(s, t) printf(\"x\" # s \"= %d, x\" # t \"= %s\", x ## s, x ## t)",
+            "INCFILE(n) vers ## n", "glue(a, b) a ## b", "xglue(a, b) glue(a, b)",
+            "HIGHLOW \"hello\"", "LOW LOW \", world\"",
+            "t(x,y,z) x ## y ## z",
+        ]);
+
+        assert_eq!(expand(&macros, "debug(1, 2);"),
+            Ok("printf(\"x\" \"1\" \"= %d, x\" \"2\" \"= %s\", x1, x2);".to_owned()));
+
+        assert_eq!(expand(&macros, "xstr(INCFILE(2).h) glue(HIGH, LOW); xglue(HIGH, LOW)"),
+            Ok("\"vers2.h\" \"hello\"; \"hello\" \", world\"".to_owned()));
+
+        assert_eq!(expand(&macros, "str( strncmp(\"abc\\0d\", \"abc\", '\\4') == 0 )"),
+            Ok("\"strncmp(\\\"abc\\\\0d\\\", \\\"abc\\\", '\\\\4') == 0\"".to_owned()));
+
+        assert_eq!(expand(&macros, "t(1,2,3), t(,4,5), t(6,,7), t(8,9,), t(,,)"),
+            Ok("123, 45, 67, 89,".to_owned()));
+
+        assert_eq!(expand(&macros, "glue(+, -)"), Err(ErrorKind::InvalidPaste("+".to_owned(), "-".to_owned())));
+    }
+
+    #[test]
+    fn test_variadic() {
+        // C11 6.10.3.5 EXAMPLE 7 and C23 6.10.5.2
+        let macros = table(&[
+            "debug(...) fprintf(stderr, __VA_ARGS__)",
+            "showlist(...) puts(#__VA_ARGS__)",
+            "F(...) f(0 __VA_OPT__(,) __VA_ARGS__)",
+            "G(X, ...) f(0, X __VA_OPT__(,) __VA_ARGS__)",
+            "SDEF(sname, ...) S sname __VA_OPT__(= { __VA_ARGS__ })",
+            "EMP",
+        ]);
+
+        assert_eq!(expand(&macros, "debug(\"X = %d\\n\", x); showlist(The first, second, and third items.);"),
+            Ok("fprintf(stderr, \"X = %d\\n\", x); puts(\"The first, second, and third items.\");".to_owned()));
+
+        assert_eq!(expand(&macros, "F(a, b, c) F() F(EMP) G(a, b, c) G(a, ) G(a)"),
+            Ok("f(0 , a, b, c) f(0) f(0) f(0, a , b, c) f(0, a) f(0, a)".to_owned()));
+
+        assert_eq!(expand(&macros, "SDEF(foo); SDEF(bar, 1, 2);"), Ok("S foo; S bar = { 1, 2 };".to_owned()));
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let parse = |source: &str| Macro::parse(&tokenize(source.as_bytes()).unwrap()).map(|_| ());
+
+        assert_eq!(parse("A ## b"), Err(ErrorKind::InvalidPasteOperand("A".to_owned())));
+        assert_eq!(parse("f(x) #y"), Err(ErrorKind::InvalidStringize("f".to_owned())));
+        assert_eq!(parse("f(x, x) x"), Err(ErrorKind::InvalidMacroParameters("f".to_owned())));
+        assert_eq!(parse("f(x) __VA_OPT__(x)"), Err(ErrorKind::InvalidVaOpt("f".to_owned())));
+        assert_eq!(parse("1"), Err(ErrorKind::MissingMacroName));
+
+        let mut macros = table(&["A 1 + 2"]);
+
+        assert!(macros.define(Macro::parse(&tokenize(b"A 1  +  2").unwrap()).unwrap()).is_ok());
+        assert_eq!(macros.define(Macro::parse(&tokenize(b"A 1+2").unwrap()).unwrap()), Err(ErrorKind::MacroRedefinition("A".to_owned())));
+    }
+}
@@ -0,0 +1,14 @@
+//! The C preprocessor
+//!
+//! Source files are split into preprocessing tokens, directives are executed and macros
+//! are expanded, producing text for `parser::translation_unit::parse_translation_unit`.
+
+pub mod error;
+pub mod token;
+pub mod macros;
+pub mod condition;
+pub mod include;
+pub mod directive;
+
+pub use self::directive::Preprocessor;
+pub use self::error::PreprocessorError;
@@ -0,0 +1,371 @@
+//! Preprocessing tokens
+//!
+//! Translation phases 1 through 3: line splices are removed, comments are replaced by whitespace
+//! and the source is split into preprocessing tokens, each remembering where it came from.
+
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::error::{PreprocessorError, ErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Identifier,
+    /// A pp-number, which is any sequence of characters that could begin a numeric literal
+    Number,
+    CharacterConstant,
+    StringLiteral,
+    Punctuator,
+    /// Any other single non-whitespace character
+    Other,
+    /// Stands in for an empty macro argument while `##` is applied, and never leaves macro expansion
+    Placemarker,
+}
+
+/// Names of macros that may not be expanded from a token, since it resulted from their expansion
+pub type HideSet = BTreeSet<String>;
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// Whether the token was preceded by whitespace on the same line
+    pub leading_space: bool,
+    /// Whether the token is the first on its line
+    pub line_start: bool,
+    /// Byte offset of the token in the original source
+    pub offset: usize,
+    /// One-based line of the token in the original source
+    pub line: usize,
+    pub hide_set: HideSet,
+}
+
+impl Token {
+    pub fn new<S: Into<String>>(kind: TokenKind, text: S) -> Token {
+        Token {
+            kind: kind,
+            text: text.into(),
+            leading_space: false,
+            line_start: false,
+            offset: 0,
+            line: 0,
+            hide_set: HideSet::new(),
+        }
+    }
+
+    pub fn is_identifier(&self, name: &str) -> bool {
+        self.kind == TokenKind::Identifier && self.text == name
+    }
+
+    pub fn is_punctuator(&self, punct: &str) -> bool {
+        self.kind == TokenKind::Punctuator && self.text == punct
+    }
+
+    /// Returns true if both tokens are spelled the same with the same whitespace before them,
+    /// as required for macro redefinitions
+    pub fn same_as(&self, other: &Token) -> bool {
+        self.kind == other.kind && self.text == other.text && self.leading_space == other.leading_space
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.text)
+    }
+}
+
+/// Every punctuator, longest first within each starting character so the first match is the longest
+const PUNCTUATORS: &'static [&'static str] = &[
+    "%:%:", "...", "<<=", ">>=",
+    "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=", "/=", "%=", "+=", "-=",
+    "&=", "^=", "|=", "##", "<:", ":>", "<%", "%>", "%:", "::",
+    "[", "]", "(", ")", "{", "}", ".", "&", "*", "+", "-", "~", "!", "/", "%", "<", ">", "^", "|",
+    "?", ":", ";", "=", ",", "#",
+];
+
+fn is_identifier_start(c: u8) -> bool {
+    c == b'_' || c.is_ascii_alphabetic() || c >= 0x80
+}
+
+fn is_identifier_continue(c: u8) -> bool {
+    is_identifier_start(c) || c.is_ascii_digit()
+}
+
+/// Source text after line splicing, remembering the original offset of every byte
+struct Spliced {
+    text: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl Spliced {
+    fn new(source: &[u8]) -> Spliced {
+        let mut text = Vec::with_capacity(source.len());
+        let mut offsets = Vec::with_capacity(source.len());
+
+        let mut i = 0;
+
+        while i < source.len() {
+            if source[i] == b'\\' {
+                let newline = match source.get(i + 1) {
+                    Some(&b'\n') => Some(2),
+                    Some(&b'\r') if source.get(i + 2) == Some(&b'\n') => Some(3),
+                    _ => None,
+                };
+
+                if let Some(len) = newline {
+                    i += len;
+                    continue;
+                }
+            }
+
+            text.push(source[i]);
+            offsets.push(i);
+            i += 1;
+        }
+
+        offsets.push(source.len());
+
+        Spliced { text: text, offsets: offsets }
+    }
+}
+
+/// Splits `source` into preprocessing tokens
+///
+/// Comments become whitespace, so they separate tokens but never end a line.
+pub fn tokenize(source: &[u8]) -> Result<Vec<Token>, PreprocessorError> {
+    let spliced = Spliced::new(source);
+    let text = &spliced.text[..];
+
+    let line_starts: Vec<usize> = ::std::iter::once(0)
+        .chain(source.iter().enumerate().filter(|&(_, &c)| c == b'\n').map(|(i, _)| i + 1))
+        .collect();
+
+    let line_of = |offset: usize| match line_starts.binary_search(&offset) {
+        Ok(line) => line + 1,
+        Err(next) => next,
+    };
+
+    let mut tokens = Vec::new();
+
+    let mut leading_space = false;
+    let mut line_start = true;
+
+    let mut i = 0;
+
+    while i < text.len() {
+        let c = text[i];
+
+        if c == b'\n' {
+            line_start = true;
+            leading_space = false;
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_whitespace() {
+            leading_space = true;
+            i += 1;
+            continue;
+        }
+
+        if text[i..].starts_with(b"//") {
+            while i < text.len() && text[i] != b'\n' {
+                i += 1;
+            }
+
+            leading_space = true;
+            continue;
+        }
+
+        if text[i..].starts_with(b"/*") {
+            let start = i;
+
+            i += 2;
+
+            while i < text.len() && !text[i..].starts_with(b"*/") {
+                i += 1;
+            }
+
+            if i >= text.len() {
+                return Err(PreprocessorError::new(ErrorKind::UnterminatedComment, spliced.offsets[start], line_of(spliced.offsets[start])));
+            }
+
+            i += 2;
+            leading_space = true;
+            continue;
+        }
+
+        let (kind, len) = lex_one(&text[i..]);
+
+        let offset = spliced.offsets[i];
+
+        tokens.push(Token {
+            kind: kind,
+            text: String::from_utf8_lossy(&text[i..i + len]).into_owned(),
+            leading_space: leading_space,
+            line_start: line_start,
+            offset: offset,
+            line: line_of(offset),
+            hide_set: HideSet::new(),
+        });
+
+        leading_space = false;
+        line_start = false;
+
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+/// Lexes the single token at the start of `text`, which must not begin with whitespace or a comment
+fn lex_one(text: &[u8]) -> (TokenKind, usize) {
+    let c = text[0];
+
+    if is_identifier_start(c) {
+        let len = text.iter().take_while(|&&c| is_identifier_continue(c)).count();
+
+        // Encoding prefixes of character constants and string literals
+        match (&text[..len], text.get(len)) {
+            (b"L", Some(&q)) | (b"u", Some(&q)) | (b"U", Some(&q)) | (b"u8", Some(&q)) if q == b'"' || q == b'\'' => {
+                let (kind, quoted) = lex_quoted(&text[len..]);
+
+                return (kind, len + quoted);
+            },
+            _ => return (TokenKind::Identifier, len),
+        }
+    }
+
+    if c.is_ascii_digit() || (c == b'.' && text.get(1).map_or(false, |c| c.is_ascii_digit())) {
+        let mut len = 1;
+
+        while len < text.len() {
+            match text[len] {
+                b'+' | b'-' if b"eEpP".contains(&text[len - 1]) => len += 1,
+                b'\'' if text.get(len + 1).map_or(false, |&c| is_identifier_continue(c)) => len += 2,
+                c if c == b'.' || is_identifier_continue(c) => len += 1,
+                _ => break,
+            }
+        }
+
+        return (TokenKind::Number, len);
+    }
+
+    if c == b'"' || c == b'\'' {
+        return lex_quoted(text);
+    }
+
+    for punct in PUNCTUATORS {
+        if text.starts_with(punct.as_bytes()) {
+            return (TokenKind::Punctuator, punct.len());
+        }
+    }
+
+    (TokenKind::Other, 1)
+}
+
+/// Lexes a character constant or string literal, which ends at the closing quote or the end of the line
+///
+/// An unterminated literal is not an error here, since it may appear in a skipped group.
+fn lex_quoted(text: &[u8]) -> (TokenKind, usize) {
+    let quote = text[0];
+
+    let mut len = 1;
+
+    while len < text.len() && text[len] != quote && text[len] != b'\n' {
+        if text[len] == b'\\' && len + 1 < text.len() && text[len + 1] != b'\n' {
+            len += 1;
+        }
+
+        len += 1;
+    }
+
+    if len < text.len() && text[len] == quote {
+        len += 1;
+    }
+
+    (if quote == b'"' { TokenKind::StringLiteral } else { TokenKind::CharacterConstant }, len)
+}
+
+/// Lexes `text` as exactly one token, as needed for the result of `##`
+pub fn single_token(text: &str) -> Option<Token> {
+    let bytes = text.as_bytes();
+
+    if bytes.is_empty() || bytes[0].is_ascii_whitespace() || bytes.starts_with(b"//") || bytes.starts_with(b"/*") {
+        return None;
+    }
+
+    match lex_one(bytes) {
+        (kind, len) if len == bytes.len() => Some(Token::new(kind, text)),
+        _ => None,
+    }
+}
+
+/// Returns true if `next` must be separated from `prev` by whitespace
+/// so that they are not read back as a different sequence of tokens
+pub fn needs_space(prev: &Token, next: &Token) -> bool {
+    let joined = format!("{}{}", prev.text, next.text);
+    let bytes = joined.as_bytes();
+
+    if bytes.starts_with(b"//") || bytes.starts_with(b"/*") {
+        return true;
+    }
+
+    lex_one(bytes).1 != prev.text.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts(source: &[u8]) -> Vec<(TokenKind, String)> {
+        tokenize(source).unwrap().into_iter().map(|token| (token.kind, token.text)).collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use self::TokenKind::*;
+
+        assert_eq!(texts(b"a+++b 0x1p-3.f 1'000 u8\"s\\\"\" L'x' %:%: @"), vec![
+            (Identifier, "a".to_owned()),
+            (Punctuator, "++".to_owned()),
+            (Punctuator, "+".to_owned()),
+            (Identifier, "b".to_owned()),
+            (Number, "0x1p-3.f".to_owned()),
+            (Number, "1'000".to_owned()),
+            (StringLiteral, "u8\"s\\\"\"".to_owned()),
+            (CharacterConstant, "L'x'".to_owned()),
+            (Punctuator, "%:%:".to_owned()),
+            (Other, "@".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_lines() {
+        let tokens = tokenize(b"#define A \\\n  1 /* two\nlines */ 2 // end\nx").unwrap();
+
+        let layout: Vec<_> = tokens.iter().map(|token| (&token.text[..], token.line, token.line_start, token.leading_space)).collect();
+
+        assert_eq!(layout, vec![
+            ("#", 1, true, false),
+            ("define", 1, false, false),
+            ("A", 1, false, true),
+            ("1", 2, false, true),
+            ("2", 3, false, true),
+            ("x", 4, true, false),
+        ]);
+
+        assert_eq!(tokenize(b"a /* b").unwrap_err().kind, ErrorKind::UnterminatedComment);
+    }
+
+    #[test]
+    fn test_needs_space() {
+        let plus = Token::new(TokenKind::Punctuator, "+");
+
+        assert!(needs_space(&plus, &plus));
+        assert!(needs_space(&Token::new(TokenKind::Identifier, "a"), &Token::new(TokenKind::Number, "1")));
+        assert!(!needs_space(&Token::new(TokenKind::Identifier, "a"), &plus));
+        assert_eq!(single_token("<<=").map(|token| token.kind), Some(TokenKind::Punctuator));
+        assert!(single_token("+-").is_none());
+    }
+}